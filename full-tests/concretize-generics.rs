@@ -0,0 +1,32 @@
+// ~MINIMIZE-ARGS --passes concretize-generics,item-deleter
+/// ~REQUIRE-DELETED trait-Bound
+trait Bound {}
+
+/// ~REQUIRE-DELETED impl-Bound
+impl Bound for u32 {}
+
+trait Shown {
+    fn show(&self) {}
+}
+
+impl Shown for u8 {}
+impl Shown for u32 {}
+
+/// ~MINIMIZE-ROOT concrete
+fn concrete<T: Bound>(value: T) -> T {
+    value
+}
+
+/// ~MINIMIZE-ROOT shown
+fn shown<T: Shown>(value: T) {
+    value.show();
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    let byte: u8 = 1;
+    concrete(1u32);
+    // `shown` looks like it's only called with `u32`, but `byte` needs the type parameter.
+    shown(1u32);
+    shown(byte);
+}
@@ -0,0 +1,16 @@
+// ~MINIMIZE-ARGS --passes delete-blanket-impls
+trait Unused {}
+
+/// ~REQUIRE-DELETED impl-Unused
+impl<T> Unused for T {}
+
+trait Shown {
+    fn show(&self) {}
+}
+
+impl<T: Copy> Shown for T {}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    1u8.show();
+}
@@ -0,0 +1,19 @@
+// ~MINIMIZE-ARGS --passes delete-impl-items
+
+struct S;
+
+impl S {
+    /// ~MINIMIZE-ROOT used
+    fn used(&self) {}
+
+    fn unused(&self) {
+        "~REQUIRE-DELETED unused";
+    }
+
+    const UNUSED: &'static str = "~REQUIRE-DELETED UNUSED";
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    S.used();
+}
@@ -0,0 +1,22 @@
+// ~MINIMIZE-ARGS --passes delete-params
+
+/// ~MINIMIZE-ROOT function
+fn function(used: u8, unused: &str) -> u8 {
+    used
+}
+
+struct S;
+
+impl S {
+    /// ~MINIMIZE-ROOT method
+    fn method(&self, unused: &str, used: u8) -> u8 {
+        used
+    }
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    function(1, "~REQUIRE-DELETED function-arg");
+    S.method("~REQUIRE-DELETED method-arg", 2);
+    S::method(&S, "~REQUIRE-DELETED path-method-arg", 3);
+}
@@ -0,0 +1,20 @@
+// ~MINIMIZE-ARGS --passes delete-phantom-fields
+use std::marker::PhantomData;
+
+/// ~MINIMIZE-ROOT Unneeded
+struct Unneeded<T> {
+    /// ~REQUIRE-DELETED field-unneeded
+    marker: PhantomData<T>,
+}
+
+/// ~MINIMIZE-ROOT Kept
+struct Kept<T> {
+    marker: PhantomData<T>,
+}
+
+// The parameter of `Kept` is passed here, so its field can't be deleted.
+/// ~MINIMIZE-ROOT takes_kept
+fn takes_kept(_: Kept<u8>) {}
+
+/// ~MINIMIZE-ROOT main
+fn main() {}
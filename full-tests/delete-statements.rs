@@ -0,0 +1,12 @@
+// ~MINIMIZE-ARGS --passes delete-statements
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    let mut list = Vec::new();
+    "~REQUIRE-DELETED first";
+    list.push("~MINIMIZE-ROOT pushed");
+    let _unused = "~REQUIRE-DELETED second";
+    if list.is_empty() {
+        "~REQUIRE-DELETED third";
+    }
+}
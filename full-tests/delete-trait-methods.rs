@@ -0,0 +1,32 @@
+// ~MINIMIZE-ARGS --passes delete-trait-methods
+
+/// ~MINIMIZE-ROOT Trait
+trait Trait {
+    /// ~MINIMIZE-ROOT used
+    fn used(&self);
+
+    /// ~REQUIRE-DELETED unused
+    fn unused(&self);
+}
+
+impl Trait for u8 {
+    fn used(&self) {}
+
+    fn unused(&self) {
+        "~REQUIRE-DELETED unused-u8";
+    }
+}
+
+impl Trait for u16 {
+    fn used(&self) {}
+
+    fn unused(&self) {
+        "~REQUIRE-DELETED unused-u16";
+    }
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    0u8.used();
+    0u16.used();
+}
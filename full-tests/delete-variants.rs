@@ -0,0 +1,19 @@
+// ~MINIMIZE-ARGS --passes everybody-loops,delete-variants
+/// ~MINIMIZE-ROOT Enum
+enum Enum {
+    /// ~MINIMIZE-ROOT variant-A
+    A,
+    /// ~REQUIRE-DELETED variant-B
+    B(u8),
+    /// ~REQUIRE-DELETED variant-C
+    C { field: () },
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    match Enum::A {
+        Enum::A => {}
+        Enum::B(_) => {}
+        Enum::C { .. } => {}
+    }
+}
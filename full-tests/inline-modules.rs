@@ -0,0 +1,26 @@
+// ~MINIMIZE-ARGS --passes inline-modules
+/// ~REQUIRE-DELETED mod-inner
+mod inner {
+    /// ~MINIMIZE-ROOT function
+    pub fn function() {}
+
+    /// ~REQUIRE-DELETED mod-nested
+    pub mod nested {
+        use super::*;
+
+        /// ~MINIMIZE-ROOT Nested
+        pub struct Nested(pub fn());
+
+        /// ~MINIMIZE-ROOT NESTED
+        pub static NESTED: Nested = Nested(function);
+    }
+}
+
+/// ~MINIMIZE-ROOT FUNCTION
+static FUNCTION: fn() = inner::function;
+
+/// ~MINIMIZE-ROOT NESTED_FUNCTION
+static NESTED_FUNCTION: &crate::inner::nested::Nested = &inner::nested::NESTED;
+
+/// ~MINIMIZE-ROOT main
+fn main() {}
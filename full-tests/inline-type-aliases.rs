@@ -0,0 +1,12 @@
+// ~MINIMIZE-ARGS --passes inline-type-aliases
+/// ~REQUIRE-DELETED alias-Inlined
+type Inlined = u8;
+
+// Macro arguments aren't visited, so this use can't be replaced and the alias has to stay.
+type InMacro = u16;
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    let _: Inlined = 1;
+    println!("{}", InMacro::MAX);
+}
@@ -0,0 +1,17 @@
+// ~MINIMIZE-ARGS --passes privatize
+
+/// ~MINIMIZE-ROOT Unused
+pub struct Unused {
+    /// ~MINIMIZE-ROOT field
+    pub field: (),
+}
+
+/// ~MINIMIZE-ROOT unused
+pub fn unused() {
+    "~MINIMIZE-ROOT unused-body";
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    "~MINIMIZE-ROOT main-body";
+}
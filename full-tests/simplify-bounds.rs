@@ -0,0 +1,20 @@
+// ~MINIMIZE-ARGS --passes simplify-bounds,item-deleter
+use std::borrow::Cow;
+
+/// ~REQUIRE-DELETED trait-Unneeded
+trait Unneeded {}
+impl<T> Unneeded for T {}
+
+/// ~REQUIRE-DELETED trait-AlsoUnneeded
+trait AlsoUnneeded {}
+impl<T> AlsoUnneeded for T {}
+
+/// ~MINIMIZE-ROOT takes_cow
+fn takes_cow<T: Clone + Unneeded>(_: Cow<'static, T>)
+where
+    T: AlsoUnneeded,
+{
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {}
@@ -0,0 +1,7 @@
+/// ~REQUIRE-DELETED unused
+fn unused() {}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    unused();
+}
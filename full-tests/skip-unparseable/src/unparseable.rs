@@ -0,0 +1,4 @@
+// This file is not part of the crate and uses syntax that syn doesn't know about.
+fn yeet() -> Result<(), ()> {
+    do yeet ();
+}
@@ -0,0 +1,25 @@
+// ~MINIMIZE-ARGS --passes strip-attributes
+
+/// ~MINIMIZE-ROOT Layout
+#[repr(C)]
+struct Layout {
+    a: u8,
+    b: u32,
+    c: u8,
+}
+
+/// ~MINIMIZE-ROOT LAYOUT
+const LAYOUT: () = assert!(std::mem::size_of::<Layout>() == 12);
+
+/// ~MINIMIZE-ROOT function
+#[inline]
+#[must_use = "~REQUIRE-DELETED must-use"]
+fn function() -> u8 {
+    0
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    let _ = function();
+    let _ = LAYOUT;
+}
@@ -0,0 +1,20 @@
+// ~MINIMIZE-ARGS --passes strip-cfg
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    "~MINIMIZE-ROOT main-body";
+    enabled();
+}
+
+/// ~MINIMIZE-ROOT enabled
+#[cfg(not(test))]
+fn enabled() {}
+
+#[cfg(test)]
+mod tests {
+    /// ~REQUIRE-DELETED test
+    #[test]
+    fn test() {
+        "~REQUIRE-DELETED test-body";
+    }
+}
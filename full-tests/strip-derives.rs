@@ -0,0 +1,17 @@
+// ~MINIMIZE-ARGS --passes strip-derives,item-deleter
+/// ~MINIMIZE-ROOT Root
+#[derive(Debug, Clone, PartialEq)]
+struct Root;
+
+/// ~MINIMIZE-ROOT Wrapper
+struct Wrapper<T: std::fmt::Debug>(T);
+
+/// ~MINIMIZE-ROOT ROOT
+static ROOT: Wrapper<Root> = Wrapper(Root);
+
+/// ~REQUIRE-DELETED Other
+#[derive(Debug, Clone)]
+struct Other;
+
+/// ~MINIMIZE-ROOT main
+fn main() {}
@@ -0,0 +1,18 @@
+// ~MINIMIZE-ARGS --passes delete-statements,remove-unused-imports
+/// ~MINIMIZE-ROOT import-hashmap
+use std::collections::HashMap;
+/// ~REQUIRE-DELETED import-hashset
+use std::collections::HashSet;
+/// ~REQUIRE-DELETED import-btreemap
+pub use std::collections::BTreeMap;
+
+mod inner {
+    /// ~REQUIRE-DELETED import-inner-fmt
+    pub use std::fmt::Write as _;
+}
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    let _: HashMap<(), ()> = HashMap::new();
+    let _: HashSet<()> = HashSet::new();
+}
@@ -0,0 +1,7 @@
+// ~MINIMIZE-ARGS --workspace
+
+/// ~MINIMIZE-ROOT main
+fn main() {
+    "~MINIMIZE-ROOT main-body";
+    library::used();
+}
@@ -0,0 +1,9 @@
+/// ~MINIMIZE-ROOT used
+pub fn used() {
+    "~MINIMIZE-ROOT used-body";
+}
+
+/// ~REQUIRE-DELETED unused
+pub fn unused() {
+    "~REQUIRE-DELETED unused-body";
+}
@@ -1,16 +1,29 @@
 use anyhow::{bail, ensure, Context, Result};
+use regex::Regex;
 use rustfix::diagnostics::Diagnostic;
 use serde::Deserialize;
 use std::{
-    collections::HashSet,
+    cell::{Cell, RefCell},
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
     ffi::OsStr,
     fmt::{Debug, Display},
-    path::PathBuf,
-    process::Command,
+    hash::{Hash, Hasher},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Output, Stdio},
     rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::{dylib_flag::RustFunction, EnvVar, Options};
+use crate::{
+    dylib_flag::RustFunction, edition::Edition, predicate::BuildOutput, processor::SourceFile,
+    EnvVar, ExitCodes, Options, Predicate, Sources,
+};
 
 #[derive(Debug, Clone)]
 pub struct Build {
@@ -20,6 +33,8 @@ pub struct Build {
 pub enum Verify {
     Ice,
     Custom(RustFunction),
+    Regex(Regex),
+    Predicate(Predicate),
     None,
 }
 
@@ -28,6 +43,8 @@ impl Debug for Verify {
         match self {
             Self::Ice => write!(f, "Ice"),
             Self::Custom(_) => f.debug_tuple("Custom").finish(),
+            Self::Regex(regex) => f.debug_tuple("Regex").field(&regex.as_str()).finish(),
+            Self::Predicate(predicate) => f.debug_tuple("Predicate").field(predicate).finish(),
             Self::None => write!(f, "None"),
         }
     }
@@ -43,6 +60,53 @@ struct BuildInner {
     allow_color: bool,
     project_dir: Option<PathBuf>,
     extra_args: Vec<String>,
+    timeout: Option<Duration>,
+    timeout_reproduces: bool,
+    verify_loop: u32,
+    reproduce_exit_codes: Option<ExitCodes>,
+    keep_going_on_build_error: bool,
+    max_builds: Option<u64>,
+    /// How many builds have run, without the ones that came from the cache.
+    builds: Cell<u64>,
+    /// Copies of the project directory for `build_parallel`.
+    scratch_dirs: RefCell<Vec<tempfile::TempDir>>,
+    /// Bisection often goes back to states that we have already built.
+    cache: RefCell<BuildCache>,
+    /// Running builds are killed when this is set, see `cancel_on`.
+    cancel: RefCell<Option<Arc<AtomicBool>>>,
+}
+
+/// How often a build that failed to run is retried with `--keep-going-on-build-error`.
+const BUILD_RETRIES: u32 = 3;
+/// The delay before the first retry, it doubles with every retry.
+const BUILD_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// How many build results are cached, the oldest ones are dropped first.
+const MAX_CACHED_BUILDS: usize = 10_000;
+/// How much of the end of stderr is cached with a build result.
+const CACHED_STDERR_BYTES: usize = 4096;
+
+/// Lines in the output of cargo that show that it failed before building anything.
+const CARGO_INFRASTRUCTURE_ERRORS: &[&str] = &[
+    "failed to load manifest",
+    "failed to parse lock file",
+    "failed to download",
+    "failed to get `",
+    "failed to select a version",
+    "could not execute process",
+];
+
+/// Why a build was killed before it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interruption {
+    TimedOut,
+    Cancelled,
+}
+
+/// Where a build runs. Parallel builds run in copies of the project directory.
+struct BuildDir {
+    project_dir: Option<PathBuf>,
+    input_path: PathBuf,
 }
 
 #[derive(Debug)]
@@ -54,6 +118,36 @@ enum BuildMode {
     },
     Script(PathBuf),
     Rustc(PathBuf),
+    Fn(ReproducesFn),
+}
+
+/// Decides whether the current state of the project reproduces the issue.
+#[derive(Clone)]
+pub(crate) enum ReproducesFn {
+    /// Gets the sources, keyed by their path relative to the project directory.
+    Sources(Rc<dyn Fn(&Sources) -> bool>),
+    /// Gets the project directory, with the sources written to it.
+    Path(Rc<dyn Fn(&Path) -> bool>),
+}
+
+impl ReproducesFn {
+    pub(crate) fn new(reproduces: impl Fn(&Sources) -> bool + 'static) -> Self {
+        Self::Sources(Rc::new(reproduces))
+    }
+
+    pub(crate) fn on_path(reproduces: Box<dyn Fn(&Path) -> bool>) -> Self {
+        Self::Path(Rc::from(reproduces))
+    }
+
+    fn none() -> Self {
+        Self::new(|_| false)
+    }
+}
+
+impl Debug for ReproducesFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ReproducesFn").finish()
+    }
 }
 
 impl Build {
@@ -61,12 +155,12 @@ impl Build {
         if options.rustc && options.cargo_subcmd != "build" {
             bail!("Cannot specify --rustc together with --cargo-subcmd or --cargo-args");
         }
-
-        let extra_args = options
-            .extra_args
-            .as_deref()
-            .map(split_args)
-            .unwrap_or_default();
+        if options.rustc && options.path.is_dir() {
+            bail!(
+                "--rustc compiles a single file, but {} is a directory",
+                options.path.display()
+            );
+        }
 
         let mode = if options.rustc {
             let rustc = rustup_which("rustc")?;
@@ -74,7 +168,13 @@ impl Build {
         } else if let Some(script) = &options.script_path {
             BuildMode::Script(script.clone())
         } else {
-            let subcommand = split_args(&options.cargo_subcmd);
+            let mut subcommand = split_args(&options.cargo_subcmd);
+            if options.workspace {
+                subcommand.push("--workspace".to_owned());
+            }
+            if let Some(target) = &options.cargo_target {
+                subcommand.extend(target.args());
+            }
             let cargo_path = rustup_which("cargo")?;
             BuildMode::Cargo {
                 cargo_path,
@@ -92,11 +192,17 @@ impl Build {
         {
             BuildMode::Script(script.clone())
         } else {
-            let subcommand = options
+            let mut subcommand = options
                 .cargo_subcmd_lints
                 .as_deref()
                 .map(split_args)
                 .unwrap_or_else(|| split_args(&options.cargo_subcmd));
+            if options.workspace {
+                subcommand.push("--workspace".to_owned());
+            }
+            if let Some(target) = &options.cargo_target {
+                subcommand.extend(target.args());
+            }
             let cargo_path = rustup_which("cargo")?;
 
             BuildMode::Cargo {
@@ -105,15 +211,49 @@ impl Build {
             }
         };
 
+        if options.verify_fn.is_some() && options.regex.is_some() {
+            bail!("Cannot specify --verify-fn together with --regex");
+        }
+        if options.reproduces.is_some() && (options.verify_fn.is_some() || options.regex.is_some())
+        {
+            bail!("Cannot specify --reproduces together with --verify-fn or --regex");
+        }
+
         let verify = if options.no_verify {
             Verify::None
         } else if let Some(func) = options.verify_fn {
             Verify::Custom(func)
+        } else if let Some(regex) = &options.regex {
+            Verify::Regex(regex.clone())
+        } else if let Some(predicate) = &options.reproduces {
+            Verify::Predicate(predicate.clone())
         } else {
             Verify::Ice
         };
 
-        Ok(Self {
+        Ok(Self::with_modes(options, mode, lint_mode, verify))
+    }
+
+    /// A build that doesn't run anything, but asks the function whether the current sources reproduce the issue.
+    /// A function that gets a path is called with the project directory, or a copy of it for parallel builds.
+    /// There are no diagnostics, so `delete-unused-functions` doesn't find anything.
+    pub(crate) fn from_fn(options: &Options, reproduces: ReproducesFn) -> Self {
+        let mode = BuildMode::Fn(reproduces);
+        Self::with_modes(
+            options,
+            mode,
+            BuildMode::Fn(ReproducesFn::none()),
+            Verify::Ice,
+        )
+    }
+
+    fn with_modes(
+        options: &Options,
+        mode: BuildMode,
+        lint_mode: BuildMode,
+        verify: Verify,
+    ) -> Self {
+        Self {
             inner: Rc::new(BuildInner {
                 mode,
                 lint_mode,
@@ -122,122 +262,709 @@ impl Build {
                 env: options.env.clone(),
                 allow_color: !options.no_color,
                 project_dir: options.project_dir.clone(),
-                extra_args,
+                extra_args: options
+                    .extra_args
+                    .as_deref()
+                    .map(split_args)
+                    .unwrap_or_default(),
+                timeout: options.timeout.map(Duration::from_secs),
+                timeout_reproduces: options.timeout_reproduces,
+                verify_loop: options.verify_loop,
+                reproduce_exit_codes: options.reproduce_exit_codes.clone(),
+                keep_going_on_build_error: options.keep_going_on_build_error,
+                max_builds: options.max_builds,
+                builds: Cell::new(0),
+                scratch_dirs: RefCell::new(Vec::new()),
+                cache: RefCell::new(BuildCache::new(MAX_CACHED_BUILDS)),
+                cancel: RefCell::new(None),
             }),
-        })
+        }
+    }
+
+    /// Kills running builds as soon as `cancel` is set. Builds that were cancelled don't reproduce the
+    /// issue, so the change that is being built is rolled back.
+    pub(crate) fn cancel_on(&self, cancel: Arc<AtomicBool>) {
+        *self.inner.cancel.borrow_mut() = Some(cancel);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.inner
+            .cancel
+            .borrow()
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::SeqCst))
     }
 
     fn cmd(&self, name: impl AsRef<OsStr>) -> Command {
+        self.cmd_in(name, self.inner.project_dir.as_deref())
+    }
+
+    fn cmd_in(&self, name: impl AsRef<OsStr>, dir: Option<&Path>) -> Command {
         let mut cmd = Command::new(name);
-        if let Some(path) = &self.inner.project_dir {
-            cmd.current_dir(path);
+        if let Some(dir) = dir {
+            cmd.current_dir(dir);
         }
         cmd
     }
 
-    pub fn build(&self) -> Result<BuildResult> {
-        let inner = &self.inner;
+    /// Runs the command to completion, killing it if it takes longer than the timeout or the build is
+    /// cancelled. Returns whether it was killed, in which case the output is whatever was collected until then.
+    fn output(&self, cmd: &mut Command) -> io::Result<(Output, Option<Interruption>)> {
+        let mut outputs = self.outputs(std::slice::from_mut(cmd))?;
+        Ok(outputs.remove(0))
+    }
 
-        if let Verify::None = inner.verify {
-            return Ok(BuildResult {
-                reproduces_issue: false,
-                no_verify: true,
-                output: String::new(),
-                allow_color: inner.allow_color,
-            });
+    /// Like `output`, but runs all commands at the same time.
+    fn outputs(&self, cmds: &mut [Command]) -> io::Result<Vec<(Output, Option<Interruption>)>> {
+        fn read_all(
+            mut pipe: impl Read + Send + 'static,
+        ) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                pipe.read_to_end(&mut buf)?;
+                Ok(buf)
+            })
         }
 
-        let (is_ice, cmd_status, output) = match &inner.mode {
-            BuildMode::Cargo {
-                cargo_path,
-                subcommand,
-            } => {
-                let mut cmd = self.cmd(cargo_path);
+        let start = Instant::now();
+        let children = cmds
+            .iter_mut()
+            .map(|cmd| {
+                cmd.stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped());
+                // Put it into its own process group so that we can kill everything it spawned, like the
+                // binary run by `cargo run`.
+                #[cfg(unix)]
+                if self.inner.timeout.is_some() || self.inner.cancel.borrow().is_some() {
+                    std::os::unix::process::CommandExt::process_group(cmd, 0);
+                }
 
-                cmd.args(subcommand);
+                let mut child = cmd.spawn()?;
+                let stdout = read_all(child.stdout.take().expect("stdout is piped"));
+                let stderr = read_all(child.stderr.take().expect("stderr is piped"));
+                Ok((child, stdout, stderr))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        children
+            .into_iter()
+            .map(|(mut child, stdout, stderr)| {
+                let (status, interruption) = loop {
+                    if let Some(status) = child.try_wait()? {
+                        break (status, None);
+                    }
+                    let interruption = if self.is_cancelled() {
+                        Some(Interruption::Cancelled)
+                    } else if self
+                        .inner
+                        .timeout
+                        .is_some_and(|timeout| start.elapsed() > timeout)
+                    {
+                        Some(Interruption::TimedOut)
+                    } else {
+                        None
+                    };
+                    if interruption.is_some() {
+                        kill(&mut child)?;
+                        break (child.wait()?, interruption);
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                };
+
+                let output = Output {
+                    status,
+                    stdout: stdout.join().expect("reader thread panicked")?,
+                    stderr: stderr.join().expect("reader thread panicked")?,
+                };
+                Ok((output, interruption))
+            })
+            .collect()
+    }
 
-                if inner.allow_color {
-                    cmd.arg("--color=always");
-                }
+    /// Builds the project, or returns the previous result if the files are in a state we have seen before.
+    pub fn build(&self, files: &[SourceFile]) -> Result<BuildResult> {
+        let key = self.cache_key(files, &[]);
 
-                extra_cargoflags(&mut cmd);
+        if let Some(result) = self.inner.cache.borrow().get(key) {
+            debug!("Using cached build result");
+            return Ok(result);
+        }
 
-                cmd.args(&inner.extra_args);
+        let mut result = self.build_uncached(files)?;
+        for run in 2..=self.inner.verify_loop {
+            if !result.reproduces_issue() {
+                break;
+            }
+            debug!(
+                "Reproduced, checking again ({run}/{})",
+                self.inner.verify_loop
+            );
+            result = self.build_uncached(files)?;
+        }
 
-                for env in &inner.env {
-                    cmd.env(&env.key, &env.value);
-                }
+        self.inner.cache.borrow_mut().insert(key, &result);
+        Ok(result)
+    }
 
-                let outputs = cmd.output().context("spawning rustc process")?;
+    /// Builds every candidate at the same time, each in its own copy of the project directory. A candidate
+    /// is a list of files with the content to build them with, the other files are built as they are. The
+    /// results are cached like for `build`.
+    pub fn build_parallel(
+        &self,
+        files: &[SourceFile],
+        candidates: &[Vec<(&SourceFile, &str)>],
+    ) -> Result<Vec<BuildResult>> {
+        let keys = candidates
+            .iter()
+            .map(|replaced| self.cache_key(files, replaced))
+            .collect::<Vec<_>>();
+        let mut results = {
+            let cache = self.inner.cache.borrow();
+            keys.iter().map(|&key| cache.get(key)).collect::<Vec<_>>()
+        };
 
-                let output = String::from_utf8(outputs.stderr)?;
+        let mut missing = (0..candidates.len())
+            .filter(|&index| results[index].is_none())
+            .collect::<Vec<_>>();
+        for run in 1..=self.inner.verify_loop {
+            if missing.is_empty() {
+                break;
+            }
+            if run > 1 {
+                debug!(
+                    "Reproduced, checking again ({run}/{})",
+                    self.inner.verify_loop
+                );
+            }
+            let missing_candidates = missing
+                .iter()
+                .map(|&index| candidates[index].as_slice())
+                .collect::<Vec<_>>();
+            let built = self.build_many_uncached(files, &missing_candidates)?;
+            for (&index, result) in missing.iter().zip(built) {
+                results[index] = Some(result);
+            }
+            missing.retain(|&index| results[index].as_ref().unwrap().reproduces_issue());
+        }
 
-                (
-                    // Cargo always exits with 101 when rustc has an error.
-                    output.contains("internal compiler error") || output.contains("' panicked at"),
-                    outputs.status,
-                    output,
+        let mut cache = self.inner.cache.borrow_mut();
+        Ok(keys
+            .into_iter()
+            .zip(results)
+            .map(|(key, result)| {
+                let result = result.expect("every content was built");
+                cache.insert(key, &result);
+                result
+            })
+            .collect())
+    }
+
+    /// The key for the build cache, the hash of the build configuration and all source files. `replaced` are
+    /// files that are hashed with the given content instead of their current content.
+    fn cache_key(&self, files: &[SourceFile], replaced: &[(&SourceFile, &str)]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.inner.hash_config(&mut hasher);
+        for file in files {
+            match replaced_content(replaced, file) {
+                Some(content) => file.hash_with_content(content, &mut hasher),
+                None => file.hash_content(&mut hasher),
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Whether all builds of `--max-builds` have run.
+    pub(crate) fn out_of_builds(&self) -> bool {
+        self.inner
+            .max_builds
+            .is_some_and(|max| self.inner.builds.get() >= max)
+    }
+
+    fn build_uncached(&self, files: &[SourceFile]) -> Result<BuildResult> {
+        let inner = &self.inner;
+        inner.builds.set(inner.builds.get() + 1);
+
+        if let BuildMode::Fn(reproduces) = &inner.mode {
+            let reproduces_issue = match reproduces {
+                ReproducesFn::Sources(reproduces) => reproduces(&self.sources(files, &[])),
+                ReproducesFn::Path(reproduces) => reproduces(&self.project_root()?),
+            };
+            return Ok(self.fn_result(reproduces_issue));
+        }
+
+        if let Verify::None = inner.verify {
+            return Ok(self.no_verify_result());
+        }
+
+        let dir = BuildDir {
+            project_dir: inner.project_dir.clone(),
+            input_path: inner.input_path.clone(),
+        };
+        Ok(self.run_builds(&[dir])?.remove(0))
+    }
+
+    fn build_many_uncached(
+        &self,
+        files: &[SourceFile],
+        candidates: &[&[(&SourceFile, &str)]],
+    ) -> Result<Vec<BuildResult>> {
+        let inner = &self.inner;
+        inner
+            .builds
+            .set(inner.builds.get() + candidates.len() as u64);
+
+        if let BuildMode::Fn(ReproducesFn::Sources(reproduces)) = &inner.mode {
+            return Ok(candidates
+                .iter()
+                .map(|replaced| self.fn_result(reproduces(&self.sources(files, replaced))))
+                .collect());
+        }
+        if let BuildMode::Fn(ReproducesFn::Path(reproduces)) = &inner.mode {
+            return self
+                .scratch_dirs(candidates.len())?
+                .into_iter()
+                .zip(candidates)
+                .map(|(dir, replaced)| {
+                    self.sync_scratch_dir(&dir, files, replaced)?;
+                    Ok(self.fn_result(reproduces(&dir)))
+                })
+                .collect();
+        }
+
+        if let Verify::None = inner.verify {
+            return Ok(candidates.iter().map(|_| self.no_verify_result()).collect());
+        }
+
+        let input_path = match inner.mode {
+            BuildMode::Rustc(_) => self.relative(&inner.input_path)?,
+            _ => inner.input_path.clone(),
+        };
+        let dirs = self
+            .scratch_dirs(candidates.len())?
+            .into_iter()
+            .zip(candidates)
+            .map(|(dir, replaced)| {
+                self.sync_scratch_dir(&dir, files, replaced)?;
+                Ok(BuildDir {
+                    project_dir: Some(dir),
+                    input_path: input_path.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.run_builds(&dirs)
+    }
+
+    fn fn_result(&self, reproduces_issue: bool) -> BuildResult {
+        BuildResult {
+            reproduces_issue,
+            no_verify: false,
+            output: String::new(),
+            stdout: String::new(),
+            status: None,
+            timed_out: false,
+            cancelled: false,
+            infrastructure_error: None,
+            expected: self.expected(),
+            allow_color: self.inner.allow_color,
+        }
+    }
+
+    fn no_verify_result(&self) -> BuildResult {
+        BuildResult {
+            reproduces_issue: false,
+            no_verify: true,
+            output: String::new(),
+            stdout: String::new(),
+            status: None,
+            timed_out: false,
+            cancelled: false,
+            infrastructure_error: None,
+            expected: self.expected(),
+            allow_color: self.inner.allow_color,
+        }
+    }
+
+    /// What a build has to produce to reproduce the issue, for explaining why it didn't.
+    fn expected(&self) -> String {
+        let inner = &self.inner;
+        match (&inner.verify, &inner.mode) {
+            (Verify::None, _) => "nothing, --no-verify is set".to_owned(),
+            (Verify::Regex(regex), _) => {
+                format!(
+                    "a match of the regex `{}` in stderr or stdout",
+                    regex.as_str()
                 )
             }
-            BuildMode::Rustc(rustc) => {
-                let mut cmd = self.cmd(rustc);
-                cmd.args(["--edition", "2021"]);
-                cmd.arg(&inner.input_path);
+            (Verify::Custom(_), _) => "--verify-fn to return true".to_owned(),
+            (Verify::Predicate(predicate), _) => format!("`{predicate}` to hold"),
+            (Verify::Ice, BuildMode::Cargo { .. }) => {
+                "an internal compiler error or a panic in stderr".to_owned()
+            }
+            (Verify::Ice, BuildMode::Rustc(_)) => {
+                "exit code 101 or an internal compiler error in stderr".to_owned()
+            }
+            (Verify::Ice, BuildMode::Script(_)) => match &inner.reproduce_exit_codes {
+                Some(codes) => format!("an exit code in {codes}"),
+                None => "exit code 0".to_owned(),
+            },
+            (Verify::Ice, BuildMode::Fn(_)) => "the function to return true".to_owned(),
+        }
+    }
 
-                if inner.allow_color {
-                    cmd.arg("--color=always");
+    /// The files that aren't deleted, keyed by their path relative to the project directory.
+    /// `replaced` are files that get the given content instead of their current content.
+    fn sources(&self, files: &[SourceFile], replaced: &[(&SourceFile, &str)]) -> Sources {
+        let root = self.inner.project_dir.as_deref().unwrap_or(Path::new(""));
+        files
+            .iter()
+            .filter(|file| !file.is_deleted())
+            .map(|file| {
+                let path = file.path_no_fs_interact();
+                let path = path.strip_prefix(root).unwrap_or(path);
+                let content = match replaced_content(replaced, file) {
+                    Some(content) => content.to_owned(),
+                    None => file.content_str(),
+                };
+                (path.to_owned(), content)
+            })
+            .collect()
+    }
+
+    fn project_root(&self) -> Result<PathBuf> {
+        let cwd = std::env::current_dir().context("finding project directory")?;
+        Ok(match &self.inner.project_dir {
+            Some(dir) => cwd.join(dir),
+            None => cwd,
+        })
+    }
+
+    /// The path relative to the project directory.
+    pub(crate) fn relative(&self, path: &Path) -> Result<PathBuf> {
+        let root = self.project_root()?;
+        let absolute = std::env::current_dir()
+            .context("finding project directory")?
+            .join(path);
+        match absolute.strip_prefix(&root) {
+            Ok(relative) => Ok(relative.to_owned()),
+            Err(_) => bail!(
+                "{} is not in the project directory {}, so it can't be built in parallel",
+                path.display(),
+                root.display()
+            ),
+        }
+    }
+
+    /// Copies of the project directory for parallel builds. They are created when they are first needed.
+    fn scratch_dirs(&self, count: usize) -> Result<Vec<PathBuf>> {
+        let mut dirs = self.inner.scratch_dirs.borrow_mut();
+        while dirs.len() < count {
+            let dir = tempfile::tempdir().context("creating directory for parallel build")?;
+            copy_project(&self.project_root()?, dir.path())?;
+            dirs.push(dir);
+        }
+        Ok(dirs[..count]
+            .iter()
+            .map(|dir| dir.path().to_owned())
+            .collect())
+    }
+
+    /// Brings the sources in the copy of the project directory up to date, with the given content for the
+    /// `replaced` files.
+    fn sync_scratch_dir(
+        &self,
+        dir: &Path,
+        files: &[SourceFile],
+        replaced: &[(&SourceFile, &str)],
+    ) -> Result<()> {
+        for source in files {
+            let path = dir.join(self.relative(source.path_no_fs_interact())?);
+            if source.is_deleted() {
+                if path.exists() {
+                    std::fs::remove_file(&path)
+                        .with_context(|| format!("deleting file {}", path.display()))?;
                 }
+                continue;
+            }
 
-                cmd.args(&inner.extra_args);
+            let content = match replaced_content(replaced, source) {
+                Some(content) => content.to_owned(),
+                None => source.content_str(),
+            };
+            // Files that didn't change are left alone, so that cargo doesn't consider them changed.
+            if std::fs::read_to_string(&path).ok().as_ref() == Some(&content) {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating directory {}", parent.display()))?;
+            }
+            std::fs::write(&path, content)
+                .with_context(|| format!("writing file {}", path.display()))?;
+        }
+        Ok(())
+    }
 
-                for env in &inner.env {
-                    cmd.env(&env.key, &env.value);
+    /// Runs a build in every directory at the same time. With `--keep-going-on-build-error`, builds that
+    /// fail to run are retried with a backoff, and treated as not reproducing if they keep failing.
+    fn run_builds(&self, dirs: &[BuildDir]) -> Result<Vec<BuildResult>> {
+        if !self.inner.keep_going_on_build_error {
+            return self.try_run_builds(dirs);
+        }
+
+        let mut delay = BUILD_RETRY_DELAY;
+        for retry in 1.. {
+            let error = match self.try_run_builds(dirs) {
+                Ok(results) => match results
+                    .iter()
+                    .find_map(|result| result.infrastructure_error.clone())
+                {
+                    None => return Ok(results),
+                    Some(_) if retry > BUILD_RETRIES => {
+                        warn!("The build keeps failing to run, treating it as not reproducing");
+                        return Ok(results);
+                    }
+                    Some(error) => error,
+                },
+                Err(err) if retry > BUILD_RETRIES => {
+                    warn!(
+                        "The build keeps failing to run, treating it as not reproducing: {err:#}"
+                    );
+                    let error = format!("{err:#}");
+                    return Ok(dirs.iter().map(|_| self.failed_result(&error)).collect());
                 }
+                Err(err) => format!("{err:#}"),
+            };
+            warn!("The build failed to run ({error}), retrying in {delay:?}");
+            thread::sleep(delay);
+            delay *= 2;
+        }
+        unreachable!("the loop only ends by returning")
+    }
 
-                let outputs = cmd.output().context("spawning rustc process")?;
+    fn try_run_builds(&self, dirs: &[BuildDir]) -> Result<Vec<BuildResult>> {
+        let mut cmds = dirs
+            .iter()
+            .map(|dir| self.build_cmd(dir))
+            .collect::<Vec<_>>();
+
+        let outputs = self
+            .outputs(&mut cmds)
+            .with_context(|| match self.inner.mode {
+                BuildMode::Script(_) => format!("spawning script: `{:?}`", cmds[0]),
+                _ => "spawning rustc process".to_owned(),
+            })?;
+
+        outputs
+            .into_iter()
+            .map(|(output, interruption)| self.build_result(output, interruption))
+            .collect()
+    }
 
-                let output = String::from_utf8(outputs.stderr)?;
+    fn build_cmd(&self, dir: &BuildDir) -> Command {
+        let inner = &self.inner;
 
-                (
-                    outputs.status.code() == Some(101)
-                        || output.contains("internal compiler error"),
-                    outputs.status,
-                    output,
-                )
-            }
-            BuildMode::Script(script_path) => {
-                let mut cmd = self.cmd(script_path);
+        // Colors would get in the way of matching the output.
+        let color =
+            inner.allow_color && !matches!(inner.verify, Verify::Regex(_) | Verify::Predicate(_));
 
-                cmd.args(&inner.extra_args);
+        let mut cmd = match &inner.mode {
+            BuildMode::Cargo {
+                cargo_path,
+                subcommand,
+            } => {
+                let mut cmd = self.cmd_in(cargo_path, dir.project_dir.as_deref());
 
-                for env in &inner.env {
-                    cmd.env(&env.key, &env.value);
+                cmd.args(subcommand);
+
+                if color {
+                    cmd.arg("--color=always");
                 }
 
-                let outputs = cmd
-                    .output()
-                    .with_context(|| format!("spawning script: `{cmd:?}`"))?;
+                extra_cargoflags(&mut cmd);
+                cmd
+            }
+            BuildMode::Rustc(rustc) => {
+                let mut cmd = self.cmd_in(rustc, dir.project_dir.as_deref());
+                if rustc_edition_arg(&inner.extra_args).is_none() {
+                    cmd.args(["--edition", "2021"]);
+                }
+                cmd.arg(&dir.input_path);
+
+                if color {
+                    cmd.arg("--color=always");
+                }
+                cmd
+            }
+            BuildMode::Script(script_path) => self.cmd_in(script_path, dir.project_dir.as_deref()),
+            BuildMode::Fn(_) => unreachable!("handled earlier"),
+        };
+
+        cmd.args(&inner.extra_args);
+
+        for env in &inner.env {
+            cmd.env(&env.key, &env.value);
+        }
 
-                let output = String::from_utf8(outputs.stderr)?;
+        cmd
+    }
+
+    fn build_result(
+        &self,
+        outputs: Output,
+        interruption: Option<Interruption>,
+    ) -> Result<BuildResult> {
+        let inner = &self.inner;
 
-                (outputs.status.success(), outputs.status, output)
+        let output = String::from_utf8(outputs.stderr)?;
+
+        let is_ice = match &inner.mode {
+            // Cargo always exits with 101 when rustc has an error.
+            BuildMode::Cargo { subcommand, .. } => {
+                // Newer versions print the thread id after the name, like `thread 'main' (123) panicked at`.
+                let is_ice = |output: &str| {
+                    output.contains("internal compiler error")
+                        || output.contains("' panicked at")
+                        || output.contains(") panicked at")
+                };
+                // The test harness prints the panics of failing tests and benches to stdout.
+                let runs_tests = matches!(
+                    subcommand.first().map(String::as_str),
+                    Some("test" | "bench")
+                );
+                is_ice(&output) || (runs_tests && is_ice(&String::from_utf8_lossy(&outputs.stdout)))
             }
+            BuildMode::Rustc(_) => {
+                outputs.status.code() == Some(101) || output.contains("internal compiler error")
+            }
+            BuildMode::Script(_) => match &inner.reproduce_exit_codes {
+                // Killed by a signal, which is never what we are looking for.
+                Some(codes) => outputs
+                    .status
+                    .code()
+                    .is_some_and(|code| codes.contains(code)),
+                None => outputs.status.success(),
+            },
+            BuildMode::Fn(_) => unreachable!("handled earlier"),
+        };
+
+        let infrastructure_error = match &inner.mode {
+            // The shell couldn't execute or find the command.
+            BuildMode::Script(_) => match outputs.status.code() {
+                Some(code @ (126 | 127)) => Some(format!("the script exited with {code}")),
+                _ => None,
+            },
+            BuildMode::Cargo { .. } => output
+                .lines()
+                .find(|line| {
+                    CARGO_INFRASTRUCTURE_ERRORS
+                        .iter()
+                        .any(|error| line.contains(error))
+                })
+                .map(|line| line.trim().to_owned()),
+            _ => None,
         };
 
         let reproduces_issue = match inner.verify {
+            _ if interruption == Some(Interruption::Cancelled) => {
+                info!("Build was cancelled");
+                false
+            }
+            _ if interruption == Some(Interruption::TimedOut) => {
+                info!("Build timed out");
+                inner.timeout_reproduces
+            }
             Verify::None => unreachable!("handled ealier"),
             Verify::Ice => is_ice,
-            Verify::Custom(func) => func.call(&output, cmd_status.code()),
+            Verify::Custom(func) => func.call(&output, outputs.status.code()),
+            Verify::Regex(ref regex) => {
+                regex.is_match(&output) || regex.is_match(&String::from_utf8_lossy(&outputs.stdout))
+            }
+            Verify::Predicate(ref predicate) => predicate.eval(&BuildOutput {
+                exit: outputs.status.code(),
+                stderr: &output,
+                stdout: &String::from_utf8_lossy(&outputs.stdout),
+                ice: is_ice,
+            }),
         };
 
         Ok(BuildResult {
             reproduces_issue,
             no_verify: false,
             output,
+            stdout: String::from_utf8_lossy(&outputs.stdout).into_owned(),
+            status: Some(outputs.status),
+            timed_out: interruption == Some(Interruption::TimedOut),
+            cancelled: interruption == Some(Interruption::Cancelled),
+            infrastructure_error: infrastructure_error.filter(|_| !reproduces_issue),
+            expected: self.expected(),
             allow_color: inner.allow_color,
         })
     }
 
+    /// The result of a build that couldn't be run at all.
+    fn failed_result(&self, error: &str) -> BuildResult {
+        BuildResult {
+            reproduces_issue: false,
+            no_verify: false,
+            output: error.to_owned(),
+            stdout: String::new(),
+            status: None,
+            timed_out: false,
+            cancelled: false,
+            infrastructure_error: Some(error.to_owned()),
+            expected: self.expected(),
+            allow_color: self.inner.allow_color,
+        }
+    }
+
+    /// Expands all macros of the crate, like `cargo expand` does, and returns the expanded code of the crate
+    /// root. This needs unstable rustc flags, which `RUSTC_BOOTSTRAP` allows on stable compilers.
+    pub(crate) fn expand(&self, edition: Edition) -> Result<String> {
+        let inner = &self.inner;
+        let mut cmd = match &inner.lint_mode {
+            BuildMode::Rustc(rustc) => {
+                let mut cmd = self.cmd(rustc);
+                if rustc_edition_arg(&inner.extra_args).is_none() {
+                    cmd.args(["--edition", &edition.to_string()]);
+                }
+                cmd.arg("-Zunpretty=expanded");
+                cmd.arg(&inner.input_path);
+                cmd.args(&inner.extra_args);
+                cmd
+            }
+            mode => {
+                let cargo_path = match mode {
+                    BuildMode::Cargo { cargo_path, .. } => cargo_path.as_path(),
+                    _ => Path::new("cargo"),
+                };
+                let mut cmd = self.cmd(cargo_path);
+                cmd.args(["rustc", "--profile=check", "--quiet"]);
+                extra_cargoflags(&mut cmd);
+                cmd.args(["--", "-Zunpretty=expanded"]);
+                cmd
+            }
+        };
+        cmd.env("RUSTC_BOOTSTRAP", "1");
+        for env in &inner.env {
+            cmd.env(&env.key, &env.value);
+        }
+
+        let (output, interruption) = self.output(&mut cmd).context("running the expansion")?;
+        if interruption.is_some() {
+            bail!("the expansion was interrupted");
+        }
+        let stdout = String::from_utf8(output.stdout).context("expanded code is not UTF-8")?;
+        if !output.status.success() || stdout.trim().is_empty() {
+            bail!(
+                "the expansion failed with {}:\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+        Ok(stdout)
+    }
+
     pub fn get_diags(&self) -> Result<(Vec<Diagnostic>, Vec<rustfix::Suggestion>)> {
         fn grab_rustc_diags(output: &str) -> Result<Vec<Diagnostic>> {
             serde_json::Deserializer::from_str(output)
@@ -279,21 +1006,29 @@ impl Build {
                     cmd.env(&env.key, &env.value);
                 }
 
-                let cmd_output = cmd.output()?;
+                // If this times out (for example because it's `cargo run`), the diagnostics have
+                // been emitted already anyways.
+                let (cmd_output, _) = self.output(&mut cmd)?;
                 let output = String::from_utf8(cmd_output.stdout)?;
 
                 grab_cargo_diags(&output)?
             }
             BuildMode::Rustc(rustc) => {
                 let mut cmd = self.cmd(rustc);
-                cmd.args(["--edition", "2021", "--error-format=json"]);
+                if rustc_edition_arg(&inner.extra_args).is_none() {
+                    cmd.args(["--edition", "2021"]);
+                }
+                cmd.arg("--error-format=json");
                 cmd.arg(&inner.input_path);
 
+                // The lints have to come from the same compilation as the reproduction.
+                cmd.args(&inner.extra_args);
+
                 for env in &inner.env {
                     cmd.env(&env.key, &env.value);
                 }
 
-                let output = cmd.output()?.stderr;
+                let output = self.output(&mut cmd)?.0.stderr;
                 let output = String::from_utf8(output)?;
 
                 grab_rustc_diags(&output)?
@@ -309,8 +1044,8 @@ impl Build {
 
                 cmd.env("MINIMIZE_LINTS", "1");
 
-                let outputs = cmd
-                    .output()
+                let (outputs, _) = self
+                    .output(&mut cmd)
                     .with_context(|| format!("spawning script: `{cmd:?}`"))?;
 
                 let stderr = String::from_utf8(outputs.stderr)?;
@@ -323,6 +1058,7 @@ impl Build {
                     LintMode::Cargo => grab_cargo_diags(output)?,
                 }
             }
+            BuildMode::Fn(_) => Vec::new(),
         };
 
         let mut suggestions = Vec::new();
@@ -339,6 +1075,106 @@ impl Build {
     }
 }
 
+fn kill(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        // SAFETY: Sending a signal has no memory safety implications.
+        // The child is the leader of its own process group, so this kills the group.
+        if unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) } == 0 {
+            return Ok(());
+        }
+    }
+    child.kill()
+}
+
+impl BuildInner {
+    /// Hashes everything that decides the result of a build, apart from the source files.
+    fn hash_config(&self, hasher: &mut DefaultHasher) {
+        format!("{:?}", self.mode).hash(hasher);
+        format!("{:?}", self.verify).hash(hasher);
+        for EnvVar { key, value } in &self.env {
+            (key, value).hash(hasher);
+        }
+        self.input_path.hash(hasher);
+        self.project_dir.hash(hasher);
+        self.extra_args.hash(hasher);
+        self.timeout.hash(hasher);
+        self.timeout_reproduces.hash(hasher);
+        self.verify_loop.hash(hasher);
+        self.reproduce_exit_codes
+            .as_ref()
+            .map(ToString::to_string)
+            .hash(hasher);
+    }
+}
+
+/// Results of previous builds. Only the verdict and the end of stderr are kept, and the oldest results are
+/// dropped when there are too many.
+#[derive(Debug)]
+struct BuildCache {
+    results: HashMap<u64, BuildResult>,
+    /// The keys in the order they were inserted.
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl BuildCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            results: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn get(&self, key: u64) -> Option<BuildResult> {
+        self.results.get(&key).cloned()
+    }
+
+    fn insert(&mut self, key: u64, result: &BuildResult) {
+        if !result.is_cacheable() {
+            return;
+        }
+        if self.results.insert(key, result.minimal()).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            let oldest = self.order.pop_front().expect("the cache isn't empty");
+            self.results.remove(&oldest);
+        }
+    }
+}
+
+/// The content that `file` is built with instead of its current content, if it is one of the `replaced` files.
+fn replaced_content<'a>(replaced: &[(&SourceFile, &'a str)], file: &SourceFile) -> Option<&'a str> {
+    replaced
+        .iter()
+        .find(|(replaced, _)| *replaced == file)
+        .map(|&(_, content)| content)
+}
+
+/// Copies the project directory, without `target` directories and `.git`.
+fn copy_project(from: &Path, to: &Path) -> Result<()> {
+    let walk = ignore::WalkBuilder::new(from)
+        .standard_filters(false)
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !matches!(entry.file_name().to_str(), Some("target" | ".git"))
+        })
+        .build();
+    for entry in walk {
+        let entry = entry?;
+        let dest = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_some_and(|ty| ty.is_dir()) {
+            std::fs::create_dir_all(&dest)
+                .with_context(|| format!("creating directory {}", dest.display()))?;
+        } else {
+            std::fs::copy(entry.path(), &dest)
+                .with_context(|| format!("copying {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
 fn extra_cargoflags(cargo: &mut Command) {
     cargo.arg("--offline");
 }
@@ -359,23 +1195,80 @@ pub fn rustup_which(tool: &str) -> Result<PathBuf> {
         .into())
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BuildResult {
     reproduces_issue: bool,
     no_verify: bool,
+    /// The stderr of the build.
     output: String,
+    stdout: String,
+    /// `None` if nothing was run.
+    status: Option<ExitStatus>,
+    timed_out: bool,
+    /// Killed because the minimization was cancelled. The result isn't cached.
+    cancelled: bool,
+    /// The build failed for reasons that have nothing to do with the code, like a broken lock file.
+    /// The result isn't cached.
+    infrastructure_error: Option<String>,
+    /// What the build has to produce to reproduce the issue.
+    expected: String,
     allow_color: bool,
 }
 
 impl BuildResult {
+    fn is_cacheable(&self) -> bool {
+        !self.cancelled && self.infrastructure_error.is_none()
+    }
+
+    /// The result without stdout and with only the end of stderr, to keep the cache small.
+    fn minimal(&self) -> BuildResult {
+        let mut start = self.output.len().saturating_sub(CACHED_STDERR_BYTES);
+        while !self.output.is_char_boundary(start) {
+            start += 1;
+        }
+        BuildResult {
+            reproduces_issue: self.reproduces_issue,
+            no_verify: self.no_verify,
+            output: self.output[start..].to_owned(),
+            stdout: String::new(),
+            status: self.status,
+            timed_out: self.timed_out,
+            cancelled: self.cancelled,
+            infrastructure_error: None,
+            expected: self.expected.clone(),
+            allow_color: self.allow_color,
+        }
+    }
+
     pub fn require_reproduction(&self, build: &str) -> Result<()> {
-        if !self.reproduces_issue() {
-            bail!(
-                "{build} build must reproduce issue. Output:\n{}",
-                self.output
-            );
+        if self.reproduces_issue() {
+            return Ok(());
         }
-        Ok(())
+        if self.cancelled {
+            bail!("{build} build was cancelled");
+        }
+
+        let mut message = format!(
+            "{build} build must reproduce the issue, but it didn't.\nExpected {}.\n",
+            self.expected
+        );
+        match self.status {
+            _ if self.timed_out => message.push_str("The build timed out.\n"),
+            Some(status) => message.push_str(&format!("The build exited with {status}.\n")),
+            None => {}
+        }
+        for (name, output) in [("stderr", &self.output), ("stdout", &self.stdout)] {
+            if !output.trim().is_empty() {
+                message.push_str(&format!("{name}:\n{}\n", output.trim_end()));
+            }
+        }
+        bail!("{}", message.trim_end())
+    }
+
+    /// Whether stderr or stdout of the build contain the path. A cached result only has the end of stderr.
+    pub(crate) fn mentions(&self, path: &Path) -> bool {
+        let path = path.display().to_string();
+        self.output.contains(&path) || self.stdout.contains(&path)
     }
 
     pub fn reproduces_issue(&self) -> bool {
@@ -408,10 +1301,24 @@ pub struct CargoJsonCompileMessage {
     pub message: Option<Diagnostic>,
 }
 
-fn split_args(s: &str) -> Vec<String> {
+pub(crate) fn split_args(s: &str) -> Vec<String> {
     s.split_whitespace().map(ToString::to_string).collect()
 }
 
+/// The edition that is passed to rustc with `--edition` in the arguments.
+pub(crate) fn rustc_edition_arg(args: &[String]) -> Option<&str> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(edition) = arg.strip_prefix("--edition=") {
+            return Some(edition);
+        }
+        if arg == "--edition" {
+            return args.next().map(String::as_str);
+        }
+    }
+    None
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum LintMode {
     Rustc,
@@ -441,6 +1348,274 @@ mod tests {
 
     use super::read_script_output;
 
+    #[test]
+    #[cfg(unix)]
+    fn timeout_kills_looping_script() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        use super::Build;
+        use crate::{edition::Edition, processor::SourceFile, Options};
+
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("script.sh");
+        std::fs::write(&script, "#!/bin/sh\nwhile true; do sleep 1; done\n")?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+        let source = dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() { loop {} }")?;
+        let files = [SourceFile::open(source, Edition::E2021)?];
+
+        let options = Options {
+            script_path: Some(script),
+            timeout: Some(1),
+            ..Options::default()
+        };
+
+        let build = Build::new(&options)?;
+        assert!(!build.build(&files)?.reproduces_issue());
+
+        let build = Build::new(&Options {
+            timeout_reproduces: true,
+            ..options
+        })?;
+        assert!(build.build(&files)?.reproduces_issue());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reproduce_exit_codes() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        use super::Build;
+        use crate::{edition::Edition, processor::SourceFile, EnvVar, Options};
+
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("script.sh");
+        std::fs::write(&script, "#!/bin/sh\nexit $CODE\n")?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+        let source = dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() {}")?;
+        let files = [SourceFile::open(source, Edition::E2021)?];
+
+        let reproduces = |codes: Option<&str>, code: i32| -> anyhow::Result<bool> {
+            let build = Build::new(&Options {
+                script_path: Some(script.clone()),
+                reproduce_exit_codes: codes.map(|codes| codes.parse().unwrap()),
+                env: vec![EnvVar {
+                    key: "CODE".into(),
+                    value: code.to_string(),
+                }],
+                ..Options::default()
+            })?;
+            Ok(build.build(&files)?.reproduces_issue())
+        };
+
+        assert!(reproduces(None, 0)?);
+        assert!(!reproduces(None, 101)?);
+
+        assert!(reproduces(Some("101"), 101)?);
+        assert!(!reproduces(Some("101"), 0)?);
+
+        assert!(reproduces(Some("0,3-5"), 0)?);
+        assert!(reproduces(Some("0,3-5"), 4)?);
+        assert!(!reproduces(Some("0,3-5"), 6)?);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn env_is_passed_to_build() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        use super::Build;
+        use crate::{edition::Edition, processor::SourceFile, EnvVar, Options};
+
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("script.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\ntest \"$RUSTFLAGS\" = \"-Cdebuginfo=2\"\n",
+        )?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+        let source = dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() {}")?;
+        let files = [SourceFile::open(source, Edition::E2021)?];
+
+        let reproduces = |options: Options| -> anyhow::Result<bool> {
+            let build = Build::new(&Options {
+                script_path: Some(script.clone()),
+                ..options
+            })?;
+            Ok(build.build(&files)?.reproduces_issue())
+        };
+
+        assert!(!reproduces(Options::default())?);
+        assert!(!reproduces(Options {
+            env: vec!["RUSTFLAGS=-Cdebuginfo=0".parse::<EnvVar>().unwrap()],
+            ..Options::default()
+        })?);
+        assert!(reproduces(Options {
+            env: vec!["RUSTFLAGS=-Cdebuginfo=2".parse::<EnvVar>().unwrap()],
+            ..Options::default()
+        })?);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn cache_hit_after_rollback() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        use super::Build;
+        use crate::{edition::Edition, processor::SourceFile, Options};
+
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("script.sh");
+        let count = dir.path().join("count");
+        std::fs::write(&script, format!("#!/bin/sh\necho >> {}\n", count.display()))?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+        let builds = || std::fs::read_to_string(&count).unwrap().lines().count();
+
+        let source = dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() {}")?;
+        let files = [SourceFile::open(source, Edition::E2021)?];
+
+        let build = Build::new(&Options {
+            script_path: Some(script.clone()),
+            ..Options::default()
+        })?;
+
+        let before = syn::parse_str::<syn::File>("fn main() {}")?;
+        files[0].write(before.clone())?;
+        assert!(build.build(&files)?.reproduces_issue());
+        assert_eq!(builds(), 1);
+
+        files[0].write(syn::parse_str("fn main() { loop {} }")?)?;
+        assert!(build.build(&files)?.reproduces_issue());
+        assert_eq!(builds(), 2);
+
+        files[0].write(before)?;
+        assert!(build.build(&files)?.reproduces_issue());
+        assert_eq!(builds(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cache_key_depends_on_configuration() -> anyhow::Result<()> {
+        use super::Build;
+        use crate::{edition::Edition, processor::SourceFile, Options};
+
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() {}")?;
+        let files = [SourceFile::open(source, Edition::E2021)?];
+
+        let key = |options: Options| Build::new(&options).map(|build| build.cache_key(&files, &[]));
+        let default = key(Options::default())?;
+        assert_eq!(key(Options::default())?, default);
+        assert_ne!(
+            key(Options {
+                extra_args: Some("--cfg foo".to_owned()),
+                ..Options::default()
+            })?,
+            default
+        );
+        assert_ne!(
+            key(Options {
+                env: vec!["RUSTFLAGS=-Cdebuginfo=2".parse().unwrap()],
+                ..Options::default()
+            })?,
+            default
+        );
+        assert_ne!(
+            key(Options {
+                verify_loop: 3,
+                ..Options::default()
+            })?,
+            default
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cache_keeps_only_newest_minimal_results() {
+        use super::{BuildCache, BuildResult, CACHED_STDERR_BYTES};
+
+        let result = |output: String| BuildResult {
+            reproduces_issue: true,
+            no_verify: false,
+            output,
+            stdout: "stdout".to_owned(),
+            status: None,
+            timed_out: false,
+            cancelled: false,
+            infrastructure_error: None,
+            expected: "an ICE".to_owned(),
+            allow_color: false,
+        };
+
+        let mut cache = BuildCache::new(2);
+        cache.insert(1, &result("é".repeat(CACHED_STDERR_BYTES)));
+        cache.insert(2, &result(String::new()));
+        let cached = cache.get(1).unwrap();
+        assert!(cached.reproduces_issue());
+        assert!(cached.stdout.is_empty());
+        assert!(cached.output.len() <= CACHED_STDERR_BYTES);
+        assert!(cached.output.ends_with('é'));
+
+        cache.insert(3, &result(String::new()));
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(2).is_some());
+        assert!(cache.get(3).is_some());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn keep_going_retries_failing_build() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        use super::Build;
+        use crate::{edition::Edition, processor::SourceFile, Options};
+
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("script.sh");
+        let count = dir.path().join("count");
+        // The first build fails as if the command couldn't be found.
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh
+if [ -e {0} ]; then exit 0; fi
+touch {0}
+exit 127
+",
+                count.display()
+            ),
+        )?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+        let source = dir.path().join("main.rs");
+        std::fs::write(&source, "fn main() {}")?;
+        let files = [SourceFile::open(source, Edition::E2021)?];
+
+        let build = Build::new(&Options {
+            script_path: Some(script),
+            keep_going_on_build_error: true,
+            ..Options::default()
+        })?;
+        assert!(build.build(&files)?.reproduces_issue());
+
+        Ok(())
+    }
+
     #[test]
     fn script_output_default() {
         let (output, mode) = read_script_output("uwu", "owo");
@@ -0,0 +1,128 @@
+//! Reads the options from a `minimize.toml` in the project directory, so that they don't have to be passed
+//! every time. Options that are passed on the command line take precedence over the file.
+//!
+//! ```toml
+//! passes = ["delete-statements", "item-deleter"]
+//! regex = 'error\[E0308\]'
+//! exclude = ["src/generated/*.rs"]
+//! timeout = 30
+//! ```
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::Options;
+
+pub(crate) const CONFIG_FILE: &str = "minimize.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Config {
+    extra_args: Option<String>,
+    regex: Option<String>,
+    passes: Option<Vec<String>>,
+    timeout: Option<u64>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+/// Fills in the options that weren't passed from the `minimize.toml` of the project directory, if there
+/// is one.
+pub(crate) fn apply(options: &mut Options) -> Result<()> {
+    let dir = match &options.project_dir {
+        Some(dir) => dir.clone(),
+        None => std::env::current_dir().context("getting the current directory")?,
+    };
+    let path = dir.join(CONFIG_FILE);
+    if !path.try_exists()? {
+        return Ok(());
+    }
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let config = toml::from_str::<Config>(&content)
+        .with_context(|| format!("parsing {}", path.display()))?;
+    info!("Using options from {}", path.display());
+    apply_config(options, config, &path)
+}
+
+fn apply_config(options: &mut Options, config: Config, path: &Path) -> Result<()> {
+    if options.extra_args.is_none() {
+        options.extra_args = config.extra_args;
+    }
+    // A regex from the file would conflict with the other ways of checking the output.
+    if options.regex.is_none() && options.verify_fn.is_none() && options.reproduces.is_none() {
+        if let Some(regex) = config.regex {
+            let regex = Regex::new(&regex)
+                .with_context(|| format!("parsing the regex of {}", path.display()))?;
+            options.regex = Some(regex);
+        }
+    }
+    if options.passes.is_none() {
+        options.passes = config.passes.map(|passes| passes.join(","));
+    }
+    if options.timeout.is_none() {
+        options.timeout = config.timeout;
+    }
+    if options.exclude.is_empty() {
+        options.exclude = config.exclude;
+    }
+    if options.include.is_empty() {
+        options.include = config.include;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+
+    use super::{apply, CONFIG_FILE};
+    use crate::Options;
+
+    #[test]
+    fn options_from_config_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join(CONFIG_FILE),
+            "passes = [\"delete-statements\", \"item-deleter\"]\n\
+             regex = 'error\\[E0308\\]'\n\
+             exclude = [\"src/generated/*.rs\"]\n\
+             timeout = 30\n",
+        )?;
+
+        let mut options = Options {
+            project_dir: Some(dir.path().to_owned()),
+            timeout: Some(5),
+            ..Options::default()
+        };
+        apply(&mut options)?;
+
+        assert_eq!(
+            options.passes.as_deref(),
+            Some("delete-statements,item-deleter")
+        );
+        assert_eq!(
+            options.regex.as_ref().map(|regex| regex.as_str()),
+            Some("error\\[E0308\\]")
+        );
+        assert_eq!(options.exclude, ["src/generated/*.rs"]);
+        // Passed on the command line, so the file doesn't override it.
+        assert_eq!(options.timeout, Some(5));
+
+        fs::write(dir.path().join(CONFIG_FILE), "timout = 30\n")?;
+        assert!(apply(&mut Options {
+            project_dir: Some(dir.path().to_owned()),
+            ..Options::default()
+        })
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+//! Handling of the edition of the minimized code.
+//! `syn` parses the syntax of the latest edition, so code of older editions can contain syntax that it
+//! doesn't understand anymore. We rewrite that syntax into an equivalent form that every edition accepts.
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use anyhow::{Context, Result};
+use proc_macro2::{Delimiter, Group, Ident, Punct, Spacing, TokenStream, TokenTree};
+
+use crate::{
+    build::{rustc_edition_arg, split_args},
+    Options,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Edition {
+    E2015,
+    E2018,
+    E2021,
+}
+
+impl Edition {
+    /// Finds the edition of the code from the `Cargo.toml` of the project.
+    pub(crate) fn detect(options: &Options) -> Result<Self> {
+        if options.rustc {
+            // We pass `--edition 2021` to rustc, unless the extra arguments contain an edition.
+            let args = options
+                .extra_args
+                .as_deref()
+                .map(split_args)
+                .unwrap_or_default();
+            return Ok(rustc_edition_arg(&args).map_or(Self::E2021, Self::from_name));
+        }
+
+        let project_dir = match &options.project_dir {
+            Some(dir) => dir.canonicalize(),
+            None => std::env::current_dir(),
+        }
+        .context("finding project directory")?;
+
+        Self::from_manifest_dir(&project_dir)
+    }
+
+    /// Reads the edition from the closest `Cargo.toml` in `dir` or its parents.
+    pub(crate) fn from_manifest_dir(dir: &Path) -> Result<Self> {
+        let mut inherits_edition = false;
+
+        for dir in dir.ancestors() {
+            let path = dir.join("Cargo.toml");
+            if !path.exists() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            let manifest = toml::from_str::<toml::Value>(&content)
+                .with_context(|| format!("parsing {}", path.display()))?;
+
+            let workspace_edition = manifest
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package"))
+                .and_then(|package| package.get("edition"));
+
+            let edition = match manifest.get("package") {
+                _ if inherits_edition => workspace_edition,
+                Some(package) => match package.get("edition") {
+                    Some(toml::Value::Table(_)) => {
+                        // `edition.workspace = true`, the edition is in the workspace manifest.
+                        inherits_edition = true;
+                        workspace_edition
+                    }
+                    edition => edition,
+                },
+                None => workspace_edition,
+            };
+
+            match edition.and_then(toml::Value::as_str) {
+                Some(edition) => return Ok(Self::from_name(edition)),
+                None if inherits_edition => continue,
+                // Cargo defaults to 2015 when no edition is specified.
+                None => return Ok(Self::E2015),
+            }
+        }
+
+        info!(
+            "Did not find a Cargo.toml with an edition, assuming {}",
+            Self::E2021
+        );
+        Ok(Self::E2021)
+    }
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "2015" => Self::E2015,
+            "2018" => Self::E2018,
+            "2021" => Self::E2021,
+            _ => {
+                warn!(
+                    "Edition `{name}` is not supported, syntax that is new in it may not be parsed or written back correctly"
+                );
+                Self::E2021
+            }
+        }
+    }
+
+    /// Parses a file of this edition.
+    pub(crate) fn parse_file(self, content: &str) -> syn::Result<syn::File> {
+        match syn::parse_file(content) {
+            Err(err) if self == Self::E2015 => {
+                // Most files of old editions only use syntax that is still valid, only fix them up if
+                // they don't parse.
+                let Ok(tokens) = TokenStream::from_str(&strip_shebang(content)) else {
+                    return Err(err);
+                };
+                syn::parse2(name_anonymous_params(tokens, false)).map_err(|_| err)
+            }
+            result => result,
+        }
+    }
+}
+
+impl Display for Edition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::E2015 => f.write_str("2015"),
+            Self::E2018 => f.write_str("2018"),
+            Self::E2021 => f.write_str("2021"),
+        }
+    }
+}
+
+/// `syn::parse_file` ignores the shebang, but the tokenizer doesn't.
+fn strip_shebang(content: &str) -> String {
+    match content.strip_prefix("#!") {
+        Some(rest) if !rest.trim_start().starts_with('[') => {
+            // Keep the newline so that the line numbers in spans stay the same.
+            rest.find('\n')
+                .map_or_else(String::new, |i| rest[i..].to_owned())
+        }
+        _ => content.to_owned(),
+    }
+}
+
+/// Methods in traits of the 2015 edition can have parameters without a pattern like `fn f(u8);`.
+/// `syn` doesn't support these anymore, so we name them `_`, which is valid in every edition.
+fn name_anonymous_params(tokens: TokenStream, in_trait: bool) -> TokenStream {
+    let mut result = Vec::new();
+    let mut after_trait = false;
+    let mut after_fn = false;
+    // The generics of the method can contain parentheses too, like `F: Fn()`.
+    let mut generics_depth = 0_usize;
+
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) if ident == "trait" => {
+                after_trait = true;
+                result.push(TokenTree::Ident(ident));
+            }
+            TokenTree::Ident(ident) if in_trait && ident == "fn" => {
+                after_fn = true;
+                generics_depth = 0;
+                result.push(TokenTree::Ident(ident));
+            }
+            TokenTree::Punct(punct) if after_fn => {
+                match punct.as_char() {
+                    '<' => generics_depth += 1,
+                    '>' => generics_depth = generics_depth.saturating_sub(1),
+                    _ => {}
+                }
+                result.push(TokenTree::Punct(punct));
+            }
+            TokenTree::Group(group) => {
+                let stream = match group.delimiter() {
+                    Delimiter::Brace if after_trait => {
+                        after_trait = false;
+                        name_anonymous_params(group.stream(), true)
+                    }
+                    Delimiter::Parenthesis if after_fn && generics_depth == 0 => {
+                        after_fn = false;
+                        name_params(group.stream())
+                    }
+                    // The bodies of default methods are not trait items anymore.
+                    _ => name_anonymous_params(group.stream(), false),
+                };
+                let mut new_group = Group::new(group.delimiter(), stream);
+                new_group.set_span(group.span());
+                result.push(TokenTree::Group(new_group));
+            }
+            token => result.push(token),
+        }
+    }
+
+    result.into_iter().collect()
+}
+
+fn name_params(params: TokenStream) -> TokenStream {
+    let mut result = Vec::new();
+    for param in split_params(params) {
+        if !result.is_empty() {
+            result.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+        }
+        if !param.is_empty() && !has_pattern(&param) {
+            let span = param[0].span();
+            result.push(TokenTree::Ident(Ident::new("_", span)));
+            let mut colon = Punct::new(':', Spacing::Alone);
+            colon.set_span(span);
+            result.push(TokenTree::Punct(colon));
+        }
+        result.extend(param);
+    }
+    result.into_iter().collect()
+}
+
+/// Splits the parameters at commas that are not inside generic arguments.
+fn split_params(params: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut result = vec![Vec::new()];
+    let mut depth = 0_usize;
+    let mut after_minus = false;
+
+    for token in params {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                // `->` of `Fn() -> T` is not a closing angle bracket.
+                '>' if !after_minus => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    result.push(Vec::new());
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        after_minus = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '-' && punct.spacing() == Spacing::Joint);
+        result.last_mut().expect("never empty").push(token);
+    }
+
+    result
+}
+
+/// Whether the parameter is `self` or has a `pattern: Type`.
+fn has_pattern(param: &[TokenTree]) -> bool {
+    let mut depth = 0_usize;
+    let mut after_colon = false;
+    for (i, token) in param.iter().enumerate() {
+        match token {
+            TokenTree::Punct(punct) => match punct.as_char() {
+                '<' => depth += 1,
+                '>' => depth = depth.saturating_sub(1),
+                // `::` is two joint colons.
+                ':' if depth == 0 && !after_colon && punct.spacing() == Spacing::Alone => {
+                    return true
+                }
+                _ => {}
+            },
+            TokenTree::Ident(ident) if ident == "self" && i == param.len() - 1 => return true,
+            _ => {}
+        }
+        after_colon = matches!(token, TokenTree::Punct(punct) if punct.as_char() == ':' && punct.spacing() == Spacing::Joint);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use quote::ToTokens;
+
+    use super::Edition;
+
+    #[test]
+    fn detect_from_manifest() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = dir.path().join("Cargo.toml");
+
+        fs::write(&manifest, "[package]\nname = \"a\"\nversion = \"0.1.0\"\n")?;
+        assert_eq!(Edition::from_manifest_dir(dir.path())?, Edition::E2015);
+
+        fs::write(
+            &manifest,
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+        )?;
+        assert_eq!(Edition::from_manifest_dir(dir.path())?, Edition::E2018);
+
+        let member = dir.path().join("member");
+        fs::create_dir(&member)?;
+        fs::write(
+            &manifest,
+            "[workspace]\nmembers = [\"member\"]\n[workspace.package]\nedition = \"2021\"\n",
+        )?;
+        fs::write(
+            member.join("Cargo.toml"),
+            "[package]\nname = \"member\"\nversion = \"0.1.0\"\nedition.workspace = true\n",
+        )?;
+        assert_eq!(Edition::from_manifest_dir(&member)?, Edition::E2021);
+
+        Ok(())
+    }
+
+    #[test]
+    fn anonymous_params_2015() {
+        let src = "
+trait Trait {
+    fn anonymous<F: Fn()>(u8, Vec<(u8, u16)>, &Fn(u8) -> u8) -> u8;
+    fn method(&self, std::collections::HashMap<u8, u8>);
+    fn named(x: u8) {
+        fn inner(y: u8) {}
+    }
+}
+";
+        assert!(Edition::E2021.parse_file(src).is_err());
+
+        let file = Edition::E2015.parse_file(src).unwrap();
+        let file = file.into_token_stream().to_string();
+        assert!(
+            file.contains("fn anonymous < F : Fn () > (_ : u8 , _ : Vec < (u8 , u16) > , _ : & Fn (u8) -> u8) -> u8 ;"),
+            "{file}"
+        );
+        assert!(
+            file.contains("fn method (& self , _ : std :: collections :: HashMap < u8 , u8 >) ;"),
+            "{file}"
+        );
+        assert!(file.contains("fn named (x : u8)"), "{file}");
+    }
+}
@@ -3,10 +3,15 @@ use std::collections::HashMap;
 use anyhow::Context;
 use genemichaels::FormatConfig;
 
-pub fn format(file: syn::File) -> anyhow::Result<String> {
-    Ok(
-        genemichaels::format_ast(file, &FormatConfig::default(), HashMap::new())
-            .context("formatting source file")?
-            .rendered,
-    )
+/// Formats the file. The formatter only writes the code, so the shebang is put back in front of it, or the
+/// file would lose it with the first change that is written.
+pub fn format(mut file: syn::File) -> anyhow::Result<String> {
+    let shebang = file.shebang.take();
+    let rendered = genemichaels::format_ast(file, &FormatConfig::default(), HashMap::new())
+        .context("formatting source file")?
+        .rendered;
+    Ok(match shebang {
+        Some(shebang) => format!("{shebang}\n{rendered}"),
+        None => rendered,
+    })
 }
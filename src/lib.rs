@@ -2,30 +2,37 @@
 extern crate tracing;
 
 use std::{
-    path::PathBuf,
+    collections::BTreeMap,
+    fmt::Display,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::{atomic::AtomicBool, Arc},
 };
 
 mod build;
+mod config;
 mod dylib_flag;
+mod edition;
 mod formatting;
 mod passes;
+mod predicate;
 mod processor;
+mod workspace;
 
 pub use build::rustup_which;
+pub use predicate::Predicate;
 
 #[cfg(this_pulls_in_cargo_which_is_a_big_dep_i_dont_like_it)]
 mod expand;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use dylib_flag::RustFunction;
-use processor::Minimizer;
+use processor::{Minimizer, Step};
+use regex::Regex;
 use tracing::Level;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Registry};
 
-use crate::processor::Pass;
-
 // Export so that the user doesn't have to add clap themselves.
 pub use clap::Parser;
 
@@ -45,6 +52,13 @@ pub struct Options {
     #[arg(long, default_value = "build")]
     pub cargo_subcmd: String,
 
+    /// The cargo target to build, for issues that only reproduce with tests, benches or examples. One of `lib`,
+    /// `bins`, `bin=NAME`, `tests`, `test=NAME`, `benches`, `bench=NAME`, `examples` and `example=NAME`.
+    /// The files in the `tests`, `benches` or `examples` directory are minimized together with `path`.
+    /// Example: `--cargo-subcmd test --cargo-target test=repro`
+    #[arg(long, conflicts_with_all = ["rustc", "script_path"])]
+    pub cargo_target: Option<CargoTarget>,
+
     /// The cargo subcommand used to get diagnostics like the dead_code lint from the compiler, seperated by whitespace.
     /// Defaults to the value of `--cargo-subcmd`.
     #[arg(long)]
@@ -54,7 +68,13 @@ pub struct Options {
     #[arg(long)]
     pub no_color: bool,
 
+    /// Print a diff of every change that was kept.
+    #[arg(long)]
+    pub diff: bool,
+
     /// This option bypasses cargo and uses rustc directly. Only works when a single file is passed as an argument.
+    /// `--extra-args` are passed to rustc, for example `--extra-args='--emit=metadata -Zmir-opt-level=3'`.
+    /// The file is compiled as edition 2021, unless they contain `--edition`.
     #[arg(long)]
     pub rustc: bool,
 
@@ -68,12 +88,27 @@ pub struct Options {
     #[arg(long)]
     pub verify_fn: Option<RustFunction>,
 
+    /// A regular expression that has to match the stderr or stdout of the build for the regression to reproduce.
+    /// Use this to minimize a specific error instead of any ICE.
+    /// Example: `--regex='error\[E0308\]: mismatched types'`
+    #[arg(long)]
+    pub regex: Option<Regex>,
+
+    /// A condition on the output of the build that has to hold for the regression to reproduce, for regressions
+    /// that a single regex can't describe. It combines `exit == <code>` (or `!=`, `<`, `<=`, `>`, `>=`),
+    /// `stderr matches "<regex>"`, `stdout matches "<regex>"`, `output matches "<regex>"` and `ice`, which is
+    /// what reproduces without this option, with `&&`, `||`, `!` and parentheses.
+    /// Example: `--reproduces='exit == 101 && stderr matches "panicked at"'`
+    #[arg(long, conflicts_with_all = ["regex", "verify_fn"])]
+    pub reproduces: Option<Predicate>,
+
     /// Additional environment variables to pass to cargo/rustc.
     /// Example: `--env NAME=VALUE --env ANOTHER_NAME=VALUE`
     #[arg(long)]
     pub env: Vec<EnvVar>,
 
-    /// The working directory where cargo/rustc are invoked in. By default, this is the current working directory.
+    /// The working directory where cargo/rustc are invoked in. By default, this is the closest directory with a
+    /// `Cargo.toml`, starting at `path`, or the current working directory with `--rustc` or `--script-path`.
     #[arg(long)]
     pub project_dir: Option<PathBuf>,
 
@@ -81,32 +116,213 @@ pub struct Options {
     #[arg(default_value = "src")]
     pub path: PathBuf,
 
-    /// A comma-seperated list of passes that should be enabled. By default, all passes are enabled.
+    /// Minimize the `src` directories of all members of the workspace in the project directory together,
+    /// instead of `path`. The cargo subcommand gets `--workspace`, so every change is verified against all crates.
+    #[arg(long, conflicts_with = "rustc")]
+    pub workspace: bool,
+
+    /// A comma-separated list of the passes to run, in this order. By default, a few passes run that work
+    /// on any code, the other passes only run when they are given here. An unknown name lists all passes.
     #[arg(long)]
     pub passes: Option<String>,
 
-    /// A path to a script that is run to check whether code reproduces. When it exits with code 0, the
-    /// problem reproduces. If `--script-path-lints` isn't set, this script is also run to get lints.
+    /// Stop after this pass has finished and leave the code as it is at that point, to inspect what the
+    /// passes before it did. The passes still run in the order from `--passes`.
+    #[arg(long)]
+    pub stop_after: Option<String>,
+
+    /// Run all passes again as long as the last time made any changes, as later passes often make room for
+    /// earlier ones, like a deleted call that lets `item-deleter` delete the function. Stops after 10 cycles.
+    #[arg(long)]
+    pub fixpoint: bool,
+
+    /// A path to a script that is run to check whether code reproduces. When it exits with code 0 (or one
+    /// of `--reproduce-exit-codes`), the problem reproduces. If `--script-path-lints` isn't set, this script
+    /// is also run to get lints.
     /// For lints, the `MINIMIZE_LINTS` environment variable will be set to `1`.
     /// The first line of the lint stdout or stderr can be `minimize-fmt-rustc` or `minimize-fmt-cargo` to show whether the rustc or wrapper cargo
     /// lint format and which output stream is used. Defaults to cargo and stdout.
     #[arg(long)]
     pub script_path: Option<PathBuf>,
 
+    /// The exit codes of `--script-path` that mean that the problem reproduces, instead of just 0.
+    /// A comma-separated list of codes and inclusive ranges. Example: `--reproduce-exit-codes 101,1-10`
+    #[arg(long, requires = "script_path")]
+    pub reproduce_exit_codes: Option<ExitCodes>,
+
     /// A path to a script that is run to get lints.
     /// The first line of stdout or stderr must be `minimize-fmt-rustc` or `minimize-fmt-cargo` to show whether the rustc or wrapper cargo
     /// lint format and which output stream is used. Defaults to cargo and stdout.
     #[arg(long)]
     pub script_path_lints: Option<PathBuf>,
 
+    /// Kill the build after this many seconds. A build that timed out does not reproduce the issue
+    /// unless `--timeout-reproduces` is passed.
+    #[arg(long)]
+    pub timeout: Option<u64>,
+
+    /// Treat a build that timed out as reproducing the issue, for when the issue is a hang.
+    /// Note that `everybody-loops` replaces function bodies with `loop {}`, so after it almost
+    /// everything hangs. Consider `--body-replacement panic` or disabling it with `--passes`.
+    #[arg(long, requires = "timeout")]
+    pub timeout_reproduces: bool,
+
+    /// Only count a build as reproducing the issue if it reproduces this many times in a row.
+    /// Use this for flaky issues, so that changes that only reproduced by accident aren't kept.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub verify_loop: u32,
+
+    /// Stop processing a file in the current pass after this many rounds that changed the file without
+    /// making it smaller than it was before. This stops passes that would keep changing a file forever.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_rounds_without_progress: u32,
+
+    /// Stop the minimization after this many builds and keep the smallest code that reproduced so far. Builds
+    /// that are already running are finished, so with `--bisect-jobs` a few more builds can run.
+    #[arg(long)]
+    pub max_builds: Option<u64>,
+
+    /// Retry builds that fail to run, like cargo failing to download dependencies or a script that can't be
+    /// executed, with a backoff. If they keep failing, the build is treated as not reproducing instead of
+    /// aborting the minimization.
+    #[arg(long)]
+    pub keep_going_on_build_error: bool,
+
+    /// Expand all macros once before minimizing, like `cargo expand` does, and minimize the expanded code.
+    /// Macro-heavy crates become plain code that the passes handle well. The expansion is only kept if it
+    /// still reproduces the issue.
+    #[arg(long)]
+    pub expand: bool,
+
+    /// How many sets of candidates to build at the same time while bisecting a pass. Every build runs in
+    /// its own copy of the project directory, so scripts have to use paths relative to it.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub bisect_jobs: u32,
+
+    /// How many files to work on at the same time, if they don't use each other. The passes still run on one
+    /// file after another, but then one set of candidates of every file is built at the same time, each in its
+    /// own copy of the project directory like with `--bisect-jobs`.
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with = "bisect_jobs"
+    )]
+    pub file_jobs: u32,
+
+    /// Shuffle the candidates of every pass with this seed before bisecting them, instead of trying them in
+    /// the order of the code. Runs with different seeds can end up with different, sometimes smaller results.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// The order in which the files are minimized in every pass. Minimizing the files that are likely
+    /// irrelevant first finds big wins early.
+    #[arg(long, value_enum, default_value_t = FileOrder::Walk)]
+    pub file_order: FileOrder,
+
+    /// The expression that `everybody-loops` replaces function bodies with.
+    #[arg(long, value_enum, default_value_t = BodyReplacement::Loop)]
+    pub body_replacement: BodyReplacement,
+
+    /// Keep the original text of the top level items that a change doesn't touch, instead of formatting the
+    /// whole file. Only the changed items are formatted, so diffs stay small and errors that depend on line
+    /// numbers keep their lines.
+    #[arg(long)]
+    pub preserve_formatting: bool,
+
+    /// Only report how many candidates each pass collects in every file, without building or changing anything.
+    /// This is an estimate of the search space, as later rounds of a pass may find new candidates.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Save the progress to this file, so that the run can be resumed with `--resume` if it gets interrupted.
+    #[arg(long)]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Resume the run that saved its progress to `--checkpoint`.
+    /// Passes that have finished and files that have finished in the current pass are skipped.
+    #[arg(long, requires = "checkpoint")]
+    pub resume: bool,
+
+    /// Append every change that was kept to this file, so that the minimization can be replayed with
+    /// `--replay`.
+    #[arg(long)]
+    pub change_log: Option<PathBuf>,
+
+    /// Apply the changes from a `--change-log` of a previous run to the original code, without building.
+    /// This produces the same output as the run that recorded the log.
+    #[arg(long, conflicts_with_all = ["dry_run", "resume"])]
+    pub replay: Option<PathBuf>,
+
+    /// Mirror the smallest code that is known to reproduce the issue into this directory. It is updated after
+    /// every change that was kept, so it holds a usable reduction even if the minimization crashes or is killed.
+    #[arg(long)]
+    pub best_effort_output: Option<PathBuf>,
+
+    /// How to report the progress. `json` prints every event as a line of JSON to stdout, for tools that
+    /// wrap cargo-minimize. The logs are printed to stderr either way.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub output_format: OutputFormat,
+
+    /// Don't show a progress bar. It is only shown when stderr is a terminal and the output format is
+    /// `human`, it replaces the log line for every build.
+    #[arg(long)]
+    pub no_progress: bool,
+
     /// Do not touch the following files.
     #[arg(long)]
     pub ignore_file: Vec<PathBuf>,
 
+    /// Do not touch files that match this glob, relative to the project directory. Globs use the syntax
+    /// of `.gitignore` files, so a glob without a `/` matches in every directory.
+    /// Example: `--exclude 'src/generated/*.rs' --exclude vendored.rs`
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Only minimize files that match this glob, relative to the project directory. The whole project is
+    /// still built, but the other files are not touched. Uses the same syntax as `--exclude`.
+    /// Example: `--include src/index.rs`
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Also minimize files that are ignored by `.gitignore` files or are in `target` directories.
+    #[arg(long)]
+    pub no_ignore: bool,
+
     #[arg(skip)]
     pub no_delete_functions: bool,
 }
 
+/// A diverging expression that type checks as the body of every function.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyReplacement {
+    /// `loop {}`. Builds that run the code will hang.
+    Loop,
+    /// `panic!()`
+    Panic,
+    /// `unimplemented!()`
+    Unimplemented,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOrder {
+    /// The order in which the files are found in the directory.
+    Walk,
+    /// The largest files first.
+    Largest,
+    /// Files that the output of the initial build doesn't mention first, the largest first.
+    UnmentionedFirst,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Only the logs.
+    Human,
+    /// Newline-delimited JSON events: `pass-started`, `build`, `change-committed`, `change-rolled-back`,
+    /// `file-processed` and `pass-finished`.
+    Json,
+}
+
 #[derive(Debug, Clone)]
 pub struct EnvVar {
     pub key: String,
@@ -116,39 +332,316 @@ pub struct EnvVar {
 impl FromStr for EnvVar {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut split = s.split('=');
-        let key = split
-            .next()
-            .ok_or("env var must have KEY=VALUE format")?
-            .to_string();
-        let value = split
-            .next()
-            .ok_or("env var must have KEY=VALUE format")?
-            .to_string();
-        Ok(Self { key, value })
+        // Only the first `=` separates the key, values like `RUSTFLAGS=-Cdebuginfo=2` contain more.
+        let (key, value) = s
+            .split_once('=')
+            .ok_or("env var must have KEY=VALUE format")?;
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Exit codes, like `101,1-10`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitCodes(Vec<RangeInclusive<i32>>);
+
+impl ExitCodes {
+    pub fn contains(&self, code: i32) -> bool {
+        self.0.iter().any(|range| range.contains(&code))
+    }
+}
+
+impl Display for ExitCodes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, range) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            match range.start() == range.end() {
+                true => write!(f, "{}", range.start())?,
+                false => write!(f, "{}-{}", range.start(), range.end())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ExitCodes {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |code: &str| {
+            code.trim()
+                .parse::<i32>()
+                .map_err(|err| format!("invalid exit code `{code}`: {err}"))
+        };
+        s.split(',')
+            .map(|part| {
+                // Split at a `-` after the first character, so that negative codes still work.
+                match part.char_indices().skip(1).find(|&(_, c)| c == '-') {
+                    Some((i, _)) => Ok(parse(&part[..i])?..=parse(&part[i + 1..])?),
+                    None => parse(part).map(|code| code..=code),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// The cargo targets that `--cargo-target` selects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CargoTarget {
+    Lib,
+    Bins,
+    Bin(String),
+    Tests,
+    Test(String),
+    Benches,
+    Bench(String),
+    Examples,
+    Example(String),
+}
+
+impl CargoTarget {
+    /// The arguments that select the target.
+    pub(crate) fn args(&self) -> Vec<String> {
+        let (flag, name) = match self {
+            Self::Lib => ("--lib", None),
+            Self::Bins => ("--bins", None),
+            Self::Bin(name) => ("--bin", Some(name)),
+            Self::Tests => ("--tests", None),
+            Self::Test(name) => ("--test", Some(name)),
+            Self::Benches => ("--benches", None),
+            Self::Bench(name) => ("--bench", Some(name)),
+            Self::Examples => ("--examples", None),
+            Self::Example(name) => ("--example", Some(name)),
+        };
+        std::iter::once(flag.to_owned())
+            .chain(name.cloned())
+            .collect()
+    }
+
+    /// The directory of the package that contains the sources of the target, if they aren't in `src`.
+    pub(crate) fn dir(&self) -> Option<&'static str> {
+        match self {
+            Self::Lib | Self::Bins | Self::Bin(_) => None,
+            Self::Tests | Self::Test(_) => Some("tests"),
+            Self::Benches | Self::Bench(_) => Some("benches"),
+            Self::Examples | Self::Example(_) => Some("examples"),
+        }
+    }
+}
+
+impl FromStr for CargoTarget {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = match s.split_once('=') {
+            Some((kind, name)) => (kind, Some(name.to_owned())),
+            None => (s, None),
+        };
+        Ok(match (kind, name) {
+            ("lib", None) => Self::Lib,
+            ("bins", None) => Self::Bins,
+            ("bin", Some(name)) => Self::Bin(name),
+            ("tests", None) => Self::Tests,
+            ("test", Some(name)) => Self::Test(name),
+            ("benches", None) => Self::Benches,
+            ("bench", Some(name)) => Self::Bench(name),
+            ("examples", None) => Self::Examples,
+            ("example", Some(name)) => Self::Example(name),
+            _ => {
+                return Err(format!(
+                "invalid cargo target `{s}`, expected something like `lib`, `tests` or `test=NAME`"
+            ))
+            }
+        })
+    }
+}
+
+/// Source files, keyed by their path relative to the project directory.
+pub type Sources = BTreeMap<PathBuf, String>;
+
+pub fn minimize(mut options: Options, stop: Arc<AtomicBool>) -> Result<()> {
+    detect_project_dir(&mut options)?;
+    config::apply(&mut options)?;
+    let build = build::Build::new(&options)?;
+    minimize_with(options, build, stop)
+}
+
+/// Sets the project directory to the closest directory with a `Cargo.toml` around `path` if it wasn't passed.
+/// With `--workspace`, the search starts in the current directory instead, as `path` isn't used then.
+fn detect_project_dir(options: &mut Options) -> Result<()> {
+    if options.project_dir.is_some() {
+        return Ok(());
+    }
+    let current_dir = std::env::current_dir().context("getting the current directory")?;
+    let start = if options.workspace {
+        current_dir.clone()
+    } else {
+        current_dir.join(&options.path)
+    };
+
+    match start
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+    {
+        Some(dir) => {
+            if dir != current_dir {
+                info!("Using the project directory {}", dir.display());
+            }
+            options.project_dir = Some(dir.to_owned());
+        }
+        // Neither of them needs a cargo project, they run in the current directory.
+        None if options.rustc || options.script_path.is_some() => {}
+        None => bail!(
+            "Could not find a Cargo.toml in {} or any of its parent directories, pass the directory of the \
+             project with --project-dir",
+            start.display()
+        ),
+    }
+    Ok(())
+}
+
+/// Minimizes the project like [`minimize`], but instead of building, `reproduces` is called with the
+/// project directory to check whether it still reproduces the issue. For parallel builds, it is called with
+/// a copy of the project directory that has the sources of the build.
+pub fn minimize_with_fn(
+    options: Options,
+    reproduces: Box<dyn Fn(&Path) -> bool>,
+    stop: Arc<AtomicBool>,
+) -> Result<()> {
+    let build = build::Build::from_fn(&options, build::ReproducesFn::on_path(reproduces));
+    minimize_with(options, build, stop)
+}
+
+/// Minimizes in-memory sources, for using cargo-minimize as a library. Instead of building, `reproduces`
+/// is called with the current sources to check whether they still reproduce the issue.
+/// The sources are written to a temporary directory, `path` and `project_dir` of the options are ignored.
+/// Returns the minimized sources, without the files that were deleted.
+pub fn minimize_sources(
+    sources: &Sources,
+    options: Options,
+    reproduces: impl Fn(&Sources) -> bool + 'static,
+) -> Result<Sources> {
+    let dir = tempfile::tempdir().context("creating temporary directory")?;
+    for (path, content) in sources {
+        let path = dir.path().join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content).with_context(|| format!("writing {}", path.display()))?;
     }
+
+    let options = Options {
+        path: dir.path().to_owned(),
+        project_dir: Some(dir.path().to_owned()),
+        ..options
+    };
+    let build = build::Build::from_fn(&options, build::ReproducesFn::new(reproduces));
+    minimize_with(options, build, Arc::new(AtomicBool::new(false)))?;
+
+    sources
+        .keys()
+        .filter_map(
+            |path| match std::fs::read_to_string(dir.path().join(path)) {
+                Ok(content) => Some(Ok((path.clone(), content))),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => Some(Err(err.into())),
+            },
+        )
+        .collect()
 }
 
-pub fn minimize(options: Options, stop: Arc<AtomicBool>) -> Result<()> {
+fn minimize_with(options: Options, build: build::Build, stop: Arc<AtomicBool>) -> Result<()> {
     for ignore_file in &options.ignore_file {
         if !ignore_file.try_exists()? {
             warn!("Ignored path {} does not exist", ignore_file.display());
         }
     }
 
-    let build = build::Build::new(&options)?;
+    let pass_names = match &options.passes {
+        Some(passes) => passes
+            .split(',')
+            .map(|name| name.trim().to_owned())
+            .collect(),
+        None => passes::PASSES
+            .iter()
+            .filter(|entry| entry.default)
+            .map(|entry| entry.name.to_owned())
+            .collect::<Vec<_>>(),
+    };
+    let mut steps = Vec::new();
+    for name in &pass_names {
+        let Some(step) = passes::by_name(name) else {
+            bail!(
+                "Unknown pass `{name}`. Available passes: {}",
+                passes::PASSES
+                    .iter()
+                    .map(|entry| entry.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        };
+        steps.push(step);
+    }
 
+    if let Some(stop_after) = &options.stop_after {
+        if !pass_names.contains(stop_after) {
+            bail!(
+                "Cannot stop after `{stop_after}`, it is not one of the passes that run: {}",
+                pass_names.join(", ")
+            );
+        }
+    }
+
+    let dry_run = options.dry_run;
+    // When resuming, the files on disk are expanded already.
+    let expand = options.expand && !options.resume;
+    let replay = options.replay.clone();
     let mut minimizer = Minimizer::new_glob_dir(options, build, stop)?;
 
-    minimizer.run_passes([
-        passes::EverybodyLoops::default().boxed(),
-        passes::FieldDeleter::default().boxed(),
-        passes::Privatize::default().boxed(),
-    ])?;
+    if let Some(log) = replay {
+        minimizer.replay(&log).context("replaying change log")?;
+        info!("{}", minimizer.summary());
+        return Ok(());
+    }
+
+    if dry_run {
+        for step in &mut steps {
+            let name = step.name();
+            let pass = match step {
+                Step::Pass(pass) => pass,
+                Step::DeleteUnusedFunctions => {
+                    info!("{name}: Candidates come from the compiler diagnostics and can't be counted without building");
+                    continue;
+                }
+                Step::FlattenCrate => {
+                    info!("{name}: Merges all files at once and has no candidates");
+                    continue;
+                }
+            };
+            let counts = minimizer.count_candidates(&mut **pass)?;
+            for (file, count) in &counts {
+                info!("{name}: {file:?}: {count} candidates");
+            }
+            let total = counts.iter().map(|(_, count)| count).sum::<usize>();
+            info!("{name}: {total} candidates in total");
+        }
+        return Ok(());
+    }
+
+    minimizer.start(&pass_names)?;
+    if expand {
+        minimizer.expand_macros().context("expanding macros")?;
+    }
+    minimizer.order_files().context("ordering files")?;
 
-    minimizer.delete_dead_code().context("deleting dead code")?;
+    minimizer.run_passes(steps)?;
 
-    minimizer.run_passes([passes::ItemDeleter::default().boxed()])?;
+    minimizer.validate()?;
+
+    info!("{}", minimizer.summary());
 
     Ok(())
 }
@@ -174,18 +667,243 @@ impl Default for Options {
             extra_args: None,
             cargo_subcmd: "build".into(),
             cargo_subcmd_lints: None,
+            cargo_target: None,
             no_color: false,
+            diff: false,
             rustc: false,
             no_verify: false,
             verify_fn: None,
+            regex: None,
+            reproduces: None,
             env: Vec::new(),
             project_dir: None,
             path: PathBuf::from("/the/wrong/path/you/need/to/change/it"),
+            workspace: false,
             passes: None,
+            stop_after: None,
+            fixpoint: false,
             script_path: None,
+            reproduce_exit_codes: None,
             script_path_lints: None,
+            timeout: None,
+            timeout_reproduces: false,
+            verify_loop: 1,
+            max_rounds_without_progress: 5,
+            max_builds: None,
+            bisect_jobs: 1,
+            file_jobs: 1,
+            seed: None,
+            file_order: FileOrder::Walk,
+            keep_going_on_build_error: false,
+            expand: false,
+            body_replacement: BodyReplacement::Loop,
+            preserve_formatting: false,
+            dry_run: false,
+            checkpoint: None,
+            resume: false,
+            change_log: None,
+            replay: None,
+            best_effort_output: None,
+            output_format: OutputFormat::Human,
+            no_progress: false,
             ignore_file: Vec::new(),
+            exclude: Vec::new(),
+            include: Vec::new(),
+            no_ignore: false,
             no_delete_functions: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        fs,
+        path::PathBuf,
+        rc::Rc,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use crate::{detect_project_dir, minimize_sources, minimize_with_fn, Options, Sources};
+
+    #[test]
+    fn minimize_in_memory() -> anyhow::Result<()> {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "fn main() {\n    let a = 1;\n    \"needed\";\n}\n\npub fn unused() {}\n".to_owned(),
+        )]);
+
+        let options = Options {
+            passes: Some("delete-statements,item-deleter".to_owned()),
+            ..Options::default()
+        };
+        let minimized = minimize_sources(&sources, options, |sources| {
+            sources.values().any(|content| content.contains("needed"))
+        })?;
+
+        let content = &minimized[&main];
+        assert!(content.contains("needed"));
+        assert!(!content.contains("let a"));
+        assert!(!content.contains("unused"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stops_after_pass() -> anyhow::Result<()> {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "pub fn needed() {}\n\nfn main() {\n    needed();\n}\n".to_owned(),
+        )]);
+        let minimize = |stop_after: Option<&str>| {
+            let options = Options {
+                passes: Some("privatize,remove-visibility".to_owned()),
+                stop_after: stop_after.map(ToOwned::to_owned),
+                ..Options::default()
+            };
+            minimize_sources(&sources, options, |sources| {
+                sources
+                    .values()
+                    .any(|content| content.contains("fn needed"))
+            })
+        };
+
+        // `remove-visibility` runs after `privatize` and would make the function private.
+        let content = &minimize(Some("privatize"))?[&main];
+        assert!(content.contains("pub(crate) fn needed"), "{content}");
+        assert!(content.contains("fn main"), "{content}");
+
+        let content = &minimize(None)?[&main];
+        assert!(!content.contains("pub(crate)"), "{content}");
+
+        assert!(minimize(Some("unknown-pass")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fixpoint_runs_passes_again() -> anyhow::Result<()> {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "fn helper() {}\n\nfn main() {\n    helper();\n}\n".to_owned(),
+        )]);
+        let minimize = |fixpoint| {
+            let options = Options {
+                passes: Some("item-deleter,delete-statements".to_owned()),
+                fixpoint,
+                ..Options::default()
+            };
+            // `helper` can only be deleted once nothing calls it anymore.
+            minimize_sources(&sources, options, |sources| {
+                sources.values().any(|content| {
+                    content.contains("fn main")
+                        && (!content.contains("helper()") || content.contains("fn helper"))
+                })
+            })
+        };
+
+        let content = &minimize(false)?[&main];
+        assert!(content.contains("fn helper"), "{content}");
+        assert!(!content.contains("helper();"), "{content}");
+
+        let content = &minimize(true)?[&main];
+        assert!(!content.contains("helper"), "{content}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn stops_after_max_builds() -> anyhow::Result<()> {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "fn main() {\n    let a = 1;\n    let b = 2;\n    \"needed\";\n}\n\npub fn unused() {}\n"
+                .to_owned(),
+        )]);
+        let builds = Rc::new(Cell::new(0));
+
+        let options = Options {
+            max_builds: Some(3),
+            ..Options::default()
+        };
+        let minimized = minimize_sources(&sources, options, {
+            let builds = builds.clone();
+            move |sources| {
+                builds.set(builds.get() + 1);
+                sources.values().any(|content| content.contains("needed"))
+            }
+        })?;
+
+        // The initial build and two more, the final validation comes from the cache.
+        assert_eq!(builds.get(), 3);
+        let content = &minimized[&main];
+        assert!(content.contains("needed"), "{content}");
+        assert!(content.contains("let b"), "{content}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn minimize_with_closure() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let main = dir.path().join("src/main.rs");
+        fs::create_dir_all(main.parent().unwrap())?;
+        fs::write(
+            &main,
+            "fn main() {\n    let a = 1;\n    \"needed\";\n}\n\npub fn unused() {}\n",
+        )?;
+
+        let options = Options {
+            path: dir.path().to_owned(),
+            project_dir: Some(dir.path().to_owned()),
+            passes: Some("delete-statements,item-deleter".to_owned()),
+            ..Options::default()
+        };
+        minimize_with_fn(
+            options,
+            Box::new(|dir| {
+                fs::read_to_string(dir.join("src/main.rs"))
+                    .is_ok_and(|content| content.contains("needed"))
+            }),
+            Arc::new(AtomicBool::new(false)),
+        )?;
+
+        let content = fs::read_to_string(&main)?;
+        assert!(content.contains("needed"), "{content}");
+        assert!(!content.contains("let a"), "{content}");
+        assert!(!content.contains("unused"), "{content}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn detects_project_dir_from_subdirectory() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let nested = dir.path().join("src/nested");
+        fs::create_dir_all(&nested)?;
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n",
+        )?;
+
+        let mut options = Options {
+            path: nested.clone(),
+            ..Options::default()
+        };
+        detect_project_dir(&mut options)?;
+        assert_eq!(options.project_dir.as_deref(), Some(dir.path()));
+
+        fs::remove_file(dir.path().join("Cargo.toml"))?;
+        let mut options = Options {
+            path: nested,
+            ..Options::default()
+        };
+        assert!(detect_project_dir(&mut options).is_err());
+
+        Ok(())
+    }
+}
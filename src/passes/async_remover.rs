@@ -0,0 +1,139 @@
+//! Removes `async` from functions, together with the `.await`s on calls to them in the same file.
+//! The build rejects the functions that really need to be `async`, like the ones that `.await` something
+//! themselves or are called from another file.
+
+use std::collections::HashSet;
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Expr, ExprAwait, ImplItemMethod, ItemFn, Signature};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    /// The names of the functions that aren't `async` anymore.
+    removed: HashSet<String>,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            removed: HashSet::new(),
+        }
+    }
+
+    /// Called with the name of the function already in the path.
+    fn remove_async(&mut self, sig: &mut Signature) {
+        if sig.asyncness.is_some() && self.checker.can_process(&self.current_path) {
+            sig.asyncness = None;
+            self.removed.insert(sig.ident.to_string());
+            self.process_state = ProcessState::Changed;
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        self.current_path.push(func.sig.ident.to_string());
+        self.remove_async(&mut func.sig);
+        syn::visit_mut::visit_item_fn_mut(self, func);
+        self.current_path.pop();
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        self.current_path.push(method.sig.ident.to_string());
+        self.remove_async(&mut method.sig);
+        syn::visit_mut::visit_impl_item_method_mut(self, method);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+/// Removes the `.await`s on calls to the functions that aren't `async` anymore. Functions are matched by
+/// name only, the build complains about the ones that were awaited on something else.
+struct RemoveAwaits<'a> {
+    removed: &'a HashSet<String>,
+}
+
+impl VisitMut for RemoveAwaits<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        syn::visit_mut::visit_expr_mut(self, expr);
+
+        let Expr::Await(ExprAwait { base, .. }) = expr else {
+            return;
+        };
+        let name = match &**base {
+            Expr::Call(call) => match &*call.func {
+                Expr::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+                _ => None,
+            },
+            Expr::MethodCall(call) => Some(call.method.to_string()),
+            _ => None,
+        };
+        if name.is_some_and(|name| self.removed.contains(&name)) {
+            *expr = (**base).clone();
+        }
+    }
+}
+
+fn remove_async(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+
+    if !visitor.removed.is_empty() {
+        RemoveAwaits {
+            removed: &visitor.removed,
+        }
+        .visit_file_mut(krate);
+    }
+
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct AsyncRemover;
+
+impl Pass for AsyncRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_async(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-async"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_async;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_async)
+    }
+
+    #[test]
+    fn trivial_async_fn_becomes_sync() {
+        assert_eq!(
+            remove(
+                "async fn answer() -> u8 { 42 } struct S; impl S { async fn run(&self) -> u8 { answer().await } } async fn main() { S.run().await; }"
+            ),
+            tokens(
+                "fn answer() -> u8 { 42 } struct S; impl S { fn run(&self) -> u8 { answer() } } fn main() { S.run(); }"
+            )
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Removes attributes that are often irrelevant to the issue, like `#[inline]` or `#[repr(C)]`.
+//! Every attribute is a separate candidate, so the ones that are needed for the reproduction are kept.
+//! Only the attributes in `STRIPPABLE` are tried, other attributes like `#[cfg]`, `#[derive]` and doc comments
+//! are handled by other passes or are left alone. Lint attributes like `#[allow]` are also tried on `let`
+//! statements, where they are often left over after the code that they were about is gone.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Attribute, Field, ImplItem, Item, Local, TraitItem, Variant};
+
+use super::cfg_stripper::{item_attrs, item_name};
+use crate::processor::{Pass, PassController, ProcessState, SourceFile};
+
+const STRIPPABLE: &[&str] = &[
+    "allow",
+    "cold",
+    "deny",
+    "deprecated",
+    "expect",
+    "export_name",
+    "forbid",
+    "inline",
+    "link_section",
+    "must_use",
+    "no_mangle",
+    "non_exhaustive",
+    "repr",
+    "track_caller",
+    "used",
+    "warn",
+];
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn strip_attributes(&mut self, attrs: &mut Vec<Attribute>) {
+        attrs.retain(|attr| {
+            let strippable = attr
+                .path
+                .get_ident()
+                .is_some_and(|ident| STRIPPABLE.iter().any(|name| ident == name));
+            if !strippable {
+                return true;
+            }
+
+            self.current_path.push(attr.to_token_stream().to_string());
+            let can_process = self.checker.can_process(&self.current_path);
+            self.current_path.pop();
+
+            if can_process {
+                self.process_state = ProcessState::Changed;
+            }
+            !can_process
+        });
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.strip_attributes(&mut file.attrs);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        self.current_path.push(item_name(item));
+        if let Some(attrs) = item_attrs(item) {
+            self.strip_attributes(attrs);
+        }
+        syn::visit_mut::visit_item_mut(self, item);
+        self.current_path.pop();
+    }
+
+    fn visit_impl_item_mut(&mut self, item: &mut ImplItem) {
+        let (name, attrs) = match item {
+            ImplItem::Const(const_) => (const_.ident.to_string(), &mut const_.attrs),
+            ImplItem::Method(method) => (method.sig.ident.to_string(), &mut method.attrs),
+            ImplItem::Type(type_) => (type_.ident.to_string(), &mut type_.attrs),
+            _ => return syn::visit_mut::visit_impl_item_mut(self, item),
+        };
+        self.current_path.push(name);
+        self.strip_attributes(attrs);
+        syn::visit_mut::visit_impl_item_mut(self, item);
+        self.current_path.pop();
+    }
+
+    fn visit_trait_item_mut(&mut self, item: &mut TraitItem) {
+        let (name, attrs) = match item {
+            TraitItem::Const(const_) => (const_.ident.to_string(), &mut const_.attrs),
+            TraitItem::Method(method) => (method.sig.ident.to_string(), &mut method.attrs),
+            TraitItem::Type(type_) => (type_.ident.to_string(), &mut type_.attrs),
+            _ => return syn::visit_mut::visit_trait_item_mut(self, item),
+        };
+        self.current_path.push(name);
+        self.strip_attributes(attrs);
+        syn::visit_mut::visit_trait_item_mut(self, item);
+        self.current_path.pop();
+    }
+
+    fn visit_field_mut(&mut self, field: &mut Field) {
+        if let Some(ident) = &field.ident {
+            self.current_path.push(ident.to_string());
+            self.strip_attributes(&mut field.attrs);
+            self.current_path.pop();
+        }
+        syn::visit_mut::visit_field_mut(self, field);
+    }
+
+    fn visit_local_mut(&mut self, local: &mut Local) {
+        self.current_path
+            .push(format!("let {}", local.pat.to_token_stream()));
+        self.strip_attributes(&mut local.attrs);
+        syn::visit_mut::visit_local_mut(self, local);
+        self.current_path.pop();
+    }
+
+    fn visit_variant_mut(&mut self, variant: &mut Variant) {
+        self.current_path.push(variant.ident.to_string());
+        self.strip_attributes(&mut variant.attrs);
+        syn::visit_mut::visit_variant_mut(self, variant);
+        self.current_path.pop();
+    }
+}
+
+fn strip_attributes(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct AttributeStripper;
+
+impl Pass for AttributeStripper {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        strip_attributes(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "strip-attributes"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_attributes;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn strip(src: &str) -> String {
+        apply_all(src, strip_attributes)
+    }
+
+    #[test]
+    fn strips_lint_attributes() {
+        assert_eq!(
+            strip(
+                "#![allow(internal_features)] \
+                 #[allow(dead_code)] fn unused() { #[expect(unused_variables)] let x = 1; } \
+                 #[derive(Debug)] #[deny(missing_docs)] struct S;"
+            ),
+            tokens("fn unused() { let x = 1; } #[derive(Debug)] struct S;")
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Simplifies bindings. The `mut` of a binding or a `self` receiver is a candidate for being removed, as is the
+//! `ref` of a binding. Whether the binding still has to be mutable, or the value can be moved instead of
+//! borrowed, is left to the build.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, PatIdent, Receiver};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_pat_ident_mut(&mut self, pat: &mut PatIdent) {
+        let ident = pat.ident.to_string();
+        if pat.mutability.is_some() && self.can_process(format!("mut {ident}")) {
+            pat.mutability = None;
+        }
+        if pat.by_ref.is_some() && self.can_process(format!("ref {ident}")) {
+            pat.by_ref = None;
+        }
+
+        syn::visit_mut::visit_pat_ident_mut(self, pat);
+    }
+
+    fn visit_receiver_mut(&mut self, receiver: &mut Receiver) {
+        // The `mut` of `&mut self` is part of the type, not of the binding.
+        if receiver.reference.is_none()
+            && receiver.mutability.is_some()
+            && self.can_process("mut self".to_owned())
+        {
+            receiver.mutability = None;
+        }
+
+        syn::visit_mut::visit_receiver_mut(self, receiver);
+    }
+
+    tracking!();
+}
+
+fn simplify_bindings(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct BindingSimplifier;
+
+impl Pass for BindingSimplifier {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        simplify_bindings(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "simplify-bindings"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simplify_bindings;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn simplify(src: &str) -> String {
+        apply_all(src, simplify_bindings)
+    }
+
+    #[test]
+    fn removes_mut_and_ref() {
+        assert_eq!(
+            simplify(
+                "fn f(mut a: u8) { let mut x = a; match &x { ref y => {} } } \
+                 impl S { fn g(mut self, other: &mut Self) {} fn h(&mut self) {} }"
+            ),
+            tokens(
+                "fn f(a: u8) { let x = a; match &x { y => {} } } \
+                 impl S { fn g(self, other: &mut Self) {} fn h(&mut self) {} }"
+            )
+        );
+    }
+}
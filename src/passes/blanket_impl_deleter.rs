@@ -0,0 +1,136 @@
+//! Deletes generic impls, like the blanket `impl<T: Display> Trait for T`. They apply to many types, so they are
+//! often the cause of an issue or the noise around it, and removing them early makes the rest of the code
+//! cheaper to build. Impls that are only generic over lifetimes are left to `item-deleter`.
+
+use syn::{visit_mut::VisitMut, GenericParam, Item, ItemImpl, Type};
+
+use super::item_deleter::impl_name;
+use crate::processor::{Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn retain(&mut self, impl_: &ItemImpl) -> bool {
+        let type_params = impl_
+            .generics
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                GenericParam::Type(param) => Some(&param.ident),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if type_params.is_empty() {
+            return true;
+        }
+        // The candidates tell blanket impls apart from the other generic impls, for the logs.
+        let is_blanket = matches!(&*impl_.self_ty, Type::Path(ty) if ty.qself.is_none()
+            && type_params.iter().any(|param| ty.path.is_ident(*param)));
+        let kind = if is_blanket {
+            "blanket impl"
+        } else {
+            "generic impl"
+        };
+
+        self.current_path.push(kind.to_owned());
+        self.current_path.push(impl_name(impl_));
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        !can_process
+    }
+
+    fn delete_impls(&mut self, items: &mut Vec<Item>) {
+        items.retain(|item| match item {
+            Item::Impl(impl_) => self.retain(impl_),
+            _ => true,
+        });
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.delete_impls(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+        if let Some((_, items)) = &mut module.content {
+            self.delete_impls(items);
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block.stmts.retain(|stmt| match stmt {
+            syn::Stmt::Item(Item::Impl(impl_)) => self.retain(impl_),
+            _ => true,
+        });
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+}
+
+fn delete_blanket_impls(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct BlanketImplDeleter;
+
+impl Pass for BlanketImplDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        delete_blanket_impls(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-blanket-impls"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::delete_blanket_impls;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn delete(src: &str) -> String {
+        apply_all(src, delete_blanket_impls)
+    }
+
+    #[test]
+    fn deletes_generic_impls() {
+        assert_eq!(
+            delete(
+                "trait Tr {} \
+                 impl<T: std::fmt::Display> Tr for T {} \
+                 impl<T> Tr for Vec<T> {} \
+                 impl<'a> Tr for &'a str {} \
+                 impl Tr for u8 {}"
+            ),
+            tokens("trait Tr {} impl<'a> Tr for &'a str {} impl Tr for u8 {}")
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, GenericParam};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn should_retain(&mut self, name: String) -> bool {
+        self.current_path.push(name);
+        let can_process = self.checker.can_process(&self.current_path);
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        self.current_path.pop();
+        !can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_generics_mut(&mut self, generics: &mut syn::Generics) {
+        for param in &mut generics.params {
+            if let GenericParam::Type(ty) = param {
+                let name = ty.ident.to_string();
+                ty.bounds = std::mem::take(&mut ty.bounds)
+                    .into_iter()
+                    .filter(|bound| {
+                        self.should_retain(format!("{name}: {}", bound.to_token_stream()))
+                    })
+                    .collect();
+                if ty.bounds.is_empty() {
+                    ty.colon_token = None;
+                }
+            }
+        }
+
+        if let Some(where_clause) = &mut generics.where_clause {
+            where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+                .into_iter()
+                .filter(|predicate| {
+                    self.should_retain(format!("where {}", predicate.to_token_stream()))
+                })
+                .collect();
+            if where_clause.predicates.is_empty() {
+                generics.where_clause = None;
+            }
+        }
+
+        syn::visit_mut::visit_generics_mut(self, generics);
+    }
+
+    tracking!();
+}
+
+#[derive(Default)]
+pub struct BoundsSimplifier;
+
+impl Pass for BoundsSimplifier {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "simplify-bounds"
+    }
+}
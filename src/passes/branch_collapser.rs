@@ -0,0 +1,143 @@
+//! Replaces `if` and `match` expressions with one of their branches, so only the path that triggers the
+//! issue is left. Every branch is a candidate, if several are chosen the first one wins.
+//! The branch is put into a block, which has the same type as the `if`/`match` that it came from. Arms that
+//! use bindings of their pattern don't build on their own and are rejected by the build.
+
+use quote::ToTokens;
+use syn::{parse_quote, visit_mut::VisitMut, Expr, ExprBlock, ExprIf};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    /// Asks about every branch, so that all of them are collected, and returns the first one that may be
+    /// taken.
+    fn choose(&mut self, branches: Vec<(String, Expr)>) -> Option<Expr> {
+        let mut chosen = None;
+        for (name, branch) in branches {
+            self.current_path.push(name);
+            let can_process = self.checker.can_process(&self.current_path);
+            self.current_path.pop();
+            if can_process && chosen.is_none() {
+                chosen = Some(branch);
+            }
+        }
+        chosen
+    }
+
+    fn if_branches(if_: &ExprIf) -> Vec<(String, Expr)> {
+        let cond = if_.cond.to_token_stream().to_string();
+        let then = &if_.then_branch;
+        let mut branches = vec![(format!("if {cond} then"), parse_quote!(#then))];
+        if let Some((_, else_)) = &if_.else_branch {
+            // `else if` stays an `if`, it is collapsed itself in the next round.
+            branches.push((format!("if {cond} else"), block(else_)));
+        }
+        branches
+    }
+}
+
+/// The expression as a block, so that it can replace the `if`/`match` in any position.
+fn block(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Block(ExprBlock {
+            attrs, label: None, ..
+        }) if attrs.is_empty() => expr.clone(),
+        _ => parse_quote!({ #expr }),
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        // The branches are visited first, so that the names of the candidates don't depend on the branches
+        // around them.
+        syn::visit_mut::visit_expr_mut(self, expr);
+
+        let branches = match expr {
+            Expr::If(if_) => Self::if_branches(if_),
+            Expr::Match(match_) => {
+                let scrutinee = match_.expr.to_token_stream().to_string();
+                match_
+                    .arms
+                    .iter()
+                    .map(|arm| {
+                        let pat = arm.pat.to_token_stream();
+                        (format!("match {scrutinee} => {pat}"), block(&arm.body))
+                    })
+                    .collect()
+            }
+            _ => return,
+        };
+
+        if let Some(branch) = self.choose(branches) {
+            *expr = branch;
+            self.process_state = ProcessState::Changed;
+        }
+    }
+
+    tracking!();
+}
+
+#[derive(Default)]
+pub struct BranchCollapser;
+
+impl Pass for BranchCollapser {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "collapse-branches"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{minimize_sources, Options, Sources};
+
+    #[test]
+    fn collapses_match_to_arm_that_matters() {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "fn main() {\n    match 1 {\n        0 => {\n            \"zero\";\n        }\n        _ => {\n            \"needed\";\n        }\n    }\n}\n"
+                .to_owned(),
+        )]);
+        let options = Options {
+            passes: Some("collapse-branches".to_owned()),
+            ..Options::default()
+        };
+
+        let minimized = minimize_sources(&sources, options, |sources| {
+            sources[&PathBuf::from("src/main.rs")].contains("\"needed\"")
+        })
+        .unwrap();
+
+        let content = &minimized[&main];
+        assert!(!content.contains("match"), "{content}");
+        assert!(!content.contains("zero"), "{content}");
+        assert!(content.contains("\"needed\";"), "{content}");
+    }
+}
@@ -0,0 +1,303 @@
+//! Deletes items that are disabled by a `#[cfg]` and removes the `#[cfg]` from items that are enabled.
+//! Which cfgs are enabled is found out with `rustc --print cfg`. Cfgs that we can't know, like features that
+//! `strip-features` left behind, are treated as disabled and deleting the item is up to the build to verify.
+
+use std::{collections::HashSet, process::Command};
+
+use anyhow::{ensure, Context, Result};
+use quote::ToTokens;
+use syn::{
+    visit_mut::VisitMut, Attribute, ImplItem, Item, ItemImpl, Lit, Meta, MetaNameValue, NestedMeta,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+/// These depend on how cargo invokes rustc, so `rustc --print cfg` doesn't tell us whether they are set.
+const UNKNOWN_CFGS: &[&str] = &["feature", "debug_assertions"];
+
+/// The cfgs that are set for the host target.
+#[derive(Debug, Default)]
+struct ActiveCfgs(HashSet<(String, Option<String>)>);
+
+impl ActiveCfgs {
+    fn from_rustc() -> Result<Self> {
+        let rustc = crate::build::rustup_which("rustc")?;
+        let output = Command::new(rustc)
+            .args(["--print", "cfg"])
+            .output()
+            .context("running rustc --print cfg")?;
+        ensure!(output.status.success(), "rustc --print cfg failed");
+
+        let cfgs = String::from_utf8(output.stdout)?
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.trim_matches('"').to_owned())),
+                None => (line.to_owned(), None),
+            })
+            .collect();
+        Ok(Self(cfgs))
+    }
+
+    /// Evaluates a cfg predicate. Returns `None` if we don't know whether it's enabled.
+    fn eval(&self, predicate: &NestedMeta) -> Option<bool> {
+        match predicate {
+            NestedMeta::Meta(Meta::Path(path)) => {
+                let name = path.get_ident()?.to_string();
+                if UNKNOWN_CFGS.contains(&name.as_str()) {
+                    return None;
+                }
+                Some(self.0.contains(&(name, None)))
+            }
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Str(value),
+                ..
+            })) => {
+                let name = path.get_ident()?.to_string();
+                if UNKNOWN_CFGS.contains(&name.as_str()) {
+                    return None;
+                }
+                Some(self.0.contains(&(name, Some(value.value()))))
+            }
+            NestedMeta::Meta(Meta::List(list)) => {
+                let values = list
+                    .nested
+                    .iter()
+                    .map(|predicate| self.eval(predicate))
+                    .collect::<Vec<_>>();
+                if list.path.is_ident("all") {
+                    all(&values)
+                } else if list.path.is_ident("any") {
+                    all(&values
+                        .iter()
+                        .map(|value| value.map(|v| !v))
+                        .collect::<Vec<_>>())
+                    .map(|none_enabled| !none_enabled)
+                } else if list.path.is_ident("not") && values.len() == 1 {
+                    values[0].map(|value| !value)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A disabled value decides it, otherwise an unknown value makes it unknown.
+fn all(values: &[Option<bool>]) -> Option<bool> {
+    if values.contains(&Some(false)) {
+        Some(false)
+    } else if values.contains(&None) {
+        None
+    } else {
+        Some(true)
+    }
+}
+
+/// What to do with an item after checking its cfgs.
+enum Action {
+    Keep,
+    Delete,
+    RemoveCfg,
+}
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    active: &'a ActiveCfgs,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController, active: &'a ActiveCfgs) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            active,
+        }
+    }
+
+    fn action(&mut self, name: String, attrs: &[Attribute]) -> Action {
+        let predicates = attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("cfg"))
+            .filter_map(|attr| match attr.parse_meta() {
+                Ok(Meta::List(list)) if list.nested.len() == 1 => list.nested.into_iter().next(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if predicates.is_empty() {
+            return Action::Keep;
+        }
+
+        let values = predicates
+            .iter()
+            .map(|predicate| self.active.eval(predicate))
+            .collect::<Vec<_>>();
+        let description = predicates
+            .iter()
+            .map(|predicate| format!("cfg({})", predicate.to_token_stream()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        self.current_path.push(name);
+        self.current_path.push(description);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        self.current_path.pop();
+
+        if !can_process {
+            return Action::Keep;
+        }
+        self.process_state = ProcessState::Changed;
+        match all(&values) {
+            Some(true) => Action::RemoveCfg,
+            Some(false) | None => Action::Delete,
+        }
+    }
+
+    fn strip_items(&mut self, items: &mut Vec<Item>) {
+        items.retain_mut(|item| {
+            let name = item_name(item);
+            let Some(attrs) = item_attrs(item) else {
+                return true;
+            };
+            match self.action(name, attrs) {
+                Action::Keep => true,
+                Action::Delete => false,
+                Action::RemoveCfg => {
+                    attrs.retain(|attr| !attr.path.is_ident("cfg"));
+                    true
+                }
+            }
+        });
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.strip_items(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+
+        if let Some((_, items)) = &mut module.content {
+            self.strip_items(items);
+        }
+
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path
+            .push(impl_.self_ty.clone().into_token_stream().to_string());
+
+        impl_.items.retain_mut(|item| {
+            let (name, attrs) = match item {
+                ImplItem::Const(const_) => (const_.ident.to_string(), &mut const_.attrs),
+                ImplItem::Method(method) => (method.sig.ident.to_string(), &mut method.attrs),
+                ImplItem::Type(type_) => (type_.ident.to_string(), &mut type_.attrs),
+                ImplItem::Macro(macro_) => (
+                    macro_.mac.path.to_token_stream().to_string(),
+                    &mut macro_.attrs,
+                ),
+                _ => return true,
+            };
+            match self.action(name, attrs) {
+                Action::Keep => true,
+                Action::Delete => false,
+                Action::RemoveCfg => {
+                    attrs.retain(|attr| !attr.path.is_ident("cfg"));
+                    true
+                }
+            }
+        });
+
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+pub(crate) fn item_name(item: &Item) -> String {
+    match item {
+        Item::Const(item) => item.ident.to_string(),
+        Item::Enum(item) => item.ident.to_string(),
+        Item::ExternCrate(item) => item.ident.to_string(),
+        Item::Fn(item) => item.sig.ident.to_string(),
+        Item::Impl(item) => format!("impl {}", item.self_ty.to_token_stream()),
+        Item::Macro(item) => match &item.ident {
+            Some(ident) => ident.to_string(),
+            None => item.mac.path.to_token_stream().to_string(),
+        },
+        Item::Macro2(item) => item.ident.to_string(),
+        Item::Mod(item) => item.ident.to_string(),
+        Item::Static(item) => item.ident.to_string(),
+        Item::Struct(item) => item.ident.to_string(),
+        Item::Trait(item) => item.ident.to_string(),
+        Item::TraitAlias(item) => item.ident.to_string(),
+        Item::Type(item) => item.ident.to_string(),
+        Item::Union(item) => item.ident.to_string(),
+        Item::Use(item) => format!("use {}", item.tree.to_token_stream()),
+        _ => "item".to_owned(),
+    }
+}
+
+pub(super) fn item_attrs(item: &mut Item) -> Option<&mut Vec<Attribute>> {
+    match item {
+        Item::Const(item) => Some(&mut item.attrs),
+        Item::Enum(item) => Some(&mut item.attrs),
+        Item::ExternCrate(item) => Some(&mut item.attrs),
+        Item::Fn(item) => Some(&mut item.attrs),
+        Item::ForeignMod(item) => Some(&mut item.attrs),
+        Item::Impl(item) => Some(&mut item.attrs),
+        Item::Macro(item) => Some(&mut item.attrs),
+        Item::Macro2(item) => Some(&mut item.attrs),
+        Item::Mod(item) => Some(&mut item.attrs),
+        Item::Static(item) => Some(&mut item.attrs),
+        Item::Struct(item) => Some(&mut item.attrs),
+        Item::Trait(item) => Some(&mut item.attrs),
+        Item::TraitAlias(item) => Some(&mut item.attrs),
+        Item::Type(item) => Some(&mut item.attrs),
+        Item::Union(item) => Some(&mut item.attrs),
+        Item::Use(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+pub struct CfgStripper {
+    active: Option<ActiveCfgs>,
+}
+
+impl Pass for CfgStripper {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let active = self.active.get_or_insert_with(|| {
+            ActiveCfgs::from_rustc().unwrap_or_else(|err| {
+                warn!("Failed to get the enabled cfgs, treating all of them as disabled: {err:#}");
+                ActiveCfgs::default()
+            })
+        });
+
+        let mut visitor = Visitor::new(checker, active);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "strip-cfg"
+    }
+}
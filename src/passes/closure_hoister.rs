@@ -0,0 +1,167 @@
+//! Turns closures that are bound with `let` into function items. Only closures with a type on every
+//! parameter can be turned into functions, the return type is taken from the closure if it has one.
+//! A function can't capture anything, so the build rejects the change for closures that do.
+
+use quote::ToTokens;
+use syn::{
+    parse_quote, punctuated::Punctuated, visit_mut::VisitMut, Block, Expr, ExprBlock, ExprClosure,
+    FnArg, Item, ItemFn, Local, Pat, PatIdent, Stmt, Token,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn hoist(&mut self, stmt: &mut Stmt) {
+        let Stmt::Local(local) = stmt else {
+            return;
+        };
+        let Some(func) = closure_fn(local) else {
+            return;
+        };
+
+        self.current_path.push(func.sig.ident.to_string());
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            *stmt = Stmt::Item(Item::Fn(func));
+            self.process_state = ProcessState::Changed;
+        }
+    }
+}
+
+/// The function for `let name = |param: Type| body;`, if the closure can be written as one.
+fn closure_fn(local: &Local) -> Option<ItemFn> {
+    let Pat::Ident(PatIdent {
+        by_ref: None,
+        ident,
+        subpat: None,
+        ..
+    }) = &local.pat
+    else {
+        return None;
+    };
+    let Some((_, init)) = &local.init else {
+        return None;
+    };
+    let Expr::Closure(ExprClosure {
+        asyncness: None,
+        movability: None,
+        inputs,
+        output,
+        body,
+        ..
+    }) = &**init
+    else {
+        return None;
+    };
+
+    let inputs = inputs
+        .iter()
+        .map(|input| match input {
+            Pat::Type(input) => Some(FnArg::Typed(input.clone())),
+            _ => None,
+        })
+        .collect::<Option<Punctuated<FnArg, Token![,]>>>()?;
+    let block: Block = match &**body {
+        Expr::Block(ExprBlock {
+            attrs,
+            label: None,
+            block,
+        }) if attrs.is_empty() => block.clone(),
+        body => parse_quote!({ #body }),
+    };
+    let attrs = &local.attrs;
+
+    Some(parse_quote! {
+        #(#attrs)*
+        fn #ident(#inputs) #output #block
+    })
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        // Closures in the bodies are visited first, so that their paths don't depend on whether the
+        // closure around them was hoisted.
+        syn::visit_mut::visit_block_mut(self, block);
+
+        for stmt in &mut block.stmts {
+            self.hoist(stmt);
+        }
+    }
+
+    tracking!();
+}
+
+fn hoist_closures(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct ClosureHoister;
+
+impl Pass for ClosureHoister {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        hoist_closures(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "hoist-closures"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hoist_closures;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn hoist(src: &str) -> String {
+        apply_all(src, hoist_closures)
+    }
+
+    #[test]
+    fn hoists_non_capturing_closure() {
+        assert_eq!(
+            hoist(
+                r#"fn main() {
+                     let add = |a: u8, b: u8| -> u8 { a + b };
+                     let print = |a: u8| println!("{}", add(a, a));
+                     print(1);
+                 }"#
+            ),
+            tokens(
+                r#"fn main() {
+                     fn add(a: u8, b: u8) -> u8 { a + b }
+                     fn print(a: u8) { println!("{}", add(a, a)) }
+                     print(1);
+                 }"#
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_closures_without_types() {
+        let src = "fn main() { let f = |a| a; let (g, h) = (|a: u8| a, 1); let _ = f(1); }";
+        assert_eq!(hoist(src), tokens(src));
+    }
+}
@@ -0,0 +1,118 @@
+//! Deletes `const` and `static` items, which often stay around after the functions using them are gone.
+//! Items that are still used are kept, as the build fails without them.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Item, ItemConst, ItemStatic, Stmt};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn should_retain_item(&mut self, item: &Item) -> bool {
+        let (Item::Const(ItemConst { ident, .. }) | Item::Static(ItemStatic { ident, .. })) = item
+        else {
+            return true;
+        };
+
+        self.current_path.push(ident.to_string());
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        !can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        file.items.retain(|item| self.should_retain_item(item));
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+        if let Some((_, items)) = &mut module.content {
+            items.retain(|item| self.should_retain_item(item));
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block.stmts.retain(|stmt| match stmt {
+            Stmt::Item(item) => self.should_retain_item(item),
+            _ => true,
+        });
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+#[derive(Default)]
+pub struct ConstDeleter;
+
+impl Pass for ConstDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-consts"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{minimize_sources, Options, Sources};
+
+    #[test]
+    fn keeps_used_const() {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "const USED: u8 = 1;\nconst UNUSED: u8 = 2;\nstatic ALSO_UNUSED: u8 = 3;\n\nfn main() {\n    let _ = USED;\n}\n"
+                .to_owned(),
+        )]);
+        let options = Options {
+            passes: Some("delete-consts".to_owned()),
+            ..Options::default()
+        };
+
+        // Removing a constant that is still used breaks the build.
+        let minimized = minimize_sources(&sources, options, |sources| {
+            sources[&PathBuf::from("src/main.rs")].contains("const USED")
+        })
+        .unwrap();
+
+        let content = &minimized[&main];
+        assert!(content.contains("const USED: u8 = 1;"), "{content}");
+        assert!(!content.contains("UNUSED"), "{content}");
+    }
+}
@@ -0,0 +1,134 @@
+//! Removes the default bodies of trait methods. If an impl in the file overrides the method, the default body
+//! is a candidate for being removed, which makes the method required. If none does, the whole method is a
+//! candidate for being deleted, as the default is all there is to it. Impls in other files and calls of the
+//! method are left to the build to complain about.
+
+use std::collections::HashSet;
+
+use syn::{visit_mut::VisitMut, ImplItem, ItemImpl, ItemTrait, TraitItem};
+
+use crate::processor::{Pass, PassController, ProcessState, SourceFile};
+
+/// The trait and method names of the methods that impls in the file define.
+#[derive(Default)]
+struct FindImplMethods {
+    methods: HashSet<(String, String)>,
+}
+
+impl VisitMut for FindImplMethods {
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        if let Some((_, path, _)) = &impl_.trait_ {
+            let trait_name = path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default();
+            for item in &impl_.items {
+                if let ImplItem::Method(method) = item {
+                    self.methods
+                        .insert((trait_name.clone(), method.sig.ident.to_string()));
+                }
+            }
+        }
+
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+    }
+}
+
+struct Visitor<'a> {
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    impl_methods: HashSet<(String, String)>,
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_trait_mut(&mut self, trait_: &mut ItemTrait) {
+        let trait_name = trait_.ident.to_string();
+        trait_.items.retain_mut(|item| {
+            let TraitItem::Method(method) = item else {
+                return true;
+            };
+            if method.default.is_none() {
+                return true;
+            }
+            let key = (trait_name.clone(), method.sig.ident.to_string());
+            let overridden = self.impl_methods.contains(&key);
+            let candidate = if overridden {
+                "default body"
+            } else {
+                "default method"
+            };
+            // The candidates are not nested in modules, the impls may be anywhere in the file.
+            if !self
+                .checker
+                .can_process(&[key.0, key.1, candidate.to_owned()])
+            {
+                return true;
+            }
+            self.process_state = ProcessState::Changed;
+            // An overridden method stays without its default body, any other one is deleted.
+            if overridden {
+                method.default = None;
+                method.semi_token = Some(Default::default());
+            }
+            overridden
+        });
+
+        syn::visit_mut::visit_item_trait_mut(self, trait_);
+    }
+}
+
+fn remove_default_bodies(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut find = FindImplMethods::default();
+    find.visit_file_mut(krate);
+    let mut visitor = Visitor {
+        checker,
+        process_state: ProcessState::NoChange,
+        impl_methods: find.methods,
+    };
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct DefaultBodyRemover;
+
+impl Pass for DefaultBodyRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_default_bodies(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-default-bodies"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_default_bodies;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_default_bodies)
+    }
+
+    #[test]
+    fn removes_default_methods() {
+        assert_eq!(
+            remove(
+                "trait Tr { fn required(&self); fn unused(&self) { loop {} } \
+                     fn overridden(&self) -> u8 { 0 } } \
+                 impl Tr for () { fn required(&self) {} fn overridden(&self) -> u8 { 1 } }"
+            ),
+            tokens(
+                "trait Tr { fn required(&self); fn overridden(&self) -> u8; } \
+                 impl Tr for () { fn required(&self) {} fn overridden(&self) -> u8 { 1 } }"
+            )
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use quote::ToTokens;
+use syn::{
+    parse_quote, punctuated::Punctuated, visit_mut::VisitMut, Attribute, ItemEnum, ItemStruct,
+    ItemUnion, Token,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    /// Every derived trait is a separate candidate, so we may end up keeping only some of them.
+    fn strip_derives(&mut self, attrs: &mut Vec<Attribute>) {
+        attrs.retain_mut(|attr| {
+            if !attr.path.is_ident("derive") {
+                return true;
+            }
+            let Ok(paths) =
+                attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)
+            else {
+                return true;
+            };
+
+            let old_len = paths.len();
+            let paths = paths
+                .into_iter()
+                .filter(|path| {
+                    self.current_path
+                        .push(format!("derive({})", path.to_token_stream()));
+                    let can_process = self.checker.can_process(&self.current_path);
+                    self.current_path.pop();
+                    !can_process
+                })
+                .collect::<Vec<_>>();
+
+            if paths.len() == old_len {
+                return true;
+            }
+
+            self.process_state = ProcessState::Changed;
+            if paths.is_empty() {
+                return false;
+            }
+            *attr = parse_quote! { #[derive(#(#paths),*)] };
+            true
+        });
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_struct_mut(&mut self, struct_: &mut ItemStruct) {
+        self.current_path.push(struct_.ident.to_string());
+        self.strip_derives(&mut struct_.attrs);
+        syn::visit_mut::visit_item_struct_mut(self, struct_);
+        self.current_path.pop();
+    }
+
+    fn visit_item_enum_mut(&mut self, enum_: &mut ItemEnum) {
+        self.current_path.push(enum_.ident.to_string());
+        self.strip_derives(&mut enum_.attrs);
+        syn::visit_mut::visit_item_enum_mut(self, enum_);
+        self.current_path.pop();
+    }
+
+    fn visit_item_union_mut(&mut self, union_: &mut ItemUnion) {
+        self.current_path.push(union_.ident.to_string());
+        self.strip_derives(&mut union_.attrs);
+        syn::visit_mut::visit_item_union_mut(self, union_);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_field_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+#[derive(Default)]
+pub struct DeriveStripper;
+
+impl Pass for DeriveStripper {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "strip-derives"
+    }
+}
@@ -1,23 +1,36 @@
 use quote::ToTokens;
 use syn::{parse_quote, visit_mut::VisitMut};
 
-use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+use crate::{
+    processor::{tracking, Pass, PassController, ProcessState, SourceFile},
+    BodyReplacement,
+};
 
 struct Visitor<'a> {
     current_path: Vec<String>,
     checker: &'a mut PassController,
 
     loop_expr: syn::Block,
+    /// `syn` types don't implement `PartialEq` without the `extra-traits` feature, compare the tokens instead.
+    loop_expr_tokens: String,
     process_state: ProcessState,
 }
 
 impl<'a> Visitor<'a> {
-    fn new(checker: &'a mut PassController) -> Self {
+    fn new(checker: &'a mut PassController, runs_at_build_time: bool) -> Self {
+        let loop_expr: syn::Block = match checker.options.body_replacement {
+            // A `loop {}` in a build script or proc macro would hang the build.
+            BodyReplacement::Loop if runs_at_build_time => parse_quote! { { panic!() } },
+            BodyReplacement::Loop => parse_quote! { { loop {} } },
+            BodyReplacement::Panic => parse_quote! { { panic!() } },
+            BodyReplacement::Unimplemented => parse_quote! { { unimplemented!() } },
+        };
         Self {
             current_path: Vec::new(),
             checker,
             process_state: ProcessState::NoChange,
-            loop_expr: parse_quote! { { loop {} } },
+            loop_expr_tokens: loop_expr.to_token_stream().to_string(),
+            loop_expr,
         }
     }
 }
@@ -25,9 +38,8 @@ impl<'a> Visitor<'a> {
 impl VisitMut for Visitor<'_> {
     fn visit_block_mut(&mut self, block: &mut syn::Block) {
         match block.stmts.as_slice() {
-            [syn::Stmt::Expr(syn::Expr::Loop(syn::ExprLoop {
-                body: loop_body, ..
-            }))] if loop_body.stmts.is_empty() => {}
+            // Don't replace bodies that have been replaced already.
+            [_] if block.to_token_stream().to_string() == self.loop_expr_tokens => {}
             // Empty bodies are empty already, no need to loopify them.
             [] => {}
             _ if self.checker.can_process(&self.current_path) => {
@@ -48,10 +60,10 @@ impl Pass for EverybodyLoops {
     fn process_file(
         &mut self,
         krate: &mut syn::File,
-        _: &SourceFile,
+        file: &SourceFile,
         checker: &mut PassController,
     ) -> ProcessState {
-        let mut visitor = Visitor::new(checker);
+        let mut visitor = Visitor::new(checker, file.runs_at_build_time());
         visitor.visit_file_mut(krate);
         visitor.process_state
     }
@@ -60,3 +72,43 @@ impl Pass for EverybodyLoops {
         "everybody-loops"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+    use syn::visit_mut::VisitMut;
+
+    use super::Visitor;
+    use crate::{processor::PassController, BodyReplacement, Options};
+
+    fn replace_bodies(body_replacement: BodyReplacement) -> String {
+        let mut krate: syn::File = syn::parse_quote! {
+            fn function() -> u32 {
+                let x = 1;
+                x + 1
+            }
+        };
+        let mut checker = PassController::new(Options {
+            body_replacement,
+            ..Options::default()
+        });
+        Visitor::new(&mut checker, false).visit_file_mut(&mut krate);
+        krate.to_token_stream().to_string()
+    }
+
+    #[test]
+    fn replaces_with_chosen_expression() {
+        assert_eq!(
+            replace_bodies(BodyReplacement::Loop),
+            "fn function () -> u32 { loop { } }"
+        );
+        assert_eq!(
+            replace_bodies(BodyReplacement::Panic),
+            "fn function () -> u32 { panic ! () }"
+        );
+        assert_eq!(
+            replace_bodies(BodyReplacement::Unimplemented),
+            "fn function () -> u32 { unimplemented ! () }"
+        );
+    }
+}
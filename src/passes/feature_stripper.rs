@@ -0,0 +1,425 @@
+//! Deletes items behind `#[cfg(feature = "...")]` for features that are disabled in the build and removes the
+//! `#[cfg]` from items whose features are enabled. Unlike `strip-cfg`, this knows which features are enabled:
+//! they are read from the `[features]` of the closest `Cargo.toml` together with the `--features`,
+//! `--all-features` and `--no-default-features` arguments of the build. With `--rustc`, only the features from
+//! `--cfg feature="..."` arguments are enabled. Cfgs that check more than features are left to `strip-cfg`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Attribute, ImplItem, Item, ItemImpl, Lit, Meta, NestedMeta};
+
+use super::cfg_stripper::{item_attrs, item_name};
+use crate::{
+    build::split_args,
+    processor::{tracking, Pass, PassController, ProcessState, SourceFile},
+    Options,
+};
+
+/// The features that are enabled in the build. Features that aren't in here are disabled, cargo doesn't set
+/// undeclared features either.
+#[derive(Debug, Default)]
+struct EnabledFeatures(HashSet<String>);
+
+impl EnabledFeatures {
+    /// Resolves the enabled features of the package with the manifest in `manifest_dir`.
+    fn resolve(options: &Options, manifest_dir: Option<&Path>) -> Result<Self> {
+        let mut args = options
+            .extra_args
+            .as_deref()
+            .map(split_args)
+            .unwrap_or_default();
+        if options.rustc {
+            return Ok(Self::from_rustc_args(&args));
+        }
+        args.extend(split_args(&options.cargo_subcmd));
+
+        let Some(manifest_dir) = manifest_dir else {
+            return Ok(Self::default());
+        };
+        let path = manifest_dir.join("Cargo.toml");
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        let manifest = toml::from_str::<toml::Value>(&content)
+            .with_context(|| format!("parsing {}", path.display()))?;
+        Ok(Self::from_manifest(&manifest, &args))
+    }
+
+    fn from_rustc_args(args: &[String]) -> Self {
+        let features = args
+            .windows(2)
+            .filter(|pair| pair[0] == "--cfg")
+            .filter_map(|pair| pair[1].strip_prefix("feature="))
+            .map(|feature| feature.trim_matches('"').to_owned())
+            .collect();
+        Self(features)
+    }
+
+    fn from_manifest(manifest: &toml::Value, args: &[String]) -> Self {
+        let features = manifest
+            .get("features")
+            .and_then(toml::Value::as_table)
+            .cloned()
+            .unwrap_or_default();
+        // Optional dependencies are features too.
+        let optional_deps = manifest
+            .get("dependencies")
+            .and_then(toml::Value::as_table)
+            .into_iter()
+            .flatten()
+            .filter(|(_, dep)| dep.get("optional").and_then(toml::Value::as_bool) == Some(true))
+            .map(|(name, _)| name.clone());
+        let all = features
+            .keys()
+            .cloned()
+            .chain(optional_deps)
+            .collect::<Vec<_>>();
+
+        let mut requested = Vec::new();
+        let mut default = true;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let list = match arg.as_str() {
+                "--all-features" => {
+                    requested.extend(all.iter().cloned());
+                    continue;
+                }
+                "--no-default-features" => {
+                    default = false;
+                    continue;
+                }
+                "--features" | "-F" => args.next().map(String::as_str),
+                arg => arg.strip_prefix("--features="),
+            };
+            requested.extend(
+                list.into_iter()
+                    .flat_map(|list| list.split(','))
+                    .filter(|feature| !feature.is_empty() && !feature.contains('/'))
+                    .map(ToOwned::to_owned),
+            );
+        }
+        if default {
+            requested.push("default".to_owned());
+        }
+
+        // Enabling a feature enables the features that it lists.
+        let mut enabled = HashSet::new();
+        while let Some(feature) = requested.pop() {
+            if !enabled.insert(feature.clone()) {
+                continue;
+            }
+            let implied = features
+                .get(&feature)
+                .and_then(toml::Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(toml::Value::as_str)
+                .filter_map(|implied| match implied.split_once('/') {
+                    // `dep/feature` enables the optional dependency `dep`, `dep?/feature` doesn't.
+                    Some((dep, _)) if !dep.ends_with('?') => Some(dep),
+                    Some(_) => None,
+                    None if implied.starts_with("dep:") => None,
+                    None => Some(implied),
+                });
+            requested.extend(implied.map(ToOwned::to_owned));
+        }
+        Self(enabled)
+    }
+
+    /// Evaluates a cfg predicate. Returns `None` if it checks anything else than features.
+    fn eval(&self, predicate: &NestedMeta) -> Option<bool> {
+        match predicate {
+            NestedMeta::Meta(Meta::NameValue(name_value))
+                if name_value.path.is_ident("feature") =>
+            {
+                match &name_value.lit {
+                    Lit::Str(feature) => Some(self.0.contains(&feature.value())),
+                    _ => None,
+                }
+            }
+            NestedMeta::Meta(Meta::List(list)) => {
+                let values = list
+                    .nested
+                    .iter()
+                    .map(|predicate| self.eval(predicate))
+                    .collect::<Option<Vec<_>>>()?;
+                if list.path.is_ident("all") {
+                    Some(values.iter().all(|&value| value))
+                } else if list.path.is_ident("any") {
+                    Some(values.iter().any(|&value| value))
+                } else if list.path.is_ident("not") && values.len() == 1 {
+                    Some(!values[0])
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The predicate of a `#[cfg]` that only checks features.
+fn feature_cfg(attr: &Attribute, enabled: &EnabledFeatures) -> Option<(NestedMeta, bool)> {
+    if !attr.path.is_ident("cfg") {
+        return None;
+    }
+    match attr.parse_meta() {
+        Ok(Meta::List(list)) if list.nested.len() == 1 => {
+            let predicate = list.nested.into_iter().next()?;
+            let value = enabled.eval(&predicate)?;
+            Some((predicate, value))
+        }
+        _ => None,
+    }
+}
+
+/// What to do with an item after checking its feature cfgs.
+enum Action {
+    Keep,
+    Delete,
+    RemoveCfg,
+}
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    enabled: &'a EnabledFeatures,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController, enabled: &'a EnabledFeatures) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            enabled,
+        }
+    }
+
+    fn action(&mut self, name: String, attrs: &[Attribute]) -> Action {
+        let cfgs = attrs
+            .iter()
+            .filter_map(|attr| feature_cfg(attr, self.enabled))
+            .collect::<Vec<_>>();
+        if cfgs.is_empty() {
+            return Action::Keep;
+        }
+
+        let description = cfgs
+            .iter()
+            .map(|(predicate, _)| format!("cfg({})", predicate.to_token_stream()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        self.current_path.push(name);
+        self.current_path.push(description);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        self.current_path.pop();
+
+        if !can_process {
+            return Action::Keep;
+        }
+        self.process_state = ProcessState::Changed;
+        if cfgs.iter().all(|&(_, value)| value) {
+            Action::RemoveCfg
+        } else {
+            Action::Delete
+        }
+    }
+
+    /// Applies the action to the item. Returns whether the item is kept.
+    fn apply(&mut self, name: String, attrs: &mut Vec<Attribute>) -> bool {
+        match self.action(name, attrs) {
+            Action::Keep => true,
+            Action::Delete => false,
+            Action::RemoveCfg => {
+                attrs.retain(|attr| feature_cfg(attr, self.enabled).is_none());
+                true
+            }
+        }
+    }
+
+    fn strip_items(&mut self, items: &mut Vec<Item>) {
+        items.retain_mut(|item| {
+            let name = item_name(item);
+            match item_attrs(item) {
+                Some(attrs) => self.apply(name, attrs),
+                None => true,
+            }
+        });
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.strip_items(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+
+        if let Some((_, items)) = &mut module.content {
+            self.strip_items(items);
+        }
+
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path
+            .push(impl_.self_ty.clone().into_token_stream().to_string());
+
+        impl_.items.retain_mut(|item| {
+            let (name, attrs) = match item {
+                ImplItem::Const(const_) => (const_.ident.to_string(), &mut const_.attrs),
+                ImplItem::Method(method) => (method.sig.ident.to_string(), &mut method.attrs),
+                ImplItem::Type(type_) => (type_.ident.to_string(), &mut type_.attrs),
+                _ => return true,
+            };
+            self.apply(name, attrs)
+        });
+
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+#[derive(Default)]
+pub struct FeatureStripper {
+    /// The enabled features by the directory of the manifest, crates of a workspace enable different ones.
+    enabled: HashMap<Option<PathBuf>, EnabledFeatures>,
+}
+
+impl Pass for FeatureStripper {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        file: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let manifest_dir = file
+            .path_no_fs_interact()
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.join("Cargo.toml").exists())
+            .map(Path::to_owned);
+        let enabled = self
+            .enabled
+            .entry(manifest_dir)
+            .or_insert_with_key(|manifest_dir| {
+                EnabledFeatures::resolve(&checker.options, manifest_dir.as_deref()).unwrap_or_else(
+                    |err| {
+                        warn!("Failed to get the enabled features, treating all of them as disabled: {err:#}");
+                        EnabledFeatures::default()
+                    },
+                )
+            });
+
+        let mut visitor = Visitor::new(checker, enabled);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "strip-features"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, path::PathBuf};
+
+    use quote::ToTokens;
+
+    use super::EnabledFeatures;
+    use crate::{minimize_sources, Options, Sources};
+
+    #[test]
+    fn resolves_enabled_features() {
+        let manifest = toml::from_str::<toml::Value>(
+            "[features]\ndefault = [\"a\"]\na = [\"b\"]\nb = []\nc = [\"dep/x\", \"other?/y\"]\n\n\
+             [dependencies]\ndep = { version = \"1\", optional = true }\nother = { version = \"1\", optional = true }\n",
+        )
+        .unwrap();
+        let enabled = |args: &str| {
+            let args = args
+                .split_whitespace()
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>();
+            let mut enabled = EnabledFeatures::from_manifest(&manifest, &args)
+                .0
+                .into_iter()
+                .collect::<Vec<_>>();
+            enabled.sort();
+            enabled
+        };
+
+        assert_eq!(enabled("build"), ["a", "b", "default"]);
+        assert_eq!(enabled("build --no-default-features"), Vec::<String>::new());
+        assert_eq!(
+            enabled("build --no-default-features --features=c"),
+            ["c", "dep"]
+        );
+        assert_eq!(
+            enabled("build --all-features"),
+            ["a", "b", "c", "default", "dep", "other"]
+        );
+        assert_eq!(
+            EnabledFeatures::from_rustc_args(&["--cfg".to_owned(), "feature=\"a\"".to_owned()]).0,
+            HashSet::from(["a".to_owned()])
+        );
+    }
+
+    #[test]
+    fn removes_disabled_feature_module() {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([
+            (
+                PathBuf::from("Cargo.toml"),
+                "[package]\nname = \"features\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [features]\ndefault = [\"on\"]\non = []\noff = []\n"
+                    .to_owned(),
+            ),
+            (
+                main.clone(),
+                "#[cfg(feature = \"off\")]\nmod disabled {\n    pub fn f() {}\n}\n\n\
+                 #[cfg(feature = \"on\")]\nfn enabled() {}\n\n\
+                 #[cfg(unix)]\nfn platform() {}\n\nfn main() {}\n"
+                    .to_owned(),
+            ),
+        ]);
+        let options = Options {
+            passes: Some("strip-features".to_owned()),
+            ..Options::default()
+        };
+
+        let minimized = minimize_sources(&sources, options, |_| true).unwrap();
+
+        let content = &minimized[&main];
+        assert_eq!(
+            syn::parse_file(content)
+                .unwrap()
+                .into_token_stream()
+                .to_string(),
+            syn::parse_str::<syn::File>(
+                "fn enabled() {} #[cfg(unix)] fn platform() {} fn main() {}"
+            )
+            .unwrap()
+            .into_token_stream()
+            .to_string(),
+            "{content}"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Replaces the types of struct and enum fields with `()`. The payload of a type often doesn't matter for
+//! the issue, but the field can't be deleted because it is still used. The build rejects the replacements
+//! where the type does matter.
+
+use quote::ToTokens;
+use syn::{parse_quote, visit_mut::VisitMut, Fields, ItemEnum, Type, Variant};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn replace_type(&mut self, name: String, ty: &mut Type) {
+        if matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty()) {
+            return;
+        }
+
+        self.current_path.push(name);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            *ty = parse_quote!(());
+            self.process_state = ProcessState::Changed;
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_fields_mut(&mut self, fields: &mut Fields) {
+        for (i, field) in fields.iter_mut().enumerate() {
+            let name = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => i.to_string(),
+            };
+            self.replace_type(name, &mut field.ty);
+        }
+    }
+
+    fn visit_item_enum_mut(&mut self, enum_: &mut ItemEnum) {
+        self.current_path.push(enum_.ident.to_string());
+        syn::visit_mut::visit_item_enum_mut(self, enum_);
+        self.current_path.pop();
+    }
+
+    fn visit_variant_mut(&mut self, variant: &mut Variant) {
+        self.current_path.push(variant.ident.to_string());
+        syn::visit_mut::visit_variant_mut(self, variant);
+        self.current_path.pop();
+    }
+
+    tracking!();
+}
+
+fn replace_field_types(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct FieldTypeReplacer;
+
+impl Pass for FieldTypeReplacer {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        replace_field_types(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "unit-field-types"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::replace_field_types;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn replace(src: &str) -> String {
+        apply_all(src, replace_field_types)
+    }
+
+    #[test]
+    fn string_field_becomes_unit() {
+        assert_eq!(
+            replace("struct S { name: String, id: () } enum E { A(String, u8), B { x: Vec<u8> } }"),
+            tokens("struct S { name: (), id: () } enum E { A((), ()), B { x: () } }")
+        );
+    }
+}
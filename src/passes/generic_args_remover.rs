@@ -0,0 +1,107 @@
+//! Removes explicit generic arguments in expressions, like the turbofish in `Vec::<u8>::new()` or
+//! `iter.collect::<Vec<_>>()`. The build rejects the removals where the arguments can't be inferred.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, ExprMethodCall, ExprPath, PathArguments};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn can_remove(&mut self, name: String) -> bool {
+        self.current_path.push(name);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_expr_path_mut(&mut self, expr: &mut ExprPath) {
+        syn::visit_mut::visit_expr_path_mut(self, expr);
+
+        // The name contains the whole path, so that the arguments of different segments are different
+        // candidates.
+        let path = expr.path.to_token_stream().to_string();
+        for (i, segment) in expr.path.segments.iter_mut().enumerate() {
+            if let PathArguments::AngleBracketed(_) = segment.arguments {
+                if self.can_remove(format!("{path} {i}")) {
+                    segment.arguments = PathArguments::None;
+                }
+            }
+        }
+    }
+
+    fn visit_expr_method_call_mut(&mut self, call: &mut ExprMethodCall) {
+        syn::visit_mut::visit_expr_method_call_mut(self, call);
+
+        if let Some(turbofish) = &call.turbofish {
+            let name = format!("{}{}", call.method, turbofish.to_token_stream());
+            if self.can_remove(name) {
+                call.turbofish = None;
+            }
+        }
+    }
+
+    tracking!();
+}
+
+fn remove_generic_args(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct GenericArgsRemover;
+
+impl Pass for GenericArgsRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_generic_args(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-generic-args"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_generic_args;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_generic_args)
+    }
+
+    #[test]
+    fn turbofish_is_removed() {
+        assert_eq!(
+            remove(
+                "fn main() { let v: Vec<u8> = Vec::<u8>::new(); let w: Vec<u8> = v.into_iter().collect::<Vec<_>>(); }"
+            ),
+            tokens("fn main() { let v: Vec<u8> = Vec::new(); let w: Vec<u8> = v.into_iter().collect(); }")
+        );
+    }
+}
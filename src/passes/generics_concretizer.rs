@@ -0,0 +1,430 @@
+//! Replaces type parameters of functions with the one concrete type that the function is called with.
+//! The concrete types are found syntactically from turbofishes and arguments with an obvious type like
+//! `1u32` or `x as u8`. Only calls in the same file are considered. If a call somewhere else uses a
+//! different type, the build will fail and the change won't be kept.
+
+use std::collections::{hash_map::Entry, HashMap};
+
+use quote::{quote, ToTokens};
+use syn::{
+    punctuated::Punctuated, visit_mut::VisitMut, Expr, ExprCall, ExprPath, FnArg, GenericArgument,
+    GenericParam, Ident, ItemFn, Lit, PathArguments, Type, TypePath, WherePredicate,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+/// The concrete types that a type parameter of a function has been called with.
+/// `None` is a call where we don't know the type.
+type Usages = HashMap<(String, String), Vec<Option<Type>>>;
+
+/// Collects the generic functions of the file. Functions with the same name in different modules are ignored,
+/// as we can't tell their calls apart.
+#[derive(Default)]
+struct CollectFns {
+    fns: HashMap<String, Option<ItemFn>>,
+}
+
+impl VisitMut for CollectFns {
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        match self.fns.entry(func.sig.ident.to_string()) {
+            Entry::Occupied(mut entry) => {
+                entry.insert(None);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(type_params(func).next().is_some().then(|| func.clone()));
+            }
+        }
+        syn::visit_mut::visit_item_fn_mut(self, func);
+    }
+}
+
+fn type_params(func: &ItemFn) -> impl Iterator<Item = &Ident> {
+    func.sig
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(&param.ident),
+            _ => None,
+        })
+}
+
+/// Finds the concrete types at the call sites of the generic functions.
+struct CollectUsages<'a> {
+    fns: &'a HashMap<String, ItemFn>,
+    usages: Usages,
+}
+
+impl VisitMut for CollectUsages<'_> {
+    fn visit_expr_call_mut(&mut self, call: &mut ExprCall) {
+        if let Some((name, turbofish)) = called_fn(call) {
+            if let Some(func) = self.fns.get(&name) {
+                for (i, param) in type_params(func).enumerate() {
+                    let concrete = match turbofish.and_then(|args| nth_type_arg(args, i)) {
+                        Some(ty) => Some(ty.clone()),
+                        None => infer_from_args(func, &call.args, param),
+                    };
+                    self.usages
+                        .entry((name.clone(), param.to_string()))
+                        .or_default()
+                        .push(concrete);
+                }
+            }
+        }
+        syn::visit_mut::visit_expr_call_mut(self, call);
+    }
+}
+
+/// Returns the name and the turbofish of a function call like `module::function::<u32>(1)`.
+fn called_fn(
+    call: &ExprCall,
+) -> Option<(
+    String,
+    Option<&Punctuated<GenericArgument, syn::token::Comma>>,
+)> {
+    let Expr::Path(ExprPath {
+        qself: None, path, ..
+    }) = &*call.func
+    else {
+        return None;
+    };
+    let segment = path.segments.last()?;
+    let turbofish = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => Some(&args.args),
+        _ => None,
+    };
+    Some((segment.ident.to_string(), turbofish))
+}
+
+fn nth_type_arg(args: &Punctuated<GenericArgument, syn::token::Comma>, n: usize) -> Option<&Type> {
+    args.iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .nth(n)
+}
+
+/// Infers the type parameter from the arguments for parameters of type `T` or `&T`.
+/// Returns `None` if there is such a parameter and we don't know the type of its argument.
+fn infer_from_args(
+    func: &ItemFn,
+    args: &Punctuated<Expr, syn::token::Comma>,
+    param: &Ident,
+) -> Option<Type> {
+    let mut inferred = None;
+    for (input, arg) in func.sig.inputs.iter().zip(args) {
+        let FnArg::Typed(input) = input else {
+            continue;
+        };
+        let ty = match (&*input.ty, arg) {
+            (ty, arg) if is_param(ty, param) => type_of(arg)?,
+            (Type::Reference(ty), Expr::Reference(arg)) if is_param(&ty.elem, param) => {
+                type_of(&arg.expr)?
+            }
+            (ty, _) if mentions_param(ty, param) => return None,
+            _ => continue,
+        };
+        match &inferred {
+            Some(inferred) if !same_type(inferred, &ty) => return None,
+            _ => inferred = Some(ty),
+        }
+    }
+    inferred
+}
+
+fn is_param(ty: &Type, param: &Ident) -> bool {
+    matches!(ty, Type::Path(TypePath { qself: None, path }) if path.is_ident(param))
+}
+
+fn mentions_param(ty: &Type, param: &Ident) -> bool {
+    ty.to_token_stream()
+        .into_iter()
+        .any(|token| matches!(token, proc_macro2::TokenTree::Ident(ident) if ident == *param))
+}
+
+/// The type of expressions whose type is obvious without type checking.
+fn type_of(expr: &Expr) -> Option<Type> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Int(int) if !int.suffix().is_empty() => syn::parse_str(int.suffix()).ok(),
+            Lit::Float(float) if !float.suffix().is_empty() => syn::parse_str(float.suffix()).ok(),
+            Lit::Str(_) => Some(syn::parse_quote! { &'static str }),
+            Lit::Bool(_) => Some(syn::parse_quote! { bool }),
+            Lit::Char(_) => Some(syn::parse_quote! { char }),
+            _ => None,
+        },
+        Expr::Cast(cast) => Some((*cast.ty).clone()),
+        Expr::Paren(paren) => type_of(&paren.expr),
+        _ => None,
+    }
+}
+
+fn same_type(a: &Type, b: &Type) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+/// The single concrete type of the type parameter, if every call agrees on it.
+fn single_type(usages: &[Option<Type>]) -> Option<&Type> {
+    let first = usages.first()?.as_ref()?;
+    usages
+        .iter()
+        .all(|usage| usage.as_ref().is_some_and(|ty| same_type(ty, first)))
+        .then_some(first)
+}
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    concrete: HashMap<(String, String), Type>,
+    /// The functions and the indices of the type parameters that were removed from them.
+    removed: HashMap<String, Vec<usize>>,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController, concrete: HashMap<(String, String), Type>) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            concrete,
+            removed: HashMap::new(),
+        }
+    }
+
+    fn concretize(&mut self, func: &mut ItemFn) {
+        let name = func.sig.ident.to_string();
+        let params = type_params(func).cloned().collect::<Vec<_>>();
+
+        for (i, param) in params.iter().enumerate() {
+            let Some(ty) = self
+                .concrete
+                .get(&(name.clone(), param.to_string()))
+                .cloned()
+            else {
+                continue;
+            };
+
+            self.current_path.push(param.to_string());
+            let can_process = self.checker.can_process(&self.current_path);
+            self.current_path.pop();
+            if !can_process {
+                continue;
+            }
+
+            self.process_state = ProcessState::Changed;
+            self.removed.entry(name.clone()).or_default().push(i);
+
+            let generics = &mut func.sig.generics;
+            generics.params = std::mem::take(&mut generics.params)
+                .into_iter()
+                .filter(|generic| !matches!(generic, GenericParam::Type(ty) if ty.ident == *param))
+                .collect();
+            if generics.params.is_empty() {
+                generics.lt_token = None;
+                generics.gt_token = None;
+            }
+            if let Some(where_clause) = &mut generics.where_clause {
+                where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+                    .into_iter()
+                    .filter(|predicate| {
+                        !matches!(predicate, WherePredicate::Type(predicate) if is_param(&predicate.bounded_ty, param))
+                    })
+                    .collect();
+            }
+
+            ReplaceParam { param, ty: &ty }.visit_item_fn_mut(func);
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        self.current_path.push(func.sig.ident.to_string());
+        self.concretize(func);
+        syn::visit_mut::visit_item_fn_mut(self, func);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+/// Replaces the type parameter with the concrete type.
+struct ReplaceParam<'a> {
+    param: &'a Ident,
+    ty: &'a Type,
+}
+
+impl ReplaceParam<'_> {
+    /// `T::Assoc` becomes `<u32>::Assoc`.
+    fn qualified_path<T: syn::parse::Parse>(&self, path: &syn::Path) -> Option<T> {
+        if path.leading_colon.is_some()
+            || path.segments.len() < 2
+            || path.segments[0].ident != *self.param
+        {
+            return None;
+        }
+        let ty = self.ty;
+        let rest = path.segments.iter().skip(1);
+        syn::parse2(quote! { <#ty>#(::#rest)* }).ok()
+    }
+}
+
+impl VisitMut for ReplaceParam<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if is_param(ty, self.param) {
+            *ty = self.ty.clone();
+            return;
+        }
+        if let Type::Path(TypePath { qself: None, path }) = ty {
+            if let Some(qualified) = self.qualified_path(path) {
+                *ty = Type::Path(qualified);
+                return;
+            }
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_expr_path_mut(&mut self, expr: &mut ExprPath) {
+        if expr.qself.is_none() {
+            if let Some(mut qualified) = self.qualified_path::<ExprPath>(&expr.path) {
+                qualified.attrs = std::mem::take(&mut expr.attrs);
+                *expr = qualified;
+                return;
+            }
+        }
+        syn::visit_mut::visit_expr_path_mut(self, expr);
+    }
+
+    // Items inside the function can't use the type parameter.
+    fn visit_item_mut(&mut self, _: &mut syn::Item) {}
+}
+
+/// Removes the turbofish arguments for the removed type parameters from the calls.
+struct FixTurbofish<'a> {
+    removed: &'a HashMap<String, Vec<usize>>,
+}
+
+impl VisitMut for FixTurbofish<'_> {
+    fn visit_expr_call_mut(&mut self, call: &mut ExprCall) {
+        if let Expr::Path(ExprPath { path, .. }) = &mut *call.func {
+            if let Some(segment) = path.segments.last_mut() {
+                if let (Some(removed), PathArguments::AngleBracketed(args)) = (
+                    self.removed.get(&segment.ident.to_string()),
+                    &mut segment.arguments,
+                ) {
+                    let mut type_index = 0;
+                    args.args = std::mem::take(&mut args.args)
+                        .into_iter()
+                        .filter(|arg| {
+                            let GenericArgument::Type(_) = arg else {
+                                return true;
+                            };
+                            type_index += 1;
+                            !removed.contains(&(type_index - 1))
+                        })
+                        .collect();
+                    if args.args.is_empty() {
+                        segment.arguments = PathArguments::None;
+                    }
+                }
+            }
+        }
+        syn::visit_mut::visit_expr_call_mut(self, call);
+    }
+}
+
+fn concretize_generics(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut collect_fns = CollectFns::default();
+    collect_fns.visit_file_mut(krate);
+    let fns = collect_fns
+        .fns
+        .into_iter()
+        .filter_map(|(name, func)| Some((name, func?)))
+        .collect::<HashMap<_, _>>();
+
+    let mut collect_usages = CollectUsages {
+        fns: &fns,
+        usages: Usages::new(),
+    };
+    collect_usages.visit_file_mut(krate);
+
+    let concrete = collect_usages
+        .usages
+        .iter()
+        .filter_map(|(key, usages)| Some((key.clone(), single_type(usages)?.clone())))
+        .collect::<HashMap<_, _>>();
+    if concrete.is_empty() {
+        return ProcessState::NoChange;
+    }
+
+    let mut visitor = Visitor::new(checker, concrete);
+    visitor.visit_file_mut(krate);
+
+    FixTurbofish {
+        removed: &visitor.removed,
+    }
+    .visit_file_mut(krate);
+
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct GenericsConcretizer;
+
+impl Pass for GenericsConcretizer {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        concretize_generics(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "concretize-generics"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::concretize_generics;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn concretize(src: &str) -> String {
+        apply_all(src, concretize_generics)
+    }
+
+    #[test]
+    fn single_concrete_type() {
+        assert_eq!(
+            concretize(
+                "fn generic<T: Default + Copy>(value: T) -> T where T: Clone { let _ = T::default(); value }
+                 fn main() { generic(1u32); generic::<u32>(2); }"
+            ),
+            tokens(
+                "fn generic(value: u32) -> u32 { let _ = <u32>::default(); value }
+                 fn main() { generic(1u32); generic(2); }"
+            )
+        );
+    }
+
+    #[test]
+    fn conflicting_types() {
+        let src = "fn generic<T>(value: T) {}
+                   fn main() { generic(1u32); generic(1u8); }";
+        assert_eq!(concretize(src), tokens(src));
+    }
+
+    #[test]
+    fn unknown_type() {
+        let src = "fn generic<T>(value: T) {}
+                   fn main() { generic(1u32); generic(x); }";
+        assert_eq!(concretize(src), tokens(src));
+    }
+}
@@ -0,0 +1,270 @@
+//! Expands glob imports like `use foo::*;` into a `use` for every name that the module uses from it, so that
+//! `remove-unused-imports` can delete the ones that aren't needed and the reproduction shows where things
+//! come from. There is no name resolution, so the names are guessed: everything that the module mentions
+//! but doesn't define, bind or import otherwise and that isn't in the prelude. Modules with several globs
+//! are skipped, as the names can't be attributed to one of them. If a guess is wrong, the build fails and
+//! the glob stays.
+
+use std::collections::{BTreeSet, HashSet};
+
+use quote::ToTokens;
+use syn::{
+    parse_quote, visit_mut::VisitMut, Attribute, ConstParam, Ident, Item, ItemMod, ItemUse, Macro,
+    PatIdent, Path, TypeParam, UseTree,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+/// Names that are in scope without an import.
+const PRELUDE: &str = "self Self super crate std core alloc bool char str u8 u16 u32 u64 u128 usize \
+    i8 i16 i32 i64 i128 isize f32 f64 Option Some None Result Ok Err Vec String Box ToString ToOwned \
+    Clone Copy Default Drop drop Eq PartialEq Ord PartialOrd Hash Debug Fn FnMut FnOnce Iterator \
+    IntoIterator DoubleEndedIterator ExactSizeIterator Extend Send Sync Sized Unpin AsRef AsMut Into \
+    From TryFrom TryInto FromIterator";
+
+/// The names that a module mentions and the names that it defines, without looking into nested modules.
+#[derive(Default)]
+struct FindNames {
+    used: BTreeSet<String>,
+    defined: HashSet<String>,
+}
+
+impl FindNames {
+    fn of_items(items: &mut [Item]) -> Self {
+        let mut find = Self::default();
+        for item in items {
+            find.visit_item_mut(item);
+        }
+        find
+    }
+}
+
+impl VisitMut for FindNames {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        if path.leading_colon.is_none() {
+            let first = &path.segments[0].ident;
+            // The first segment of a longer path is most likely a crate or module that doesn't come from
+            // the glob.
+            let lowercase = first.to_string().starts_with(char::is_lowercase);
+            if path.segments.len() == 1 || !lowercase {
+                self.used.insert(first.to_string());
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        let ident = match item {
+            Item::Const(item) => Some(&item.ident),
+            Item::Enum(item) => Some(&item.ident),
+            Item::ExternCrate(item) => Some(&item.ident),
+            Item::Fn(item) => Some(&item.sig.ident),
+            Item::Macro(item) => item.ident.as_ref(),
+            Item::Static(item) => Some(&item.ident),
+            Item::Struct(item) => Some(&item.ident),
+            Item::Trait(item) => Some(&item.ident),
+            Item::TraitAlias(item) => Some(&item.ident),
+            Item::Type(item) => Some(&item.ident),
+            Item::Union(item) => Some(&item.ident),
+            Item::Mod(item) => {
+                self.defined.insert(item.ident.to_string());
+                return;
+            }
+            Item::Use(use_) => {
+                use_names(&use_.tree, &mut self.defined);
+                return;
+            }
+            _ => None,
+        };
+        if let Some(ident) = ident {
+            self.defined.insert(ident.to_string());
+        }
+        syn::visit_mut::visit_item_mut(self, item);
+    }
+
+    fn visit_pat_ident_mut(&mut self, pat: &mut PatIdent) {
+        self.defined.insert(pat.ident.to_string());
+        syn::visit_mut::visit_pat_ident_mut(self, pat);
+    }
+
+    fn visit_type_param_mut(&mut self, param: &mut TypeParam) {
+        self.defined.insert(param.ident.to_string());
+        syn::visit_mut::visit_type_param_mut(self, param);
+    }
+
+    fn visit_const_param_mut(&mut self, param: &mut ConstParam) {
+        self.defined.insert(param.ident.to_string());
+        syn::visit_mut::visit_const_param_mut(self, param);
+    }
+
+    // Attributes and macros are not parsed, so their paths are left out.
+    fn visit_attribute_mut(&mut self, _: &mut Attribute) {}
+
+    fn visit_macro_mut(&mut self, _: &mut Macro) {}
+}
+
+/// The names that a `use` brings into scope.
+fn use_names(tree: &UseTree, names: &mut HashSet<String>) {
+    match tree {
+        UseTree::Path(path) => use_names(&path.tree, names),
+        UseTree::Name(name) => {
+            names.insert(name.ident.to_string());
+        }
+        UseTree::Rename(rename) => {
+            names.insert(rename.rename.to_string());
+        }
+        UseTree::Glob(_) => {}
+        UseTree::Group(group) => group.items.iter().for_each(|tree| use_names(tree, names)),
+    }
+}
+
+/// The `foo::bar` of `use foo::bar::*`.
+fn glob_prefix(tree: &UseTree) -> Option<Vec<&Ident>> {
+    match tree {
+        UseTree::Path(path) => {
+            let mut prefix = glob_prefix(&path.tree)?;
+            prefix.insert(0, &path.ident);
+            Some(prefix)
+        }
+        UseTree::Glob(_) => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn expand_globs(&mut self, items: &mut Vec<Item>) {
+        let globs = items
+            .iter()
+            .enumerate()
+            .filter(
+                |(_, item)| matches!(item, Item::Use(use_) if glob_prefix(&use_.tree).is_some()),
+            )
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let [index] = globs[..] else {
+            return;
+        };
+        let Item::Use(glob) = items[index].clone() else {
+            unreachable!()
+        };
+
+        self.current_path
+            .push(format!("expand use {}", glob.tree.to_token_stream()));
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if !can_process {
+            return;
+        }
+        self.process_state = ProcessState::Changed;
+
+        let FindNames { used, defined } = FindNames::of_items(items);
+        let prefix = glob_prefix(&glob.tree).expect("checked above");
+        let imports = used
+            .iter()
+            .filter(|name| {
+                !defined.contains(*name)
+                    && !PRELUDE.split_whitespace().any(|prelude| prelude == *name)
+            })
+            .map(|name| {
+                let name = Ident::new(name, proc_macro2::Span::call_site());
+                let ItemUse {
+                    attrs,
+                    vis,
+                    leading_colon,
+                    ..
+                } = &glob;
+                Item::Use(parse_quote!(#(#attrs)* #vis use #leading_colon #(#prefix::)* #name;))
+            })
+            .collect::<Vec<_>>();
+        items.splice(index..=index, imports);
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.expand_globs(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut ItemMod) {
+        self.current_path.push(module.ident.to_string());
+        if let Some((_, items)) = &mut module.content {
+            self.expand_globs(items);
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_field_mut);
+    tracking!(visit_item_struct_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+fn expand_glob_imports(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct GlobExpander;
+
+impl Pass for GlobExpander {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        expand_glob_imports(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "expand-glob-imports"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_glob_imports;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn expand(src: &str) -> String {
+        apply_all(src, expand_glob_imports)
+    }
+
+    #[test]
+    fn glob_becomes_used_names() {
+        assert_eq!(
+            expand(
+                "use std::collections::*; \
+                 struct Local; \
+                 fn main() { let map: HashMap<u8, Local> = HashMap::new(); \
+                     let set = BTreeSet::<u8>::new(); drop((map, set)); }"
+            ),
+            tokens(
+                "use std::collections::BTreeSet; use std::collections::HashMap; \
+                 struct Local; \
+                 fn main() { let map: HashMap<u8, Local> = HashMap::new(); \
+                     let set = BTreeSet::<u8>::new(); drop((map, set)); }"
+            )
+        );
+    }
+}
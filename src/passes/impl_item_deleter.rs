@@ -0,0 +1,77 @@
+//! Deletes the items of impls one by one. This runs before `item-deleter` deletes whole impls,
+//! so that impls that are only partially needed get smaller too.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, ImplItem, ItemImpl};
+
+use super::item_deleter::impl_name;
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path.push(impl_name(impl_));
+
+        impl_.items.retain(|item| {
+            let name = match item {
+                ImplItem::Const(const_) => const_.ident.to_string(),
+                ImplItem::Method(method) => method.sig.ident.to_string(),
+                ImplItem::Type(type_) => type_.ident.to_string(),
+                ImplItem::Macro(macro_) => macro_.mac.to_token_stream().to_string(),
+                _ => return true,
+            };
+
+            self.current_path.push(name);
+            let can_process = self.checker.can_process(&self.current_path);
+            self.current_path.pop();
+
+            if can_process {
+                self.process_state = ProcessState::Changed;
+            }
+            !can_process
+        });
+
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+#[derive(Default)]
+pub struct ImplItemDeleter;
+
+impl Pass for ImplItemDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-impl-items"
+    }
+}
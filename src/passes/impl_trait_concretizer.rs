@@ -0,0 +1,278 @@
+//! Replaces `impl Trait` in function signatures, as it hides which types are involved. A returned
+//! `impl Trait` is a candidate for being replaced with the concrete type of the returned expression, if that
+//! is obvious from the syntax, like `0..n` for `impl Iterator<Item = u32>`, which becomes
+//! `std::ops::Range<u32>`. An `impl Trait` argument is a candidate for becoming a named type parameter,
+//! which `concretize-generics` can then replace with the type that the function is called with.
+//! Methods of trait impls are skipped, as their signature has to match the trait.
+
+use quote::ToTokens;
+use syn::{
+    parse_quote, visit_mut::VisitMut, Block, Expr, FnArg, GenericArgument, GenericParam,
+    ImplItemMethod, ItemFn, ItemImpl, Lit, PathArguments, ReturnType, Signature, Stmt, Type,
+    TypeImplTrait, TypeParamBound,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    in_trait_impl: bool,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            in_trait_impl: false,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+
+    fn concretize(&mut self, sig: &mut Signature, block: &Block) {
+        if let ReturnType::Type(_, ty) = &mut sig.output {
+            if let Type::ImplTrait(impl_trait) = &**ty {
+                let concrete = match block.stmts.last() {
+                    Some(Stmt::Expr(tail)) => concrete_type(tail, iterator_item(impl_trait)),
+                    _ => None,
+                };
+                if let Some(concrete) = concrete {
+                    if self.can_process(format!("-> {}", ty.to_token_stream())) {
+                        **ty = concrete;
+                    }
+                }
+            }
+        }
+
+        let mut index = 0;
+        let mut added = 0;
+        for arg in &mut sig.inputs {
+            let FnArg::Typed(arg) = arg else {
+                continue;
+            };
+            let Type::ImplTrait(impl_trait) = &*arg.ty else {
+                continue;
+            };
+            if !self.can_process(arg.to_token_stream().to_string()) {
+                continue;
+            }
+            let name = loop {
+                let name = match index {
+                    0 => quote::format_ident!("I"),
+                    _ => quote::format_ident!("I{index}"),
+                };
+                index += 1;
+                let taken = sig.generics.params.iter().any(|param| match param {
+                    GenericParam::Type(param) => param.ident == name,
+                    GenericParam::Const(param) => param.ident == name,
+                    GenericParam::Lifetime(_) => false,
+                });
+                if !taken {
+                    break name;
+                }
+            };
+            let bounds = &impl_trait.bounds;
+            // Type parameters have to come after the lifetimes.
+            let lifetimes = sig
+                .generics
+                .params
+                .iter()
+                .take_while(|param| matches!(param, GenericParam::Lifetime(_)))
+                .count();
+            sig.generics
+                .params
+                .insert(lifetimes + added, parse_quote!(#name: #bounds));
+            added += 1;
+            arg.ty = parse_quote!(#name);
+        }
+        if !sig.generics.params.is_empty() && sig.generics.lt_token.is_none() {
+            sig.generics.lt_token = Some(Default::default());
+            sig.generics.gt_token = Some(Default::default());
+        }
+    }
+}
+
+/// The `T` of `impl Iterator<Item = T>`.
+fn iterator_item(impl_trait: &TypeImplTrait) -> Option<&Type> {
+    impl_trait.bounds.iter().find_map(|bound| {
+        let TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+        let segment = bound.path.segments.last()?;
+        if segment.ident != "Iterator" {
+            return None;
+        }
+        let PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return None;
+        };
+        args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Binding(binding) if binding.ident == "Item" => Some(&binding.ty),
+            _ => None,
+        })
+    })
+}
+
+/// The type of the expression, if it is obvious from the syntax. `item` is the item type of the iterator
+/// that the function returns.
+fn concrete_type(expr: &Expr, item: Option<&Type>) -> Option<Type> {
+    match expr {
+        Expr::Paren(paren) => concrete_type(&paren.expr, item),
+        Expr::Block(block) if block.label.is_none() => match block.block.stmts.last() {
+            Some(Stmt::Expr(tail)) => concrete_type(tail, item),
+            _ => None,
+        },
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Str(_) => Some(parse_quote!(&'static str)),
+            Lit::Bool(_) => Some(parse_quote!(bool)),
+            Lit::Char(_) => Some(parse_quote!(char)),
+            Lit::Int(int) if !int.suffix().is_empty() => syn::parse_str(int.suffix()).ok(),
+            Lit::Float(float) if !float.suffix().is_empty() => syn::parse_str(float.suffix()).ok(),
+            _ => None,
+        },
+        Expr::Struct(struct_)
+            if struct_
+                .path
+                .segments
+                .iter()
+                .all(|segment| segment.arguments.is_empty()) =>
+        {
+            let path = &struct_.path;
+            Some(parse_quote!(#path))
+        }
+        Expr::Range(range) => {
+            let item = item?;
+            match (&range.from, &range.to, &range.limits) {
+                (Some(_), Some(_), syn::RangeLimits::HalfOpen(_)) => {
+                    Some(parse_quote!(std::ops::Range<#item>))
+                }
+                (Some(_), Some(_), syn::RangeLimits::Closed(_)) => {
+                    Some(parse_quote!(std::ops::RangeInclusive<#item>))
+                }
+                (Some(_), None, _) => Some(parse_quote!(std::ops::RangeFrom<#item>)),
+                _ => None,
+            }
+        }
+        Expr::Call(call) => {
+            let item = item?;
+            let Expr::Path(func) = &*call.func else {
+                return None;
+            };
+            let segments = &func.path.segments;
+            let in_iter = segments.len() >= 2 && segments[segments.len() - 2].ident == "iter";
+            match segments.last()?.ident.to_string().as_str() {
+                "empty" if in_iter => Some(parse_quote!(std::iter::Empty<#item>)),
+                "once" if in_iter => Some(parse_quote!(std::iter::Once<#item>)),
+                "repeat" if in_iter => Some(parse_quote!(std::iter::Repeat<#item>)),
+                _ => None,
+            }
+        }
+        Expr::MethodCall(call) if call.method == "into_iter" && call.args.is_empty() => {
+            let item = item?;
+            let is_vec = match &*call.receiver {
+                Expr::Macro(mac) => mac.mac.path.is_ident("vec"),
+                Expr::Call(new) => new.func.to_token_stream().to_string() == "Vec :: new",
+                _ => false,
+            };
+            is_vec.then(|| parse_quote!(std::vec::IntoIter<#item>))
+        }
+        _ => None,
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        self.current_path.push(func.sig.ident.to_string());
+        self.concretize(&mut func.sig, &func.block);
+        syn::visit_mut::visit_item_fn_mut(self, func);
+        self.current_path.pop();
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        self.current_path.push(method.sig.ident.to_string());
+        if !self.in_trait_impl {
+            self.concretize(&mut method.sig, &method.block);
+        }
+        syn::visit_mut::visit_impl_item_method_mut(self, method);
+        self.current_path.pop();
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path
+            .push(impl_.self_ty.clone().into_token_stream().to_string());
+        let in_trait_impl = std::mem::replace(&mut self.in_trait_impl, impl_.trait_.is_some());
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.in_trait_impl = in_trait_impl;
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+fn concretize_impl_trait(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct ImplTraitConcretizer;
+
+impl Pass for ImplTraitConcretizer {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        concretize_impl_trait(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "concretize-impl-trait"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::concretize_impl_trait;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn concretize(src: &str) -> String {
+        apply_all(src, concretize_impl_trait)
+    }
+
+    #[test]
+    fn impl_trait_becomes_concrete() {
+        assert_eq!(
+            concretize(
+                "fn range(n: u32) -> impl Iterator<Item = u32> { 0..n } \
+                 fn empty() -> impl Iterator<Item = u8> { std::iter::empty() } \
+                 fn closure() -> impl Fn() { || () } \
+                 fn show<'a, T>(a: &'a T, b: impl std::fmt::Display, c: impl Copy) {} \
+                 impl Iterator for S { type Item = u8; fn next(&mut self) -> Option<u8> { None } \
+                     fn nth(&mut self, n: impl Copy) -> Option<u8> { None } }"
+            ),
+            tokens(
+                "fn range(n: u32) -> std::ops::Range<u32> { 0..n } \
+                 fn empty() -> std::iter::Empty<u8> { std::iter::empty() } \
+                 fn closure() -> impl Fn() { || () } \
+                 fn show<'a, I: std::fmt::Display, I1: Copy, T>(a: &'a T, b: I, c: I1) {} \
+                 impl Iterator for S { type Item = u8; fn next(&mut self) -> Option<u8> { None } \
+                     fn nth(&mut self, n: impl Copy) -> Option<u8> { None } }"
+            )
+        );
+    }
+}
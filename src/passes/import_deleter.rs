@@ -0,0 +1,85 @@
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Item};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn consider_deleting_import(&mut self, item: &Item) -> bool {
+        match item {
+            Item::Use(use_) => {
+                self.current_path
+                    .push(format!("use {}", use_.tree.to_token_stream()));
+
+                let can_process = self.checker.can_process(&self.current_path);
+                if can_process {
+                    self.process_state = ProcessState::Changed;
+                }
+
+                self.current_path.pop();
+                !can_process
+            }
+            _ => true,
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        file.items
+            .retain(|item| self.consider_deleting_import(item));
+
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+
+        if let Some((_, items)) = &mut module.content {
+            items.retain(|item| self.consider_deleting_import(item));
+        }
+
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_field_mut);
+    tracking!(visit_item_struct_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+#[derive(Default)]
+pub struct ImportDeleter;
+
+impl Pass for ImportDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-unused-imports"
+    }
+}
@@ -1,11 +1,25 @@
 use quote::ToTokens;
 use syn::{
-    visit_mut::VisitMut, Item, ItemConst, ItemEnum, ItemExternCrate, ItemFn, ItemMacro, ItemMacro2,
-    ItemMod, ItemStatic, ItemStruct, ItemTrait, ItemTraitAlias, ItemType, ItemUnion, Signature,
+    visit_mut::VisitMut, Item, ItemConst, ItemEnum, ItemExternCrate, ItemFn, ItemImpl, ItemMacro,
+    ItemMacro2, ItemMod, ItemStatic, ItemStruct, ItemTrait, ItemTraitAlias, ItemType, ItemUnion,
+    Signature,
 };
 
 use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
 
+/// Distinguishes the impls of different traits for the same type.
+pub(super) fn impl_name(impl_: &ItemImpl) -> String {
+    format!(
+        "({}) for ({})",
+        impl_
+            .trait_
+            .as_ref()
+            .map(|(_, tr, _)| tr.into_token_stream().to_string())
+            .unwrap_or_default(),
+        impl_.self_ty.clone().into_token_stream()
+    )
+}
+
 struct Visitor<'a> {
     current_path: Vec<String>,
     checker: &'a mut PassController,
@@ -32,15 +46,7 @@ impl<'a> Visitor<'a> {
     fn consider_deleting_item(&mut self, item: &Item) -> bool {
         match item {
             Item::Impl(impl_) => {
-                self.current_path.push(format!(
-                    "({}) for ({})",
-                    impl_
-                        .trait_
-                        .as_ref()
-                        .map(|(_, tr, _)| tr.into_token_stream().to_string())
-                        .unwrap_or_default(),
-                    impl_.self_ty.clone().into_token_stream()
-                ));
+                self.current_path.push(impl_name(impl_));
 
                 let should_retain = self.should_retain_item();
 
@@ -0,0 +1,285 @@
+//! Removes lifetime parameters of functions and methods together with their uses. References and paths
+//! get an elided lifetime and bounds on the lifetime are deleted. If elision picks a different lifetime
+//! than the parameter, the build fails and the change won't be kept.
+//! Methods of trait impls are skipped, as their signature has to match the trait.
+
+use quote::ToTokens;
+use syn::{
+    punctuated::Punctuated, visit_mut::VisitMut, AngleBracketedGenericArguments, GenericArgument,
+    GenericParam, Generics, ImplItemMethod, ItemFn, ItemImpl, Lifetime, LifetimeDef, PathArguments,
+    PredicateLifetime, PredicateType, TypeImplTrait, TypeParam, TypeParamBound, TypeReference,
+    TypeTraitObject, WherePredicate,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    in_trait_impl: bool,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            in_trait_impl: false,
+        }
+    }
+
+    /// Removes the lifetime parameters that may be removed and returns them.
+    fn remove_params(&mut self, generics: &mut Generics) -> Vec<Lifetime> {
+        let mut removed = Vec::new();
+        generics.params = std::mem::take(&mut generics.params)
+            .into_iter()
+            .filter(|param| {
+                let GenericParam::Lifetime(def) = param else {
+                    return true;
+                };
+
+                self.current_path.push(def.lifetime.to_string());
+                let can_process = self.checker.can_process(&self.current_path);
+                self.current_path.pop();
+                if can_process {
+                    removed.push(def.lifetime.clone());
+                }
+                !can_process
+            })
+            .collect();
+
+        if generics.params.is_empty() {
+            generics.lt_token = None;
+            generics.gt_token = None;
+        }
+        if !removed.is_empty() {
+            self.process_state = ProcessState::Changed;
+        }
+        removed
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        self.current_path.push(func.sig.ident.to_string());
+        for lifetime in self.remove_params(&mut func.sig.generics) {
+            RemoveLifetime {
+                lifetime: &lifetime,
+            }
+            .visit_item_fn_mut(func);
+        }
+        syn::visit_mut::visit_item_fn_mut(self, func);
+        self.current_path.pop();
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        self.current_path.push(method.sig.ident.to_string());
+        if !self.in_trait_impl {
+            for lifetime in self.remove_params(&mut method.sig.generics) {
+                RemoveLifetime {
+                    lifetime: &lifetime,
+                }
+                .visit_impl_item_method_mut(method);
+            }
+        }
+        syn::visit_mut::visit_impl_item_method_mut(self, method);
+        self.current_path.pop();
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path
+            .push(impl_.self_ty.clone().into_token_stream().to_string());
+        let in_trait_impl = std::mem::replace(&mut self.in_trait_impl, impl_.trait_.is_some());
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.in_trait_impl = in_trait_impl;
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+/// Removes the uses of a lifetime parameter that was removed.
+struct RemoveLifetime<'a> {
+    lifetime: &'a Lifetime,
+}
+
+impl RemoveLifetime<'_> {
+    fn is_removed(&self, lifetime: &Lifetime) -> bool {
+        lifetime.ident == self.lifetime.ident
+    }
+
+    fn remove_bounds<P: Default>(&self, bounds: &mut Punctuated<TypeParamBound, P>) {
+        *bounds = std::mem::take(bounds)
+            .into_iter()
+            .filter(|bound| {
+                !matches!(bound, TypeParamBound::Lifetime(lifetime) if self.is_removed(lifetime))
+            })
+            .collect();
+    }
+
+    fn remove_lifetimes<P: Default>(&self, lifetimes: &mut Punctuated<Lifetime, P>) {
+        *lifetimes = std::mem::take(lifetimes)
+            .into_iter()
+            .filter(|lifetime| !self.is_removed(lifetime))
+            .collect();
+    }
+}
+
+impl VisitMut for RemoveLifetime<'_> {
+    fn visit_type_reference_mut(&mut self, reference: &mut TypeReference) {
+        if reference
+            .lifetime
+            .as_ref()
+            .is_some_and(|lifetime| self.is_removed(lifetime))
+        {
+            reference.lifetime = None;
+        }
+        syn::visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_angle_bracketed_generic_arguments_mut(
+        &mut self,
+        args: &mut AngleBracketedGenericArguments,
+    ) {
+        args.args = std::mem::take(&mut args.args)
+            .into_iter()
+            .filter(|arg| {
+                !matches!(arg, GenericArgument::Lifetime(lifetime) if self.is_removed(lifetime))
+            })
+            .collect();
+        syn::visit_mut::visit_angle_bracketed_generic_arguments_mut(self, args);
+    }
+
+    fn visit_path_arguments_mut(&mut self, args: &mut PathArguments) {
+        syn::visit_mut::visit_path_arguments_mut(self, args);
+        if matches!(args, PathArguments::AngleBracketed(angle) if angle.args.is_empty()) {
+            *args = PathArguments::None;
+        }
+    }
+
+    fn visit_type_param_mut(&mut self, param: &mut TypeParam) {
+        self.remove_bounds(&mut param.bounds);
+        if param.bounds.is_empty() {
+            param.colon_token = None;
+        }
+        syn::visit_mut::visit_type_param_mut(self, param);
+    }
+
+    fn visit_lifetime_def_mut(&mut self, def: &mut LifetimeDef) {
+        self.remove_lifetimes(&mut def.bounds);
+        if def.bounds.is_empty() {
+            def.colon_token = None;
+        }
+        syn::visit_mut::visit_lifetime_def_mut(self, def);
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, ty: &mut TypeImplTrait) {
+        self.remove_bounds(&mut ty.bounds);
+        syn::visit_mut::visit_type_impl_trait_mut(self, ty);
+    }
+
+    fn visit_type_trait_object_mut(&mut self, ty: &mut TypeTraitObject) {
+        self.remove_bounds(&mut ty.bounds);
+        syn::visit_mut::visit_type_trait_object_mut(self, ty);
+    }
+
+    fn visit_predicate_type_mut(&mut self, predicate: &mut PredicateType) {
+        self.remove_bounds(&mut predicate.bounds);
+        syn::visit_mut::visit_predicate_type_mut(self, predicate);
+    }
+
+    fn visit_predicate_lifetime_mut(&mut self, predicate: &mut PredicateLifetime) {
+        self.remove_lifetimes(&mut predicate.bounds);
+        syn::visit_mut::visit_predicate_lifetime_mut(self, predicate);
+    }
+
+    fn visit_generics_mut(&mut self, generics: &mut Generics) {
+        syn::visit_mut::visit_generics_mut(self, generics);
+        let Some(where_clause) = &mut generics.where_clause else {
+            return;
+        };
+        where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+            .into_iter()
+            .filter(|predicate| match predicate {
+                WherePredicate::Type(predicate) => !predicate.bounds.is_empty(),
+                WherePredicate::Lifetime(predicate) => {
+                    !self.is_removed(&predicate.lifetime) && !predicate.bounds.is_empty()
+                }
+                WherePredicate::Eq(_) => true,
+            })
+            .collect();
+        if where_clause.predicates.is_empty() {
+            generics.where_clause = None;
+        }
+    }
+
+    // Items inside the function can't use the lifetime.
+    fn visit_item_mut(&mut self, _: &mut syn::Item) {}
+}
+
+fn remove_lifetimes(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct LifetimeRemover;
+
+impl Pass for LifetimeRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_lifetimes(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-lifetimes"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_lifetimes;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_lifetimes)
+    }
+
+    #[test]
+    fn removes_unnecessary_lifetime() {
+        assert_eq!(
+            remove("fn f<'a>(x: &'a u8) -> &'a u8 { x }"),
+            tokens("fn f(x: &u8) -> &u8 { x }")
+        );
+    }
+
+    #[test]
+    fn removes_bounds_and_arguments() {
+        assert_eq!(
+            remove(
+                "fn f<'a, 'b: 'a, T: Clone + 'a>(x: Foo<'a>, y: Box<dyn Fn() + 'a>) where T: 'a, 'a: 'b {
+                     let _ = 'a: loop { break 'a; };
+                 }"
+            ),
+            tokens(
+                "fn f<T: Clone>(x: Foo, y: Box<dyn Fn()>) {
+                     let _ = 'a: loop { break 'a; };
+                 }"
+            )
+        );
+    }
+
+    #[test]
+    fn skips_trait_impls() {
+        let src = "impl<'a> Trait for S { fn f<'b>(&self, x: &'b u8) {} }";
+        assert_eq!(remove(src), tokens(src));
+    }
+}
@@ -0,0 +1,156 @@
+//! Shrinks string literals and removes elements from array and `vec![]` literals.
+//! Every element is a separate candidate, so the ones that matter for the reproduction are kept.
+//! Strings are cut in half in every round until they either become empty or stop reproducing.
+
+use quote::ToTokens;
+use syn::{punctuated::Punctuated, visit_mut::VisitMut, Expr, ExprLit, Lit, LitStr, Macro, Token};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+
+    /// Elements with the same tokens are the same candidate, the paths have to stay the same when other
+    /// elements are removed.
+    fn remove_elements(&mut self, elems: &mut Punctuated<Expr, Token![,]>) {
+        *elems = std::mem::take(elems)
+            .into_iter()
+            .filter(|elem| !self.can_process(format!("[{}]", elem.to_token_stream())))
+            .collect();
+    }
+
+    fn shrink_str(&mut self, lit: &mut LitStr) {
+        let value = lit.value();
+        if value.is_empty() || !self.can_process(format!("{value:?}")) {
+            return;
+        }
+        let half = value.chars().count() / 2;
+        let shorter = value.chars().take(half).collect::<String>();
+        *lit = LitStr::new(&shorter, lit.span());
+    }
+
+    fn shrink_vec(&mut self, mac: &mut Macro) {
+        if !mac.path.is_ident("vec") {
+            return;
+        }
+        // `vec![elem; n]` doesn't parse as a list.
+        let Ok(mut elems) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        else {
+            return;
+        };
+        for elem in &mut elems {
+            self.visit_expr_mut(elem);
+        }
+        self.remove_elements(&mut elems);
+        mac.tokens = elems.into_token_stream();
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Array(array) => {
+                syn::visit_mut::visit_expr_array_mut(self, array);
+                self.remove_elements(&mut array.elems);
+            }
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(lit), ..
+            }) => self.shrink_str(lit),
+            Expr::Macro(mac) => self.shrink_vec(&mut mac.mac),
+            _ => syn::visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    tracking!();
+}
+
+#[derive(Default)]
+pub struct LiteralShrinker;
+
+impl Pass for LiteralShrinker {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "shrink-literals"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{minimize_sources, Options, Sources};
+
+    fn shrink(src: &str, reproduces: impl Fn(&str) -> bool + 'static) -> String {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(main.clone(), src.to_owned())]);
+        let options = Options {
+            passes: Some("shrink-literals".to_owned()),
+            ..Options::default()
+        };
+        let mut minimized = minimize_sources(&sources, options, move |sources| {
+            reproduces(&sources[&PathBuf::from("src/main.rs")])
+        })
+        .unwrap();
+        minimized.remove(&main).unwrap()
+    }
+
+    #[test]
+    fn keeps_elements_that_matter() {
+        let elems = (1000..1100).map(|n| n.to_string()).collect::<Vec<_>>();
+        let src = format!(
+            "fn main() {{\n    let _ = [{}];\n    let _ = vec![{}];\n}}\n",
+            elems.join(", "),
+            elems.join(", ")
+        );
+
+        let content = shrink(&src, |content| {
+            content.contains("1042") && content.contains("1077")
+        });
+
+        // The elements of both literals are the same candidates, as they have the same paths.
+        assert!(content.contains("let _ = [1042, 1077];"), "{content}");
+        assert!(content.contains("let _ = vec![1042, 1077];"), "{content}");
+    }
+
+    #[test]
+    fn halves_strings() {
+        let content = shrink(
+            "fn main() {\n    let _ = \"hello world\";\n}\n",
+            |content| content.contains("\"he"),
+        );
+
+        assert!(content.contains("let _ = \"he\";"), "{content}");
+    }
+}
@@ -0,0 +1,187 @@
+//! Deletes `macro_rules!` definitions and macro invocations in item and statement position. Macros can
+//! expand to a lot of code that doesn't matter for the issue. Invocations are tried first, and invocations
+//! whose expansion is needed are kept, as the build fails without them. Then the definitions of the macros
+//! that nothing in the file invokes anymore are tried. Macros that are still invoked in the file are kept.
+
+use std::collections::HashSet;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Expr, Item, ItemMacro, Macro, Stmt};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    /// The macros that are invoked in the file. `None` while the invocations are deleted, definitions are
+    /// only deleted afterwards.
+    invoked: Option<HashSet<String>>,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            invoked: None,
+        }
+    }
+
+    fn should_retain_item(&mut self, item: &Item) -> bool {
+        let Item::Macro(ItemMacro { ident, mac, .. }) = item else {
+            return true;
+        };
+        match (ident, &self.invoked) {
+            (Some(ident), Some(invoked)) if mac.path.is_ident("macro_rules") => {
+                invoked.contains(&ident.to_string())
+                    || self.should_retain(format!("macro_rules! {ident}"))
+            }
+            (Some(_), _) if mac.path.is_ident("macro_rules") => true,
+            (_, None) => self.should_retain(invocation_name(mac)),
+            (_, Some(_)) => true,
+        }
+    }
+
+    fn should_retain_stmt(&mut self, stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Item(item) => self.should_retain_item(item),
+            Stmt::Semi(Expr::Macro(expr), _) if self.invoked.is_none() => {
+                self.should_retain(invocation_name(&expr.mac))
+            }
+            _ => true,
+        }
+    }
+
+    fn should_retain(&mut self, name: String) -> bool {
+        self.current_path.push(name);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        !can_process
+    }
+}
+
+/// Invocations are named after the whole invocation, so that the same macro called with different
+/// arguments gives different candidates.
+fn invocation_name(mac: &Macro) -> String {
+    format!(
+        "{}!{}",
+        mac.path.to_token_stream(),
+        mac.tokens.to_token_stream()
+    )
+}
+
+/// The names of all macros that are invoked in the tokens, including the invocations in the bodies of other
+/// macros, which `syn` doesn't parse.
+pub(crate) fn invoked_macros(tokens: TokenStream, invoked: &mut HashSet<String>) {
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) => {
+                if matches!(tokens.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '!')
+                {
+                    invoked.insert(ident.to_string());
+                }
+            }
+            TokenTree::Group(group) => invoked_macros(group.stream(), invoked),
+            _ => {}
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        file.items.retain(|item| self.should_retain_item(item));
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+        if let Some((_, items)) = &mut module.content {
+            items.retain(|item| self.should_retain_item(item));
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block.stmts.retain(|stmt| self.should_retain_stmt(stmt));
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+fn delete_macros(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+
+    let mut invoked = HashSet::new();
+    invoked_macros(krate.to_token_stream(), &mut invoked);
+    visitor.invoked = Some(invoked);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct MacroDeleter;
+
+impl Pass for MacroDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        delete_macros(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-macros"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{minimize_sources, Options, Sources};
+
+    #[test]
+    fn deletes_unused_macro_rules() {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "macro_rules! used {\n    () => {\n        1\n    };\n}\n\
+             macro_rules! unused {\n    () => {};\n}\n\n\
+             fn main() {\n    let _ = used!();\n    unused!();\n}\n"
+                .to_owned(),
+        )]);
+        let options = Options {
+            passes: Some("delete-macros".to_owned()),
+            ..Options::default()
+        };
+
+        // Like the build, an invocation needs its definition. `used!()` is what reproduces.
+        let minimized = minimize_sources(&sources, options, |sources| {
+            let content = &sources[&PathBuf::from("src/main.rs")];
+            content.contains("let _ = used!()")
+                && content.contains("macro_rules! used")
+                && (!content.contains("unused!()") || content.contains("macro_rules! unused"))
+        })
+        .unwrap();
+
+        let content = &minimized[&main];
+        assert!(content.contains("macro_rules! used"), "{content}");
+        assert!(content.contains("let _ = used!();"), "{content}");
+        assert!(!content.contains("unused"), "{content}");
+    }
+}
@@ -0,0 +1,150 @@
+//! Simplifies `match` arms one by one, for matches where `collapse-branches` has to keep the whole match.
+//! The guard of an arm is a candidate for being removed, every binding in its pattern for becoming `_` and every
+//! statement of its block for being deleted. Bindings that are still used and arms that end up with the wrong
+//! type are rejected by the build.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Arm, Expr, FieldPat, Pat, PatWild};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    in_arm_pattern: bool,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            in_arm_pattern: false,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_arm_mut(&mut self, arm: &mut Arm) {
+        self.current_path
+            .push(format!("arm {}", arm.pat.to_token_stream()));
+
+        if let Some((_, guard)) = &arm.guard {
+            if self.can_process(format!("if {}", guard.to_token_stream())) {
+                arm.guard = None;
+            }
+        }
+
+        self.in_arm_pattern = true;
+        self.visit_pat_mut(&mut arm.pat);
+        self.in_arm_pattern = false;
+
+        if let Expr::Block(body) = &mut *arm.body {
+            // Statements are identified by their content, like in `delete-statements`.
+            body.block
+                .stmts
+                .retain(|stmt| !self.can_process(stmt.to_token_stream().to_string()));
+        }
+
+        if let Some((_, guard)) = &mut arm.guard {
+            self.visit_expr_mut(guard);
+        }
+        self.visit_expr_mut(&mut arm.body);
+        self.current_path.pop();
+    }
+
+    fn visit_pat_mut(&mut self, pat: &mut Pat) {
+        if self.in_arm_pattern {
+            if let Pat::Ident(binding) = pat {
+                // Uppercase names are most likely constants or unit variants, not bindings.
+                let ident = binding.ident.to_string();
+                if binding.subpat.is_none()
+                    && !ident.starts_with(char::is_uppercase)
+                    && self.can_process(format!("binding {ident}"))
+                {
+                    *pat = Pat::Wild(PatWild {
+                        attrs: Vec::new(),
+                        underscore_token: Default::default(),
+                    });
+                    return;
+                }
+            }
+        }
+
+        syn::visit_mut::visit_pat_mut(self, pat);
+    }
+
+    fn visit_field_pat_mut(&mut self, field: &mut FieldPat) {
+        syn::visit_mut::visit_field_pat_mut(self, field);
+        // `Foo { x }` has to become `Foo { x: _ }`.
+        if field.colon_token.is_none() && matches!(&*field.pat, Pat::Wild(_)) {
+            field.colon_token = Some(Default::default());
+        }
+    }
+
+    tracking!();
+}
+
+fn simplify_match_arms(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct MatchArmSimplifier;
+
+impl Pass for MatchArmSimplifier {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        simplify_match_arms(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "simplify-match-arms"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simplify_match_arms;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn simplify(src: &str) -> String {
+        apply_all(src, simplify_match_arms)
+    }
+
+    #[test]
+    fn simplifies_arms() {
+        assert_eq!(
+            simplify(
+                "fn f(e: E) { match e { \
+                     E::A { x, y: Some(v) } if v > 1 => { let a = v; g(a); } \
+                     E::B(n, None) => {} \
+                 } }"
+            ),
+            tokens(
+                "fn f(e: E) { match e { \
+                     E::A { x: _, y: Some(_) } => {} \
+                     E::B(_, None) => {} \
+                 } }"
+            )
+        );
+    }
+}
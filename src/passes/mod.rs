@@ -1,9 +1,197 @@
+use crate::processor::{Pass, Step};
+
+mod async_remover;
+mod attribute_stripper;
+mod binding_simplifier;
+mod blanket_impl_deleter;
+mod bounds_simplifier;
+mod branch_collapser;
+mod cfg_stripper;
+mod closure_hoister;
+mod const_deleter;
+mod default_body_remover;
+mod derive_stripper;
 mod everybody_loops;
+mod feature_stripper;
 mod field_deleter;
+mod field_type_replacer;
+mod generic_args_remover;
+mod generics_concretizer;
+mod glob_expander;
+mod impl_item_deleter;
+mod impl_trait_concretizer;
+mod import_deleter;
 mod item_deleter;
+mod lifetime_remover;
+mod literal_shrinker;
+mod macro_deleter;
+mod match_arm_simplifier;
+mod module_inliner;
+mod newtype_unwrapper;
+mod number_shrinker;
+mod param_deleter;
+mod pattern_simplifier;
+mod phantom_field_deleter;
 mod privatize;
+mod return_type_remover;
+mod statement_deleter;
+mod struct_literal_simplifier;
+mod trait_deleter;
+mod trait_method_deleter;
+mod try_remover;
+mod type_alias_inliner;
+mod type_deleter;
+mod type_param_remover;
+mod unreachable_deleter;
+mod variant_deleter;
+mod visibility_remover;
 
 pub use self::{
-    everybody_loops::EverybodyLoops, field_deleter::FieldDeleter, item_deleter::ItemDeleter,
-    privatize::Privatize,
+    async_remover::AsyncRemover, attribute_stripper::AttributeStripper,
+    binding_simplifier::BindingSimplifier, blanket_impl_deleter::BlanketImplDeleter,
+    bounds_simplifier::BoundsSimplifier, branch_collapser::BranchCollapser,
+    cfg_stripper::CfgStripper, closure_hoister::ClosureHoister, const_deleter::ConstDeleter,
+    default_body_remover::DefaultBodyRemover, derive_stripper::DeriveStripper,
+    everybody_loops::EverybodyLoops, feature_stripper::FeatureStripper,
+    field_deleter::FieldDeleter, field_type_replacer::FieldTypeReplacer,
+    generic_args_remover::GenericArgsRemover, generics_concretizer::GenericsConcretizer,
+    glob_expander::GlobExpander, impl_item_deleter::ImplItemDeleter,
+    impl_trait_concretizer::ImplTraitConcretizer, import_deleter::ImportDeleter,
+    item_deleter::ItemDeleter, lifetime_remover::LifetimeRemover,
+    literal_shrinker::LiteralShrinker, macro_deleter::MacroDeleter,
+    match_arm_simplifier::MatchArmSimplifier, module_inliner::ModuleInliner,
+    newtype_unwrapper::NewtypeUnwrapper, number_shrinker::NumberShrinker,
+    param_deleter::ParamDeleter, pattern_simplifier::PatternSimplifier,
+    phantom_field_deleter::PhantomFieldDeleter, privatize::Privatize,
+    return_type_remover::ReturnTypeRemover, statement_deleter::StatementDeleter,
+    struct_literal_simplifier::StructLiteralSimplifier, trait_deleter::TraitDeleter,
+    trait_method_deleter::TraitMethodDeleter, try_remover::TryRemover,
+    type_alias_inliner::TypeAliasInliner, type_deleter::TypeDeleter,
+    type_param_remover::TypeParamRemover, unreachable_deleter::UnreachableDeleter,
+    variant_deleter::VariantDeleter, visibility_remover::VisibilityRemover,
 };
+
+pub(crate) use self::{cfg_stripper::item_name, macro_deleter::invoked_macros};
+
+/// A pass in [`PASSES`].
+pub(crate) struct PassEntry {
+    pub(crate) name: &'static str,
+    /// Whether the pass runs when `--passes` isn't given.
+    pub(crate) default: bool,
+    create: fn() -> Step<'static>,
+}
+
+const fn default(name: &'static str, create: fn() -> Step<'static>) -> PassEntry {
+    PassEntry {
+        name,
+        default: true,
+        create,
+    }
+}
+
+const fn opt_in(name: &'static str, create: fn() -> Step<'static>) -> PassEntry {
+    PassEntry {
+        name,
+        default: false,
+        create,
+    }
+}
+
+fn pass<P: Pass + Default + 'static>() -> Step<'static> {
+    Step::Pass(P::default().boxed())
+}
+
+/// All passes, in the order that the default passes run in. The other passes only run when they are given
+/// in `--passes`.
+pub(crate) const PASSES: &[PassEntry] = &[
+    opt_in("strip-features", pass::<FeatureStripper>),
+    opt_in("strip-cfg", pass::<CfgStripper>),
+    default("everybody-loops", pass::<EverybodyLoops>),
+    opt_in("delete-statements", pass::<StatementDeleter>),
+    opt_in("collapse-branches", pass::<BranchCollapser>),
+    opt_in("delete-unreachable", pass::<UnreachableDeleter>),
+    opt_in("simplify-match-arms", pass::<MatchArmSimplifier>),
+    opt_in("simplify-patterns", pass::<PatternSimplifier>),
+    opt_in("simplify-bindings", pass::<BindingSimplifier>),
+    opt_in("remove-try", pass::<TryRemover>),
+    opt_in("hoist-closures", pass::<ClosureHoister>),
+    opt_in("delete-params", pass::<ParamDeleter>),
+    opt_in("remove-return-types", pass::<ReturnTypeRemover>),
+    opt_in("delete-phantom-fields", pass::<PhantomFieldDeleter>),
+    default("field-deleter", pass::<FieldDeleter>),
+    opt_in("unit-field-types", pass::<FieldTypeReplacer>),
+    opt_in("simplify-struct-literals", pass::<StructLiteralSimplifier>),
+    opt_in("delete-variants", pass::<VariantDeleter>),
+    opt_in("delete-blanket-impls", pass::<BlanketImplDeleter>),
+    opt_in("delete-trait-methods", pass::<TraitMethodDeleter>),
+    opt_in("remove-default-bodies", pass::<DefaultBodyRemover>),
+    default("privatize", pass::<Privatize>),
+    opt_in("remove-visibility", pass::<VisibilityRemover>),
+    opt_in("strip-derives", pass::<DeriveStripper>),
+    opt_in("strip-attributes", pass::<AttributeStripper>),
+    opt_in("simplify-bounds", pass::<BoundsSimplifier>),
+    opt_in("remove-lifetimes", pass::<LifetimeRemover>),
+    opt_in("concretize-impl-trait", pass::<ImplTraitConcretizer>),
+    opt_in("concretize-generics", pass::<GenericsConcretizer>),
+    opt_in("remove-type-params", pass::<TypeParamRemover>),
+    opt_in("remove-generic-args", pass::<GenericArgsRemover>),
+    opt_in("inline-type-aliases", pass::<TypeAliasInliner>),
+    opt_in("unwrap-newtypes", pass::<NewtypeUnwrapper>),
+    default("delete-unused-functions", || Step::DeleteUnusedFunctions),
+    opt_in("delete-consts", pass::<ConstDeleter>),
+    opt_in("delete-types", pass::<TypeDeleter>),
+    opt_in("delete-macros", pass::<MacroDeleter>),
+    opt_in("delete-unused-traits", pass::<TraitDeleter>),
+    opt_in("delete-impl-items", pass::<ImplItemDeleter>),
+    default("item-deleter", pass::<ItemDeleter>),
+    opt_in("shrink-literals", pass::<LiteralShrinker>),
+    opt_in("shrink-numbers", pass::<NumberShrinker>),
+    opt_in("expand-glob-imports", pass::<GlobExpander>),
+    opt_in("remove-unused-imports", pass::<ImportDeleter>),
+    opt_in("inline-modules", pass::<ModuleInliner>),
+    opt_in("remove-async", pass::<AsyncRemover>),
+    opt_in("flatten-crate", || Step::FlattenCrate),
+];
+
+/// Creates the pass with this name.
+pub(crate) fn by_name(name: &str) -> Option<Step<'static>> {
+    PASSES
+        .iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| (entry.create)())
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+
+    use super::{by_name, PASSES};
+    use crate::{processor::PassController, Options};
+
+    /// Runs `process` on `src` with a new controller and returns the tokens of the result. A new controller
+    /// applies every candidate, so this can't show which candidates are kept, that depends on the build and is
+    /// up to the full tests.
+    pub(super) fn apply_all<R>(
+        src: &str,
+        process: impl FnOnce(&mut syn::File, &mut PassController) -> R,
+    ) -> String {
+        let mut krate = syn::parse_str(src).unwrap();
+        process(&mut krate, &mut PassController::new(Options::default()));
+        krate.into_token_stream().to_string()
+    }
+
+    /// The tokens of `src`, to compare them with the result of [`apply_all`].
+    pub(super) fn tokens(src: &str) -> String {
+        syn::parse_str::<syn::File>(src)
+            .unwrap()
+            .into_token_stream()
+            .to_string()
+    }
+
+    #[test]
+    fn names_match_the_passes() {
+        for entry in PASSES {
+            assert_eq!(by_name(entry.name).unwrap().name(), entry.name);
+        }
+    }
+}
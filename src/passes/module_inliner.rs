@@ -0,0 +1,265 @@
+//! Lifts the items of inline modules into their parent.
+//! Paths to the items are rewritten on a best-effort basis, if we miss some the build will tell us.
+
+use std::mem;
+
+use quote::ToTokens;
+use syn::{punctuated::Punctuated, visit_mut::VisitMut, Ident, Item, ItemMod, ItemUse, UseTree};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    /// Top level modules that were inlined, `crate::` paths to them must be rewritten.
+    inlined_top_level: Vec<Ident>,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            inlined_top_level: Vec::new(),
+        }
+    }
+
+    fn should_inline(&mut self, module: &ItemMod) -> bool {
+        // Attributes like `#[cfg]` would have to be applied to every item, just leave them alone.
+        if module.content.is_none() || module.attrs.iter().any(|attr| !attr.path.is_ident("doc")) {
+            return false;
+        }
+
+        self.current_path.push(module.ident.to_string());
+        let can_process = self.checker.can_process(&self.current_path);
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        self.current_path.pop();
+        can_process
+    }
+
+    fn inline_modules(&mut self, items: &mut Vec<Item>) {
+        let mut inlined = Vec::new();
+
+        for item in mem::take(items) {
+            match item {
+                Item::Mod(module) if self.should_inline(&module) => {
+                    let (_, mut module_items) = module.content.expect("checked for content");
+
+                    // Everything from `super` is now in scope anyways.
+                    module_items.retain(|item| !is_use_of_super(item));
+                    for item in &mut module_items {
+                        StripSuper.visit_item_mut(item);
+                    }
+
+                    items.extend(module_items);
+                    inlined.push(module.ident);
+                }
+                item => items.push(item),
+            }
+        }
+
+        for module in &inlined {
+            items.retain_mut(|item| match item {
+                Item::Use(use_) => rewrite_use_in_parent(use_, module),
+                _ => true,
+            });
+            for item in items.iter_mut() {
+                RewriteRelative { module }.visit_item_mut(item);
+            }
+        }
+
+        if self.current_path.is_empty() {
+            self.inlined_top_level.extend(inlined);
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.inline_modules(&mut file.items);
+
+        syn::visit_mut::visit_file_mut(self, file);
+
+        for module in mem::take(&mut self.inlined_top_level) {
+            RewriteCrate { module: &module }.visit_file_mut(file);
+        }
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut ItemMod) {
+        self.current_path.push(module.ident.to_string());
+
+        if let Some((_, items)) = &mut module.content {
+            self.inline_modules(items);
+        }
+
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+fn is_use_of_super(item: &Item) -> bool {
+    match item {
+        Item::Use(ItemUse {
+            tree: UseTree::Path(path),
+            ..
+        }) if path.ident == "super" => {
+            !matches!(&*path.tree, UseTree::Path(next) if next.ident == "super")
+        }
+        _ => false,
+    }
+}
+
+/// Removes the segment at `index` from the path.
+fn remove_segment(path: &mut syn::Path, index: usize) {
+    path.segments = mem::take(&mut path.segments)
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| i != index)
+        .map(|(_, segment)| segment)
+        .collect();
+}
+
+/// `super` in the items of the inlined module now refers to one level further up.
+struct StripSuper;
+
+impl VisitMut for StripSuper {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if path.leading_colon.is_none()
+            && path.segments.len() > 1
+            && path.segments[0].ident == "super"
+        {
+            remove_segment(path, 0);
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_use_tree_mut(&mut self, tree: &mut UseTree) {
+        if let UseTree::Path(path) = tree {
+            if path.ident == "super" {
+                *tree = (*path.tree).clone();
+            }
+        }
+    }
+
+    // `super` in nested modules still refers to the same module.
+    fn visit_item_mod_mut(&mut self, _: &mut ItemMod) {}
+}
+
+/// Rewrites an import of the inlined module in its parent. Returns whether the import should be kept.
+fn rewrite_use_in_parent(use_: &mut ItemUse, module: &Ident) -> bool {
+    let tree = match &use_.tree {
+        UseTree::Path(path) if path.ident == "self" => &*path.tree,
+        tree => tree,
+    };
+    let UseTree::Path(path) = tree else {
+        return true;
+    };
+    if path.ident != *module {
+        return true;
+    }
+
+    // The items are now defined here, importing them again would be a conflict.
+    match &*path.tree {
+        UseTree::Name(_) | UseTree::Glob(_) => false,
+        UseTree::Group(group) => {
+            let items = group
+                .items
+                .iter()
+                .filter(|tree| !matches!(tree, UseTree::Name(_) | UseTree::Glob(_)))
+                .cloned()
+                .collect::<Punctuated<_, _>>();
+            if items.is_empty() {
+                return false;
+            }
+            let mut group = group.clone();
+            group.items = items;
+            use_.tree = UseTree::Group(group);
+            true
+        }
+        tree => {
+            use_.tree = tree.clone();
+            true
+        }
+    }
+}
+
+/// Rewrites `module::Item` and `self::module::Item` in the parent of the inlined module.
+struct RewriteRelative<'a> {
+    module: &'a Ident,
+}
+
+impl VisitMut for RewriteRelative<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if path.leading_colon.is_none() {
+            let segments = &path.segments;
+            if segments.len() > 1 && segments[0].ident == *self.module {
+                remove_segment(path, 0);
+            } else if segments.len() > 2
+                && segments[0].ident == "self"
+                && segments[1].ident == *self.module
+            {
+                remove_segment(path, 1);
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    // Relative paths in nested modules can't refer to the inlined module.
+    fn visit_item_mod_mut(&mut self, _: &mut ItemMod) {}
+}
+
+/// Rewrites `crate::module::Item` everywhere in the file.
+struct RewriteCrate<'a> {
+    module: &'a Ident,
+}
+
+impl VisitMut for RewriteCrate<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        let segments = &path.segments;
+        if segments.len() > 2 && segments[0].ident == "crate" && segments[1].ident == *self.module {
+            remove_segment(path, 1);
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_use_tree_mut(&mut self, tree: &mut UseTree) {
+        if let UseTree::Path(path) = tree {
+            if path.ident == "crate" {
+                if let UseTree::Path(next) = &*path.tree {
+                    if next.ident == *self.module {
+                        path.tree = next.tree.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ModuleInliner;
+
+impl Pass for ModuleInliner {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "inline-modules"
+    }
+}
@@ -0,0 +1,204 @@
+//! Replaces newtype wrappers like `struct W(u32);` with the type that they wrap. The uses of the type,
+//! constructor calls and patterns are replaced in the same file and the struct is deleted. Field accesses
+//! like `w.0` can't be told apart from other tuple fields, the build rejects the wrappers that are used
+//! like that. Wrappers with impls are kept, the impls can't be moved to the wrapped type.
+
+use std::collections::{HashMap, HashSet};
+
+use syn::{visit_mut::VisitMut, Expr, Fields, Item, ItemImpl, ItemStruct, Pat, Stmt, Type};
+
+use crate::processor::{Pass, PassController, ProcessState, SourceFile};
+
+/// Finds the newtypes in the file, by name.
+#[derive(Default)]
+struct FindNewtypes {
+    newtypes: HashMap<String, Type>,
+    /// Names that can't be unwrapped, because they have impls or there are several types with that name.
+    excluded: HashSet<String>,
+}
+
+impl VisitMut for FindNewtypes {
+    fn visit_item_struct_mut(&mut self, struct_: &mut ItemStruct) {
+        let name = struct_.ident.to_string();
+        match &struct_.fields {
+            Fields::Unnamed(fields)
+                if fields.unnamed.len() == 1 && struct_.generics.params.is_empty() =>
+            {
+                if self
+                    .newtypes
+                    .insert(name.clone(), fields.unnamed[0].ty.clone())
+                    .is_some()
+                {
+                    self.excluded.insert(name);
+                }
+            }
+            _ => {
+                self.excluded.insert(name);
+            }
+        }
+        syn::visit_mut::visit_item_struct_mut(self, struct_);
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        if let Some(name) = type_name(&impl_.self_ty) {
+            self.excluded.insert(name);
+        }
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+    }
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident().map(ToString::to_string),
+        _ => None,
+    }
+}
+
+/// Replaces the unwrapped newtypes with the wrapped types and deletes their structs.
+struct Unwrap<'a> {
+    unwrapped: &'a HashMap<String, Type>,
+}
+
+impl Unwrap<'_> {
+    fn is_unwrapped(&self, name: Option<String>) -> bool {
+        name.is_some_and(|name| self.unwrapped.contains_key(&name))
+    }
+
+    fn retain_item(&self, item: &Item) -> bool {
+        !matches!(item, Item::Struct(struct_) if self.unwrapped.contains_key(&struct_.ident.to_string()))
+    }
+}
+
+impl VisitMut for Unwrap<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        file.items.retain(|item| self.retain_item(item));
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut module.content {
+            items.retain(|item| self.retain_item(item));
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block
+            .stmts
+            .retain(|stmt| !matches!(stmt, Stmt::Item(item) if !self.retain_item(item)));
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        match type_name(ty).and_then(|name| self.unwrapped.get(&name)) {
+            // The wrapped type isn't visited, newtypes in it are unwrapped in the next round. This way, a
+            // type that wraps itself behind a pointer doesn't recurse forever.
+            Some(inner) => *ty = inner.clone(),
+            None => syn::visit_mut::visit_type_mut(self, ty),
+        }
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        syn::visit_mut::visit_expr_mut(self, expr);
+
+        if let Expr::Call(call) = expr {
+            let constructor = match &*call.func {
+                Expr::Path(path) if path.qself.is_none() => {
+                    path.path.get_ident().map(ToString::to_string)
+                }
+                _ => None,
+            };
+            if call.args.len() == 1 && self.is_unwrapped(constructor) {
+                *expr = call.args[0].clone();
+            }
+        }
+    }
+
+    fn visit_pat_mut(&mut self, pat: &mut Pat) {
+        syn::visit_mut::visit_pat_mut(self, pat);
+
+        if let Pat::TupleStruct(tuple) = pat {
+            let name = tuple.path.get_ident().map(ToString::to_string);
+            if tuple.pat.elems.len() == 1 && self.is_unwrapped(name) {
+                *pat = tuple.pat.elems[0].clone();
+            }
+        }
+    }
+}
+
+fn unwrap_newtypes(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut find = FindNewtypes::default();
+    find.visit_file_mut(krate);
+
+    // Sorted, so that the candidates are asked for in the same order every time.
+    let mut names = find
+        .newtypes
+        .keys()
+        .filter(|name| !find.excluded.contains(*name))
+        .cloned()
+        .collect::<Vec<_>>();
+    names.sort();
+
+    // The candidates are not nested in modules, the uses may be anywhere in the file.
+    let unwrapped = names
+        .into_iter()
+        .filter(|name| checker.can_process(std::slice::from_ref(name)))
+        .map(|name| {
+            let inner = find.newtypes[&name].clone();
+            (name, inner)
+        })
+        .collect::<HashMap<_, _>>();
+    if unwrapped.is_empty() {
+        return ProcessState::NoChange;
+    }
+
+    Unwrap {
+        unwrapped: &unwrapped,
+    }
+    .visit_file_mut(krate);
+    ProcessState::Changed
+}
+
+#[derive(Default)]
+pub struct NewtypeUnwrapper;
+
+impl Pass for NewtypeUnwrapper {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        unwrap_newtypes(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "unwrap-newtypes"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unwrap_newtypes;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn unwrap(src: &str) -> String {
+        apply_all(src, unwrap_newtypes)
+    }
+
+    #[test]
+    fn newtype_becomes_wrapped_type() {
+        assert_eq!(
+            unwrap(
+                "struct W(u32); struct Kept(u8); impl Kept {} \
+                 fn f(w: W) -> Vec<W> { let W(inner) = w; vec![W(inner)] } \
+                 fn main() { f(W(1)); }"
+            ),
+            tokens(
+                "struct Kept(u8); impl Kept {} \
+                 fn f(w: u32) -> Vec<u32> { let inner = w; vec![W(inner)] } \
+                 fn main() { f(1); }"
+            )
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Shrinks integer and float literals towards zero. Integers are halved in every round until they either
+//! become zero or stop reproducing, floats are replaced with zero directly. The type suffix is kept, and
+//! halving never goes out of the range of the type.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, LitFloat, LitInt};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    /// Literals with the same tokens are the same candidate.
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_lit_int_mut(&mut self, lit: &mut LitInt) {
+        let Ok(value) = lit.base10_parse::<u128>() else {
+            return;
+        };
+        if value == 0 || !self.can_process(lit.to_string()) {
+            return;
+        }
+        *lit = LitInt::new(&format!("{}{}", value / 2, lit.suffix()), lit.span());
+    }
+
+    fn visit_lit_float_mut(&mut self, lit: &mut LitFloat) {
+        if lit.base10_parse::<f64>().ok() == Some(0.0) || !self.can_process(lit.to_string()) {
+            return;
+        }
+        *lit = LitFloat::new(&format!("0.0{}", lit.suffix()), lit.span());
+    }
+
+    tracking!();
+}
+
+#[derive(Default)]
+pub struct NumberShrinker;
+
+impl Pass for NumberShrinker {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "shrink-numbers"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{minimize_sources, Options, Sources};
+
+    #[test]
+    fn keeps_significant_constant() {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "const SIGNIFICANT: u32 = 4096;\n\nfn main() {\n    let _ = 1_000_000u64;\n    let _ = 2.5f32;\n}\n"
+                .to_owned(),
+        )]);
+        let options = Options {
+            passes: Some("shrink-numbers".to_owned()),
+            ..Options::default()
+        };
+
+        let minimized = minimize_sources(&sources, options, |sources| {
+            sources[&PathBuf::from("src/main.rs")].contains("= 4096;")
+        })
+        .unwrap();
+
+        let content = &minimized[&main];
+        assert!(
+            content.contains("const SIGNIFICANT: u32 = 4096;"),
+            "{content}"
+        );
+        assert!(content.contains("let _ = 0u64;"), "{content}");
+        assert!(content.contains("let _ = 0.0f32;"), "{content}");
+    }
+}
@@ -0,0 +1,197 @@
+//! Deletes parameters of functions and the corresponding arguments at their call sites.
+//! Only calls in the same file are updated, calls in other files are left to the build to complain about.
+//! Methods of trait impls are skipped, as their signature has to match the trait. Functions whose name is
+//! used by more than one function in the file are skipped too, as we can't tell their calls apart.
+
+use std::collections::HashMap;
+
+use quote::ToTokens;
+use syn::{
+    punctuated::Punctuated, visit_mut::VisitMut, Expr, ExprCall, ExprMethodCall, FnArg,
+    ImplItemMethod, ItemFn, ItemImpl, Signature,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+/// Counts how often every function name is defined in the file.
+#[derive(Default)]
+struct CountFns {
+    counts: HashMap<String, usize>,
+}
+
+impl VisitMut for CountFns {
+    fn visit_signature_mut(&mut self, sig: &mut Signature) {
+        *self.counts.entry(sig.ident.to_string()).or_default() += 1;
+    }
+}
+
+/// The parameters that were deleted from a function.
+struct Deleted {
+    /// The indices of the deleted parameters, counting the `self` parameter.
+    indices: Vec<usize>,
+    has_receiver: bool,
+}
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    counts: HashMap<String, usize>,
+    deleted: HashMap<String, Deleted>,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController, counts: HashMap<String, usize>) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            counts,
+            deleted: HashMap::new(),
+        }
+    }
+
+    fn delete_params(&mut self, sig: &mut Signature) {
+        let name = sig.ident.to_string();
+        if self.counts.get(&name) != Some(&1) {
+            return;
+        }
+
+        let has_receiver = sig.receiver().is_some();
+        let mut indices = Vec::new();
+        let mut index = 0;
+        sig.inputs = std::mem::take(&mut sig.inputs)
+            .into_iter()
+            .filter(|input| {
+                index += 1;
+                let FnArg::Typed(param) = input else {
+                    return true;
+                };
+
+                self.current_path.push(param.to_token_stream().to_string());
+                let can_process = self.checker.can_process(&self.current_path);
+                self.current_path.pop();
+                if can_process {
+                    indices.push(index - 1);
+                }
+                !can_process
+            })
+            .collect();
+
+        if !indices.is_empty() {
+            self.process_state = ProcessState::Changed;
+            self.deleted.insert(
+                name,
+                Deleted {
+                    indices,
+                    has_receiver,
+                },
+            );
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        self.current_path.push(func.sig.ident.to_string());
+        self.delete_params(&mut func.sig);
+        syn::visit_mut::visit_item_fn_mut(self, func);
+        self.current_path.pop();
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path
+            .push(impl_.self_ty.clone().into_token_stream().to_string());
+        if impl_.trait_.is_none() {
+            for item in &mut impl_.items {
+                if let syn::ImplItem::Method(method) = item {
+                    self.current_path.push(method.sig.ident.to_string());
+                    self.delete_params(&mut method.sig);
+                    self.current_path.pop();
+                }
+            }
+        }
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.current_path.pop();
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        self.current_path.push(method.sig.ident.to_string());
+        syn::visit_mut::visit_impl_item_method_mut(self, method);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+/// Removes the arguments of the deleted parameters from the calls.
+struct FixCalls<'a> {
+    deleted: &'a HashMap<String, Deleted>,
+}
+
+fn remove_args(args: &mut Punctuated<Expr, syn::token::Comma>, indices: &[usize], offset: usize) {
+    let mut index = offset;
+    *args = std::mem::take(args)
+        .into_iter()
+        .filter(|_| {
+            index += 1;
+            !indices.contains(&(index - 1))
+        })
+        .collect();
+}
+
+impl VisitMut for FixCalls<'_> {
+    fn visit_expr_call_mut(&mut self, call: &mut ExprCall) {
+        if let Expr::Path(path) = &*call.func {
+            let deleted = path
+                .path
+                .segments
+                .last()
+                .and_then(|segment| self.deleted.get(&segment.ident.to_string()));
+            if let Some(deleted) = deleted {
+                remove_args(&mut call.args, &deleted.indices, 0);
+            }
+        }
+        syn::visit_mut::visit_expr_call_mut(self, call);
+    }
+
+    fn visit_expr_method_call_mut(&mut self, call: &mut ExprMethodCall) {
+        if let Some(deleted) = self.deleted.get(&call.method.to_string()) {
+            if deleted.has_receiver {
+                // The receiver is not part of the arguments.
+                remove_args(&mut call.args, &deleted.indices, 1);
+            }
+        }
+        syn::visit_mut::visit_expr_method_call_mut(self, call);
+    }
+}
+
+#[derive(Default)]
+pub struct ParamDeleter;
+
+impl Pass for ParamDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut count_fns = CountFns::default();
+        count_fns.visit_file_mut(krate);
+
+        let mut visitor = Visitor::new(checker, count_fns.counts);
+        visitor.visit_file_mut(krate);
+
+        FixCalls {
+            deleted: &visitor.deleted,
+        }
+        .visit_file_mut(krate);
+
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-params"
+    }
+}
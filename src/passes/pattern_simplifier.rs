@@ -0,0 +1,163 @@
+//! Simplifies the patterns of `match` arms and `let` bindings. Every pattern is a candidate for becoming `_`.
+//! If it has to stay, struct patterns are candidates for ignoring all their fields with `Foo { .. }` and
+//! `Foo(..)`, and then the patterns inside of it are tried one by one. Patterns whose bindings are still used
+//! don't build and are rejected by the build.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Arm, Local, Pat, PatRest, PatWild};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+
+    fn simplify(&mut self, pat: &mut Pat) {
+        match pat {
+            Pat::Wild(_) | Pat::Rest(_) => return,
+            // The type of a `let` stays, only its pattern is simplified.
+            Pat::Type(typed) => return self.simplify(&mut typed.pat),
+            _ => {}
+        }
+
+        if self.can_process(pat.to_token_stream().to_string()) {
+            *pat = Pat::Wild(PatWild {
+                attrs: Vec::new(),
+                underscore_token: Default::default(),
+            });
+            return;
+        }
+
+        match pat {
+            Pat::Struct(struct_) => {
+                if !struct_.fields.is_empty() {
+                    let path = struct_.path.to_token_stream();
+                    if self.can_process(format!("{path} {{ .. }}")) {
+                        struct_.fields.clear();
+                        struct_.dot2_token = Some(Default::default());
+                        return;
+                    }
+                }
+                for field in &mut struct_.fields {
+                    self.simplify(&mut field.pat);
+                    // `Foo { a }` is short for `Foo { a: a }`, the name has to be kept without the binding.
+                    if !matches!(&*field.pat, Pat::Ident(_)) {
+                        field.colon_token.get_or_insert_with(Default::default);
+                    }
+                }
+            }
+            Pat::TupleStruct(tuple_struct) => {
+                let elems = &mut tuple_struct.pat.elems;
+                let ignores_all = elems.len() == 1 && matches!(elems.first(), Some(Pat::Rest(_)));
+                if !elems.is_empty() && !ignores_all {
+                    let path = tuple_struct.path.to_token_stream();
+                    if self.can_process(format!("{path}(..)")) {
+                        elems.clear();
+                        elems.push(Pat::Rest(PatRest {
+                            attrs: Vec::new(),
+                            dot2_token: Default::default(),
+                        }));
+                        return;
+                    }
+                }
+                elems.iter_mut().for_each(|elem| self.simplify(elem));
+            }
+            Pat::Tuple(tuple) => tuple.elems.iter_mut().for_each(|elem| self.simplify(elem)),
+            Pat::Slice(slice) => slice.elems.iter_mut().for_each(|elem| self.simplify(elem)),
+            Pat::Or(or) => or.cases.iter_mut().for_each(|case| self.simplify(case)),
+            Pat::Reference(reference) => self.simplify(&mut reference.pat),
+            Pat::Box(box_) => self.simplify(&mut box_.pat),
+            Pat::Ident(ident) => {
+                if let Some((_, subpat)) = &mut ident.subpat {
+                    self.simplify(subpat);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_arm_mut(&mut self, arm: &mut Arm) {
+        self.simplify(&mut arm.pat);
+        syn::visit_mut::visit_arm_mut(self, arm);
+    }
+
+    fn visit_local_mut(&mut self, local: &mut Local) {
+        self.simplify(&mut local.pat);
+        syn::visit_mut::visit_local_mut(self, local);
+    }
+
+    tracking!();
+}
+
+fn simplify_patterns(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct PatternSimplifier;
+
+impl Pass for PatternSimplifier {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        simplify_patterns(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "simplify-patterns"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simplify_patterns;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn simplify(src: &str) -> String {
+        apply_all(src, simplify_patterns)
+    }
+
+    #[test]
+    fn struct_pattern_becomes_wildcard() {
+        assert_eq!(
+            simplify(
+                "fn f(foo: Foo) -> u8 { \
+                     let Foo { a, b }: Foo = foo; \
+                     match foo { Foo { a, b: Some(_) } => 1, Foo { .. } => 2 } }"
+            ),
+            tokens(
+                "fn f(foo: Foo) -> u8 { \
+                     let _: Foo = foo; \
+                     match foo { _ => 1, _ => 2 } }"
+            )
+        );
+    }
+}
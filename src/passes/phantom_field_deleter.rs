@@ -0,0 +1,207 @@
+//! Deletes `PhantomData` fields of structs together with the generic parameters that only they used.
+//! `field-deleter` can't delete such a field on its own, as the struct would then have an unused parameter,
+//! which is an error. Every `PhantomData` field is a candidate. Uses of the struct that pass the parameter
+//! are left to `remove-generic-args`, and fields that are needed for variance or auto traits are kept by the
+//! build.
+
+use std::collections::HashSet;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Fields, GenericParam, ItemStruct, Type, WherePredicate};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+}
+
+fn is_phantom_data(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "PhantomData"),
+        _ => false,
+    }
+}
+
+/// All identifiers in the tokens. Lifetimes are included without their `'`.
+fn idents(tokens: TokenStream, idents: &mut HashSet<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => self::idents(group.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+/// Removes the generic parameters that the fields don't use anymore, with the where clauses about them.
+fn remove_unused_params(struct_: &mut ItemStruct) {
+    let mut used = HashSet::new();
+    idents(struct_.fields.to_token_stream(), &mut used);
+    let name = |param: &GenericParam| match param {
+        GenericParam::Type(param) => param.ident.to_string(),
+        GenericParam::Lifetime(param) => param.lifetime.ident.to_string(),
+        GenericParam::Const(param) => param.ident.to_string(),
+    };
+    let removed = struct_
+        .generics
+        .params
+        .iter()
+        .map(name)
+        .filter(|param| !used.contains(param))
+        .collect::<HashSet<_>>();
+    if removed.is_empty() {
+        return;
+    }
+
+    struct_.generics.params = std::mem::take(&mut struct_.generics.params)
+        .into_pairs()
+        .filter(|pair| !removed.contains(&name(pair.value())))
+        .collect();
+    if struct_.generics.params.is_empty() {
+        struct_.generics.lt_token = None;
+        struct_.generics.gt_token = None;
+    }
+    if let Some(where_clause) = &mut struct_.generics.where_clause {
+        where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+            .into_pairs()
+            .filter(|pair| {
+                let mut mentioned = HashSet::new();
+                let bounded = match pair.value() {
+                    WherePredicate::Type(predicate) => predicate.bounded_ty.to_token_stream(),
+                    WherePredicate::Lifetime(predicate) => predicate.lifetime.to_token_stream(),
+                    WherePredicate::Eq(predicate) => predicate.lhs_ty.to_token_stream(),
+                };
+                idents(bounded, &mut mentioned);
+                mentioned.is_disjoint(&removed)
+            })
+            .collect();
+        if where_clause.predicates.is_empty() {
+            struct_.generics.where_clause = None;
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_struct_mut(&mut self, struct_: &mut ItemStruct) {
+        self.current_path.push(struct_.ident.to_string());
+
+        let mut changed = false;
+        match &mut struct_.fields {
+            Fields::Named(named) => {
+                named.named = std::mem::take(&mut named.named)
+                    .into_pairs()
+                    .filter(|pair| {
+                        let field = pair.value();
+                        let delete = is_phantom_data(&field.ty)
+                            && self.can_process(field.ident.as_ref().unwrap().to_string());
+                        changed |= delete;
+                        !delete
+                    })
+                    .collect();
+            }
+            Fields::Unnamed(unnamed) => {
+                unnamed.unnamed = std::mem::take(&mut unnamed.unnamed)
+                    .into_pairs()
+                    .enumerate()
+                    .filter(|(index, pair)| {
+                        let delete = is_phantom_data(&pair.value().ty)
+                            && self.can_process(index.to_string());
+                        changed |= delete;
+                        !delete
+                    })
+                    .map(|(_, pair)| pair)
+                    .collect();
+            }
+            Fields::Unit => {}
+        }
+        if changed {
+            remove_unused_params(struct_);
+        }
+
+        syn::visit_mut::visit_item_struct_mut(self, struct_);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+fn delete_phantom_fields(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct PhantomFieldDeleter;
+
+impl Pass for PhantomFieldDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        delete_phantom_fields(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-phantom-fields"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::delete_phantom_fields;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn delete(src: &str) -> String {
+        apply_all(src, delete_phantom_fields)
+    }
+
+    #[test]
+    fn deletes_phantom_field_with_its_param() {
+        assert_eq!(
+            delete(
+                "struct Id<T, U> where T: Clone, U: Copy { id: u32, _marker: PhantomData<T>, u: U } \
+                 struct Ref<'a>(std::marker::PhantomData<&'a ()>, u8);"
+            ),
+            tokens(
+                "struct Id<U> where U: Copy { id: u32, u: U } \
+                 struct Ref(u8);"
+            )
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Removes the return types of functions. The return type of a function is a candidate for being removed,
+//! which makes it return `()`. The tail expression of the body becomes a statement, so it is still evaluated
+//! but its value is dropped. If a caller uses the value or the body returns early with a value, the build
+//! fails and the return type stays. Methods of traits and trait impls are skipped, as the signatures of the
+//! trait and its impls have to match.
+
+use quote::ToTokens;
+use syn::{
+    visit_mut::VisitMut, Block, ImplItemMethod, ItemFn, ItemImpl, ReturnType, Signature, Stmt,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    in_trait_impl: bool,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            in_trait_impl: false,
+        }
+    }
+
+    fn remove_return_type(&mut self, sig: &mut Signature, block: &mut Block) {
+        let ReturnType::Type(_, ty) = &sig.output else {
+            return;
+        };
+        self.current_path
+            .push(format!("-> {}", ty.to_token_stream()));
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if !can_process {
+            return;
+        }
+        self.process_state = ProcessState::Changed;
+
+        sig.output = ReturnType::Default;
+        if let Some(Stmt::Expr(tail)) = block.stmts.pop() {
+            block.stmts.push(Stmt::Semi(tail, Default::default()));
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        self.current_path.push(func.sig.ident.to_string());
+        self.remove_return_type(&mut func.sig, &mut func.block);
+        syn::visit_mut::visit_item_fn_mut(self, func);
+        self.current_path.pop();
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        self.current_path.push(method.sig.ident.to_string());
+        if !self.in_trait_impl {
+            self.remove_return_type(&mut method.sig, &mut method.block);
+        }
+        syn::visit_mut::visit_impl_item_method_mut(self, method);
+        self.current_path.pop();
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path
+            .push(impl_.self_ty.clone().into_token_stream().to_string());
+        let in_trait_impl = std::mem::replace(&mut self.in_trait_impl, impl_.trait_.is_some());
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.in_trait_impl = in_trait_impl;
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+fn remove_return_types(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct ReturnTypeRemover;
+
+impl Pass for ReturnTypeRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_return_types(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-return-types"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_return_types;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_return_types)
+    }
+
+    #[test]
+    fn return_type_becomes_unit() {
+        assert_eq!(
+            remove(
+                "fn f() -> String { let s = String::new(); s } \
+                 impl S { fn new() -> Self { S } } \
+                 impl Default for S { fn default() -> Self { S } }"
+            ),
+            tokens(
+                "fn f() { let s = String::new(); s; } \
+                 impl S { fn new() { S; } } \
+                 impl Default for S { fn default() -> Self { S } }"
+            )
+        );
+    }
+}
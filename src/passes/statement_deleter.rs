@@ -0,0 +1,64 @@
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Stmt};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn should_retain_stmt(&mut self, stmt: &Stmt) -> bool {
+        // Statements are identified by their content instead of their index, as the indices shift
+        // when statements before them are deleted.
+        self.current_path.push(stmt.to_token_stream().to_string());
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        !can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block.stmts.retain(|stmt| self.should_retain_stmt(stmt));
+
+        // Statements in deleted statements don't need to be visited.
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+
+    tracking!();
+}
+
+#[derive(Default)]
+pub struct StatementDeleter;
+
+impl Pass for StatementDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-statements"
+    }
+}
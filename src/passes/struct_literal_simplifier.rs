@@ -0,0 +1,120 @@
+//! Simplifies the fields of struct literals. Every field initializer that is more than a literal or a path is
+//! a candidate for being replaced with `Default::default()`, which builds for most field types. The fields of
+//! a literal with a `..base` are candidates for being removed instead, as the base fills them in. Without a
+//! base, every field of the struct definition is required, so none of them are removed.
+
+use quote::ToTokens;
+use syn::{parse_quote, visit_mut::VisitMut, Expr, ExprStruct};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+}
+
+/// Whether the initializer can't get any simpler by replacing it.
+fn is_simple(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(_) | Expr::Path(_) => true,
+        Expr::Call(call) => {
+            call.args.is_empty() && call.func.to_token_stream().to_string() == "Default :: default"
+        }
+        _ => false,
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_expr_struct_mut(&mut self, struct_: &mut ExprStruct) {
+        syn::visit_mut::visit_expr_struct_mut(self, struct_);
+
+        let path = struct_.path.to_token_stream().to_string();
+        let has_base = struct_.rest.is_some();
+        let fields = std::mem::take(&mut struct_.fields);
+        for mut field in fields {
+            let member = field.member.to_token_stream();
+            let candidate = has_base || !is_simple(&field.expr);
+            if candidate && self.can_process(format!("{path} {{ {member} }}")) {
+                if has_base {
+                    continue;
+                }
+                field.expr = parse_quote!(Default::default());
+                field.colon_token.get_or_insert_with(Default::default);
+            }
+            struct_.fields.push(field);
+        }
+    }
+
+    tracking!();
+}
+
+fn simplify_struct_literals(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct StructLiteralSimplifier;
+
+impl Pass for StructLiteralSimplifier {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        simplify_struct_literals(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "simplify-struct-literals"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simplify_struct_literals;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn simplify(src: &str) -> String {
+        apply_all(src, simplify_struct_literals)
+    }
+
+    #[test]
+    fn simplifies_field_initializers() {
+        assert_eq!(
+            simplify(
+                "fn f(c: u8) -> (Foo, Foo) { \
+                     (Foo { a: compute(1, 2), b: 1, c }, \
+                      Foo { a: compute(3, 4), ..Foo::new() }) }"
+            ),
+            tokens(
+                "fn f(c: u8) -> (Foo, Foo) { \
+                     (Foo { a: Default::default(), b: 1, c }, \
+                      Foo { ..Foo::new() }) }"
+            )
+        );
+    }
+}
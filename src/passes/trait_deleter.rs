@@ -0,0 +1,204 @@
+//! Deletes traits that are never used, together with all of their impls in the file, as a single candidate.
+//! Deleting the impls one by one can't work, the build fails until the last one is gone, and deleting the
+//! trait first fails because of the impls. A trait counts as used if a path or import mentions it outside
+//! of impl headers, or if a path or method call mentions one of its items, as calls don't name the trait.
+
+use std::collections::{HashMap, HashSet};
+
+use syn::{
+    visit_mut::VisitMut, ExprMethodCall, Item, ItemImpl, ItemTrait, Path, Stmt, TraitItem, UseName,
+    UseRename,
+};
+
+use crate::processor::{Pass, PassController, ProcessState, SourceFile};
+
+/// The traits of the file with the names of their items.
+#[derive(Default)]
+struct FindTraits {
+    traits: HashMap<String, Vec<String>>,
+    /// Traits with the same name in different modules can't be told apart, so they are skipped.
+    seen: HashSet<String>,
+}
+
+impl VisitMut for FindTraits {
+    fn visit_item_trait_mut(&mut self, trait_: &mut ItemTrait) {
+        let name = trait_.ident.to_string();
+        if self.seen.insert(name.clone()) {
+            let items = trait_
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    TraitItem::Const(const_) => Some(const_.ident.to_string()),
+                    TraitItem::Method(method) => Some(method.sig.ident.to_string()),
+                    TraitItem::Type(type_) => Some(type_.ident.to_string()),
+                    _ => None,
+                })
+                .collect();
+            self.traits.insert(name, items);
+        } else {
+            self.traits.remove(&name);
+        }
+        syn::visit_mut::visit_item_trait_mut(self, trait_);
+    }
+}
+
+/// Collects the names that the file mentions, except for the traits of impl headers.
+#[derive(Default)]
+struct FindUses {
+    names: HashSet<String>,
+}
+
+impl VisitMut for FindUses {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        self.names.extend(
+            path.segments
+                .iter()
+                .map(|segment| segment.ident.to_string()),
+        );
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_expr_method_call_mut(&mut self, call: &mut ExprMethodCall) {
+        self.names.insert(call.method.to_string());
+        syn::visit_mut::visit_expr_method_call_mut(self, call);
+    }
+
+    fn visit_use_name_mut(&mut self, name: &mut UseName) {
+        self.names.insert(name.ident.to_string());
+    }
+
+    fn visit_use_rename_mut(&mut self, rename: &mut UseRename) {
+        self.names.insert(rename.ident.to_string());
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        // The generic arguments of the trait still count, only its name doesn't.
+        let mut trait_ = impl_.trait_.take();
+        if let Some((_, path, _)) = &mut trait_ {
+            for segment in &mut path.segments {
+                self.visit_path_arguments_mut(&mut segment.arguments);
+            }
+        }
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        impl_.trait_ = trait_;
+    }
+}
+
+/// Deletes the traits and their impls.
+struct DeleteTraits<'a> {
+    deleted: &'a HashSet<String>,
+}
+
+impl DeleteTraits<'_> {
+    fn retain_item(&self, item: &Item) -> bool {
+        let name = match item {
+            Item::Trait(trait_) => &trait_.ident,
+            Item::Impl(ItemImpl {
+                trait_: Some((_, path, _)),
+                ..
+            }) => match path.segments.last() {
+                Some(segment) => &segment.ident,
+                None => return true,
+            },
+            _ => return true,
+        };
+        !self.deleted.contains(&name.to_string())
+    }
+}
+
+impl VisitMut for DeleteTraits<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        file.items.retain(|item| self.retain_item(item));
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut module.content {
+            items.retain(|item| self.retain_item(item));
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block
+            .stmts
+            .retain(|stmt| !matches!(stmt, Stmt::Item(item) if !self.retain_item(item)));
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+}
+
+fn delete_unused_traits(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut find = FindTraits::default();
+    find.visit_file_mut(krate);
+    let mut uses = FindUses::default();
+    uses.visit_file_mut(krate);
+
+    // Sorted, so that the candidates are asked for in the same order every time.
+    let mut unused = find
+        .traits
+        .into_iter()
+        .filter(|(name, items)| {
+            !uses.names.contains(name) && !items.iter().any(|item| uses.names.contains(item))
+        })
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    unused.sort();
+
+    let deleted = unused
+        .into_iter()
+        .filter(|name| checker.can_process(std::slice::from_ref(name)))
+        .collect::<HashSet<_>>();
+    if deleted.is_empty() {
+        return ProcessState::NoChange;
+    }
+
+    DeleteTraits { deleted: &deleted }.visit_file_mut(krate);
+    ProcessState::Changed
+}
+
+#[derive(Default)]
+pub struct TraitDeleter;
+
+impl Pass for TraitDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        delete_unused_traits(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-unused-traits"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::delete_unused_traits;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn delete(src: &str) -> String {
+        apply_all(src, delete_unused_traits)
+    }
+
+    #[test]
+    fn deletes_unused_trait_with_all_impls() {
+        assert_eq!(
+            delete(
+                "trait Unused { fn f(&self); } impl Unused for u8 { fn f(&self) {} } \
+                 mod m { impl super::Unused for u16 { fn f(&self) {} } } \
+                 trait Called { fn g(&self); } impl Called for u8 { fn g(&self) {} } \
+                 trait Bound {} impl Bound for u8 {} fn h<T: Bound>(_: T) {} \
+                 fn main() { 1u8.g(); }"
+            ),
+            tokens(
+                "mod m {} \
+                 trait Called { fn g(&self); } impl Called for u8 { fn g(&self) {} } \
+                 trait Bound {} impl Bound for u8 {} fn h<T: Bound>(_: T) {} \
+                 fn main() { 1u8.g(); }"
+            )
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Deletes methods from traits together with their implementations, so that the impls keep compiling.
+//! Only impls in the same file as the trait are updated, impls in other files are left to the build to complain about.
+
+use std::collections::HashSet;
+
+use syn::{visit_mut::VisitMut, ImplItem, ItemImpl, ItemTrait, TraitItem};
+
+use crate::processor::{Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    /// The trait and method names of the deleted methods.
+    deleted: HashSet<(String, String)>,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            checker,
+            process_state: ProcessState::NoChange,
+            deleted: HashSet::new(),
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_trait_mut(&mut self, trait_: &mut ItemTrait) {
+        let trait_name = trait_.ident.to_string();
+        trait_.items.retain(|item| {
+            let TraitItem::Method(method) = item else {
+                return true;
+            };
+            let method_name = method.sig.ident.to_string();
+            // The candidates are not nested in modules, the impls may be anywhere in the file.
+            if self
+                .checker
+                .can_process(&[trait_name.clone(), method_name.clone()])
+            {
+                self.process_state = ProcessState::Changed;
+                self.deleted.insert((trait_name.clone(), method_name));
+                false
+            } else {
+                true
+            }
+        });
+
+        syn::visit_mut::visit_item_trait_mut(self, trait_);
+    }
+}
+
+/// Removes the methods from impls of the traits they were deleted from.
+struct DeleteImplMethods<'a> {
+    deleted: &'a HashSet<(String, String)>,
+}
+
+impl VisitMut for DeleteImplMethods<'_> {
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        if let Some((_, path, _)) = &impl_.trait_ {
+            let trait_name = path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default();
+            impl_.items.retain(|item| match item {
+                ImplItem::Method(method) => !self
+                    .deleted
+                    .contains(&(trait_name.clone(), method.sig.ident.to_string())),
+                _ => true,
+            });
+        }
+
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+    }
+}
+
+#[derive(Default)]
+pub struct TraitMethodDeleter;
+
+impl Pass for TraitMethodDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+
+        if !visitor.deleted.is_empty() {
+            DeleteImplMethods {
+                deleted: &visitor.deleted,
+            }
+            .visit_file_mut(krate);
+        }
+
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-trait-methods"
+    }
+}
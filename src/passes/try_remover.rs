@@ -0,0 +1,234 @@
+//! Simplifies error handling. Every `?` is a candidate for being replaced with `.unwrap()`. Once a function
+//! doesn't use `?` anymore and ends in `Ok(...)`, returning a `Result` is a candidate for being replaced with
+//! returning the value directly, so `fn f() -> Result<u32, E>` becomes `fn f() -> u32`. If a caller or an
+//! `Err` return still needs the `Result`, the build fails and the change won't be kept.
+//! Methods of trait impls are skipped, as their signature has to match the trait.
+
+use quote::ToTokens;
+use syn::{
+    parse_quote, visit_mut::VisitMut, Block, Expr, ExprCall, GenericArgument, ImplItemMethod,
+    ItemFn, ItemImpl, PathArguments, ReturnType, Signature, Stmt, Type,
+};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    in_trait_impl: bool,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            in_trait_impl: false,
+        }
+    }
+
+    fn can_process(&mut self, candidate: String) -> bool {
+        self.current_path.push(candidate);
+        let can_process = self.checker.can_process(&self.current_path);
+        self.current_path.pop();
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        can_process
+    }
+
+    /// Makes the function return the value instead of a `Result` with it.
+    fn simplify_result(&mut self, sig: &mut Signature, block: &mut Block) {
+        let Some(ok_ty) = result_ok_type(&sig.output) else {
+            return;
+        };
+        let mut has_try = HasTry(false);
+        has_try.visit_block_mut(block);
+        let ends_in_ok =
+            matches!(block.stmts.last(), Some(Stmt::Expr(expr)) if ok_value(expr).is_some());
+        if has_try.0 || !ends_in_ok || !self.can_process("-> Result".to_owned()) {
+            return;
+        }
+
+        let unit = matches!(&ok_ty, Type::Tuple(tuple) if tuple.elems.is_empty());
+        sig.output = match unit {
+            true => ReturnType::Default,
+            false => parse_quote!(-> #ok_ty),
+        };
+        UnwrapOkReturns.visit_block_mut(block);
+        if let Some(Stmt::Expr(tail)) = block.stmts.pop() {
+            let value = ok_value(&tail).expect("checked above").clone();
+            match (unit, value) {
+                (true, Expr::Tuple(tuple)) if tuple.elems.is_empty() => {}
+                (true, value) => block.stmts.push(Stmt::Semi(value, Default::default())),
+                (false, value) => block.stmts.push(Stmt::Expr(value)),
+            }
+        }
+    }
+}
+
+/// The `T` of a `Result<T, E>` return type.
+fn result_ok_type(output: &ReturnType) -> Option<Type> {
+    let ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    let Type::Path(path) = &**ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty.clone()),
+        _ => None,
+    }
+}
+
+/// The `x` of `Ok(x)`.
+fn ok_value(expr: &Expr) -> Option<&Expr> {
+    match expr {
+        Expr::Call(ExprCall { func, args, .. }) if args.len() == 1 => match &**func {
+            Expr::Path(path) if path.path.is_ident("Ok") => args.first(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether the function body uses `?`. Closures and nested items have their own return type.
+struct HasTry(bool);
+
+impl VisitMut for HasTry {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Try(_) => self.0 = true,
+            Expr::Closure(_) | Expr::Async(_) => {}
+            _ => syn::visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _: &mut syn::Item) {}
+}
+
+/// Replaces `return Ok(x)` with `return x`.
+struct UnwrapOkReturns;
+
+impl VisitMut for UnwrapOkReturns {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Return(return_) => {
+                if let Some(value) = return_.expr.as_deref().and_then(ok_value) {
+                    return_.expr = match value {
+                        Expr::Tuple(tuple) if tuple.elems.is_empty() => None,
+                        value => Some(Box::new(value.clone())),
+                    };
+                }
+            }
+            Expr::Closure(_) | Expr::Async(_) => {}
+            _ => syn::visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _: &mut syn::Item) {}
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        syn::visit_mut::visit_expr_mut(self, expr);
+
+        if let Expr::Try(try_) = expr {
+            let inner = &try_.expr;
+            if self.can_process(format!("{}?", inner.to_token_stream())) {
+                *expr = parse_quote!(#inner.unwrap());
+            }
+        }
+    }
+
+    fn visit_item_fn_mut(&mut self, func: &mut ItemFn) {
+        self.current_path.push(func.sig.ident.to_string());
+        syn::visit_mut::visit_item_fn_mut(self, func);
+        self.simplify_result(&mut func.sig, &mut func.block);
+        self.current_path.pop();
+    }
+
+    fn visit_impl_item_method_mut(&mut self, method: &mut ImplItemMethod) {
+        self.current_path.push(method.sig.ident.to_string());
+        syn::visit_mut::visit_impl_item_method_mut(self, method);
+        if !self.in_trait_impl {
+            self.simplify_result(&mut method.sig, &mut method.block);
+        }
+        self.current_path.pop();
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        self.current_path
+            .push(impl_.self_ty.clone().into_token_stream().to_string());
+        let in_trait_impl = std::mem::replace(&mut self.in_trait_impl, impl_.trait_.is_some());
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+        self.in_trait_impl = in_trait_impl;
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_mod_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+fn remove_try(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct TryRemover;
+
+impl Pass for TryRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_try(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-try"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_try;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_try)
+    }
+
+    #[test]
+    fn try_becomes_unwrap() {
+        assert_eq!(
+            remove(
+                "fn parse(s: &str) -> Result<u32, std::num::ParseIntError> { \
+                     if s.is_empty() { return Ok(0); } let n = s.parse::<u32>()?; Ok(n) } \
+                 fn main() -> Result<(), Box<dyn std::error::Error>> { parse(\"1\")?; Ok(()) } \
+                 impl std::str::FromStr for S { type Err = (); \
+                     fn from_str(s: &str) -> Result<Self, ()> { Ok(S) } }"
+            ),
+            tokens(
+                "fn parse(s: &str) -> u32 { \
+                     if s.is_empty() { return 0; } let n = s.parse::<u32>().unwrap(); n } \
+                 fn main() { parse(\"1\").unwrap(); } \
+                 impl std::str::FromStr for S { type Err = (); \
+                     fn from_str(s: &str) -> Result<Self, ()> { Ok(S) } }"
+            )
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Replaces the uses of type aliases with the aliased type and deletes the alias.
+//! Aliases with generic parameters are skipped, and so are aliases whose name is used for more than one alias
+//! in the file, as we can't tell which one a use refers to.
+
+use std::collections::HashMap;
+
+use quote::{quote, ToTokens};
+use syn::{visit_mut::VisitMut, Item, ItemType, Type, TypePath};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+/// Counts how often every alias name is defined in the file.
+#[derive(Default)]
+struct CountAliases {
+    counts: HashMap<String, usize>,
+}
+
+impl VisitMut for CountAliases {
+    fn visit_item_type_mut(&mut self, alias: &mut ItemType) {
+        *self.counts.entry(alias.ident.to_string()).or_default() += 1;
+    }
+}
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+    counts: HashMap<String, usize>,
+    /// The aliases that were deleted, with the type that their uses get replaced with.
+    inlined: Vec<(String, Type)>,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController, counts: HashMap<String, usize>) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+            counts,
+            inlined: Vec::new(),
+        }
+    }
+
+    fn inline_aliases(&mut self, items: &mut Vec<Item>) {
+        items.retain(|item| {
+            let Item::Type(alias) = item else {
+                return true;
+            };
+            let name = alias.ident.to_string();
+            if !alias.generics.params.is_empty() || self.counts.get(&name) != Some(&1) {
+                return true;
+            }
+
+            self.current_path.push(name.clone());
+            let can_process = self.checker.can_process(&self.current_path);
+            self.current_path.pop();
+            if !can_process {
+                return true;
+            }
+
+            self.process_state = ProcessState::Changed;
+            self.inlined.push((name, (*alias.ty).clone()));
+            false
+        });
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.inline_aliases(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+        if let Some((_, items)) = &mut module.content {
+            self.inline_aliases(items);
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+/// Replaces the uses of an alias with the aliased type.
+struct ReplaceAlias<'a> {
+    name: &'a str,
+    ty: &'a Type,
+}
+
+impl ReplaceAlias<'_> {
+    /// `Foo::new` becomes `<Vec<u8>>::new`.
+    fn qualified_path<T: syn::parse::Parse>(&self, path: &syn::Path) -> Option<T> {
+        if path.leading_colon.is_some()
+            || path.segments.len() < 2
+            || path.segments[0].ident != self.name
+            || !path.segments[0].arguments.is_empty()
+        {
+            return None;
+        }
+        let ty = self.ty;
+        let rest = path.segments.iter().skip(1);
+        syn::parse2(quote! { <#ty>#(::#rest)* }).ok()
+    }
+}
+
+impl VisitMut for ReplaceAlias<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(TypePath { qself: None, path }) = ty {
+            if path.is_ident(self.name) {
+                *ty = self.ty.clone();
+                return;
+            }
+            if let Some(qualified) = self.qualified_path(path) {
+                *ty = Type::Path(qualified);
+                return;
+            }
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_expr_path_mut(&mut self, expr: &mut syn::ExprPath) {
+        if expr.qself.is_none() {
+            if let Some(mut qualified) = self.qualified_path::<syn::ExprPath>(&expr.path) {
+                qualified.attrs = std::mem::take(&mut expr.attrs);
+                *expr = qualified;
+                return;
+            }
+        }
+        syn::visit_mut::visit_expr_path_mut(self, expr);
+    }
+}
+
+fn inline_type_aliases(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut count_aliases = CountAliases::default();
+    count_aliases.visit_file_mut(krate);
+
+    let mut visitor = Visitor::new(checker, count_aliases.counts);
+    visitor.visit_file_mut(krate);
+
+    // One after another, so that aliases that refer to other aliases are resolved completely.
+    for (name, ty) in &visitor.inlined {
+        ReplaceAlias { name, ty }.visit_file_mut(krate);
+    }
+
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct TypeAliasInliner;
+
+impl Pass for TypeAliasInliner {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        inline_type_aliases(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "inline-type-aliases"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inline_type_aliases;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn inline(src: &str) -> String {
+        apply_all(src, inline_type_aliases)
+    }
+
+    #[test]
+    fn inlines_alias() {
+        assert_eq!(
+            inline(
+                "type Foo = Vec<u8>;
+                 fn f(foo: Foo) -> Foo { let _ = Foo::new(); foo }"
+            ),
+            tokens("fn f(foo: Vec<u8>) -> Vec<u8> { let _ = <Vec<u8>>::new(); foo }")
+        );
+    }
+
+    #[test]
+    fn nested_aliases() {
+        assert_eq!(
+            inline(
+                "type Bytes = Vec<Byte>;
+                 type Byte = u8;
+                 fn f(bytes: Bytes) {}"
+            ),
+            tokens("fn f(bytes: Vec<u8>) {}")
+        );
+    }
+
+    #[test]
+    fn skips_generic_aliases() {
+        let src = "type Foo<T> = Vec<T>; fn f(foo: Foo<u8>) {}";
+        assert_eq!(inline(src), tokens(src));
+    }
+}
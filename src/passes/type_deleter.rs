@@ -0,0 +1,156 @@
+//! Deletes struct, enum and union definitions, together with the impls for them in the same scope.
+//! Types that are still used are kept, as the build fails without them.
+
+use std::collections::HashSet;
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Item, ItemEnum, ItemImpl, ItemStruct, ItemUnion, Stmt, Type};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    /// Returns the names of the types in the items that may be deleted.
+    fn deleted_types<'i>(&mut self, items: impl Iterator<Item = &'i Item>) -> HashSet<String> {
+        let mut deleted = HashSet::new();
+        for item in items {
+            let Some(name) = type_name(item) else {
+                continue;
+            };
+
+            self.current_path.push(name.clone());
+            let can_process = self.checker.can_process(&self.current_path);
+            self.current_path.pop();
+            if can_process {
+                self.process_state = ProcessState::Changed;
+                deleted.insert(name);
+            }
+        }
+        deleted
+    }
+}
+
+fn type_name(item: &Item) -> Option<String> {
+    match item {
+        Item::Struct(ItemStruct { ident, .. })
+        | Item::Enum(ItemEnum { ident, .. })
+        | Item::Union(ItemUnion { ident, .. }) => Some(ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether the item is a deleted type or an impl for one.
+fn is_deleted(item: &Item, deleted: &HashSet<String>) -> bool {
+    match item {
+        Item::Impl(ItemImpl { self_ty, .. }) => match &**self_ty {
+            Type::Path(path) if path.qself.is_none() => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| deleted.contains(&ident.to_string())),
+            _ => false,
+        },
+        _ => type_name(item).is_some_and(|name| deleted.contains(&name)),
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        let deleted = self.deleted_types(file.items.iter());
+        file.items.retain(|item| !is_deleted(item, &deleted));
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.current_path.push(module.ident.to_string());
+        if let Some((_, items)) = &mut module.content {
+            let deleted = self.deleted_types(items.iter());
+            items.retain(|item| !is_deleted(item, &deleted));
+        }
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.current_path.pop();
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        let deleted = self.deleted_types(block.stmts.iter().filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item),
+            _ => None,
+        }));
+        block
+            .stmts
+            .retain(|stmt| !matches!(stmt, Stmt::Item(item) if is_deleted(item, &deleted)));
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+
+    tracking!(visit_item_fn_mut);
+    tracking!(visit_impl_item_method_mut);
+    tracking!(visit_item_impl_mut);
+    tracking!(visit_item_trait_mut);
+}
+
+#[derive(Default)]
+pub struct TypeDeleter;
+
+impl Pass for TypeDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-types"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{minimize_sources, Options, Sources};
+
+    #[test]
+    fn keeps_used_struct() {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([(
+            main.clone(),
+            "struct Used;\n\
+             struct Unused {\n    a: u8,\n}\n\
+             impl Unused {\n    fn f() {}\n}\n\
+             enum UnusedEnum {}\n\n\
+             fn main() {\n    let _ = Used;\n}\n"
+                .to_owned(),
+        )]);
+        let options = Options {
+            passes: Some("delete-types".to_owned()),
+            ..Options::default()
+        };
+
+        // Removing a type that is still used breaks the build.
+        let minimized = minimize_sources(&sources, options, |sources| {
+            sources[&PathBuf::from("src/main.rs")].contains("struct Used;")
+        })
+        .unwrap();
+
+        let content = &minimized[&main];
+        assert!(content.contains("struct Used;"), "{content}");
+        assert!(!content.contains("Unused"), "{content}");
+    }
+}
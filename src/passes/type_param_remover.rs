@@ -0,0 +1,295 @@
+//! Removes type parameters of structs, enums and unions that none of the fields use, like the `T` of
+//! `struct Foo<T>(u32)`. The generic arguments for the parameter are removed from all paths to the type in
+//! the file, and impls drop their own parameter that was only passed to the removed one. Parameters that
+//! are only used in a `PhantomData` are kept, removing them changes the fields.
+
+use std::collections::{HashMap, HashSet};
+
+use syn::{
+    punctuated::Punctuated, visit_mut::VisitMut, Fields, GenericArgument, GenericParam, Generics,
+    ItemEnum, ItemImpl, ItemStruct, ItemUnion, Path, PathArguments, Type, WherePredicate,
+};
+
+use crate::processor::{Pass, PassController, ProcessState, SourceFile};
+
+/// Whether a path or type mentions the type parameter `name`, like `T` or `T::Assoc`.
+struct Mentions<'a> {
+    name: &'a str,
+    found: bool,
+}
+
+impl VisitMut for Mentions<'_> {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        if path.leading_colon.is_none()
+            && path.segments.first().is_some_and(|s| s.ident == self.name)
+        {
+            self.found = true;
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+}
+
+fn mentions(name: &str, visit: impl FnOnce(&mut Mentions<'_>)) -> bool {
+    let mut mentions = Mentions { name, found: false };
+    visit(&mut mentions);
+    mentions.found
+}
+
+/// The type parameters of each type definition that no field uses. A parameter comes with its position among
+/// the parameters that aren't lifetimes, as the generic arguments for it are found by that.
+type UnusedParams = HashMap<String, Vec<(String, usize)>>;
+
+#[derive(Default)]
+struct FindUnused {
+    types: UnusedParams,
+    /// Types with the same name in different modules can't be told apart, so they are skipped.
+    seen: HashSet<String>,
+}
+
+impl FindUnused {
+    fn add(&mut self, name: String, generics: &Generics, fields: &mut [&mut Type]) {
+        let positions = generics
+            .params
+            .iter()
+            .filter(|param| !matches!(param, GenericParam::Lifetime(_)))
+            .enumerate()
+            .filter_map(|(position, param)| match param {
+                GenericParam::Type(param) => Some((param.ident.to_string(), position)),
+                _ => None,
+            })
+            .filter(|(param, _)| {
+                !fields
+                    .iter_mut()
+                    .any(|field| mentions(param, |mentions| mentions.visit_type_mut(field)))
+            })
+            .collect::<Vec<_>>();
+
+        if !self.seen.insert(name.clone()) {
+            self.types.remove(&name);
+        } else if !positions.is_empty() {
+            self.types.insert(name, positions);
+        }
+    }
+}
+
+fn field_types(fields: &mut Fields) -> Vec<&mut Type> {
+    fields.iter_mut().map(|field| &mut field.ty).collect()
+}
+
+impl VisitMut for FindUnused {
+    fn visit_item_struct_mut(&mut self, struct_: &mut ItemStruct) {
+        let mut fields = field_types(&mut struct_.fields);
+        self.add(struct_.ident.to_string(), &struct_.generics, &mut fields);
+        syn::visit_mut::visit_item_struct_mut(self, struct_);
+    }
+
+    fn visit_item_enum_mut(&mut self, enum_: &mut ItemEnum) {
+        let mut fields = enum_
+            .variants
+            .iter_mut()
+            .flat_map(|variant| field_types(&mut variant.fields))
+            .collect::<Vec<_>>();
+        self.add(enum_.ident.to_string(), &enum_.generics, &mut fields);
+        syn::visit_mut::visit_item_enum_mut(self, enum_);
+    }
+
+    fn visit_item_union_mut(&mut self, union_: &mut ItemUnion) {
+        let mut fields = union_
+            .fields
+            .named
+            .iter_mut()
+            .map(|field| &mut field.ty)
+            .collect::<Vec<_>>();
+        self.add(union_.ident.to_string(), &union_.generics, &mut fields);
+        syn::visit_mut::visit_item_union_mut(self, union_);
+    }
+}
+
+/// Removes the parameters from the generics, together with the where clauses that bound them.
+fn remove_params(generics: &mut Generics, removed: &[String]) {
+    generics.params = std::mem::take(&mut generics.params)
+        .into_iter()
+        .filter(|param| match param {
+            GenericParam::Type(param) => !removed.iter().any(|name| param.ident == name),
+            _ => true,
+        })
+        .collect();
+    if generics.params.is_empty() {
+        generics.lt_token = None;
+        generics.gt_token = None;
+    }
+
+    if let Some(where_clause) = &mut generics.where_clause {
+        where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+            .into_iter()
+            .filter(|predicate| match predicate {
+                WherePredicate::Type(predicate) => !removed.iter().any(|name| {
+                    mentions(name, |mentions| {
+                        mentions.visit_type_mut(&mut predicate.bounded_ty.clone())
+                    })
+                }),
+                _ => true,
+            })
+            .collect();
+    }
+}
+
+/// Removes the parameters from the definitions and the arguments for them from the paths to the types.
+struct RemoveParams<'a> {
+    removed: &'a UnusedParams,
+}
+
+impl RemoveParams<'_> {
+    fn remove_from_definition(&self, name: &syn::Ident, generics: &mut Generics) {
+        if let Some(params) = self.removed.get(&name.to_string()) {
+            let names = params
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            remove_params(generics, &names);
+        }
+    }
+}
+
+impl VisitMut for RemoveParams<'_> {
+    fn visit_item_struct_mut(&mut self, struct_: &mut ItemStruct) {
+        self.remove_from_definition(&struct_.ident, &mut struct_.generics);
+        syn::visit_mut::visit_item_struct_mut(self, struct_);
+    }
+
+    fn visit_item_enum_mut(&mut self, enum_: &mut ItemEnum) {
+        self.remove_from_definition(&enum_.ident, &mut enum_.generics);
+        syn::visit_mut::visit_item_enum_mut(self, enum_);
+    }
+
+    fn visit_item_union_mut(&mut self, union_: &mut ItemUnion) {
+        self.remove_from_definition(&union_.ident, &mut union_.generics);
+        syn::visit_mut::visit_item_union_mut(self, union_);
+    }
+
+    fn visit_item_impl_mut(&mut self, impl_: &mut ItemImpl) {
+        // `impl<T> Foo<T>` has to lose its `T` too when `Foo` loses the argument, or `T` is unconstrained.
+        let params = impl_
+            .generics
+            .type_params()
+            .map(|param| param.ident.to_string())
+            .collect::<Vec<_>>();
+        let constrains = |impl_: &mut ItemImpl, param: &str| {
+            mentions(param, |mentions| {
+                mentions.visit_type_mut(&mut impl_.self_ty);
+                if let Some((_, trait_, _)) = &mut impl_.trait_ {
+                    mentions.visit_path_mut(trait_);
+                }
+            })
+        };
+        let constrained = params
+            .into_iter()
+            .filter(|param| constrains(impl_, param))
+            .collect::<Vec<_>>();
+
+        syn::visit_mut::visit_item_impl_mut(self, impl_);
+
+        let unconstrained = constrained
+            .into_iter()
+            .filter(|param| !constrains(impl_, param))
+            .collect::<Vec<_>>();
+        remove_params(&mut impl_.generics, &unconstrained);
+    }
+
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        for segment in &mut path.segments {
+            let Some(params) = self.removed.get(&segment.ident.to_string()) else {
+                continue;
+            };
+            let PathArguments::AngleBracketed(args) = &mut segment.arguments else {
+                continue;
+            };
+            let mut position = 0;
+            args.args = std::mem::take(&mut args.args)
+                .into_iter()
+                .filter(|arg| {
+                    if matches!(arg, GenericArgument::Lifetime(_)) {
+                        return true;
+                    }
+                    let removed = params.iter().any(|&(_, removed)| removed == position);
+                    position += 1;
+                    !removed
+                })
+                .collect::<Punctuated<_, _>>();
+            if args.args.is_empty() {
+                segment.arguments = PathArguments::None;
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+}
+
+fn remove_type_params(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut find = FindUnused::default();
+    find.visit_file_mut(krate);
+
+    // Sorted, so that the candidates are asked for in the same order every time.
+    let mut types = find.types.into_iter().collect::<Vec<_>>();
+    types.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let removed = types
+        .into_iter()
+        .filter_map(|(name, unused)| {
+            let params = unused
+                .into_iter()
+                .filter(|(param, _)| checker.can_process(&[name.clone(), param.clone()]))
+                .collect::<Vec<_>>();
+            (!params.is_empty()).then_some((name, params))
+        })
+        .collect::<UnusedParams>();
+    if removed.is_empty() {
+        return ProcessState::NoChange;
+    }
+
+    RemoveParams { removed: &removed }.visit_file_mut(krate);
+    ProcessState::Changed
+}
+
+#[derive(Default)]
+pub struct TypeParamRemover;
+
+impl Pass for TypeParamRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_type_params(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-type-params"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_type_params;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_type_params)
+    }
+
+    #[test]
+    fn removes_unused_type_param() {
+        assert_eq!(
+            remove(
+                "struct Foo<T>(u32) where T: Clone; struct Used<'a, T>(&'a T); \
+                 impl<T> Foo<T> { fn new() -> Self { Foo::<T>(0) } } \
+                 fn f(foo: Foo<u8>, used: Used<'_, Foo<String>>) {}"
+            ),
+            tokens(
+                "struct Foo(u32); struct Used<'a, T>(&'a T); \
+                 impl Foo { fn new() -> Self { Foo(0) } } \
+                 fn f(foo: Foo, used: Used<'_, Foo>) {}"
+            )
+        );
+    }
+}
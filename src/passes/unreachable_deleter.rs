@@ -0,0 +1,132 @@
+//! Deletes the statements after a diverging expression, like a `return`, a `loop` without a `break` or a `panic!()`.
+//! They can't run, but `delete-statements` only deletes them one by one, and `everybody-loops` and
+//! `collapse-branches` leave a lot of them behind. All statements after the first diverging statement of a block
+//! are one candidate. Items are kept, as they can be used from anywhere in the block.
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Block, Expr, Item, Stmt};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+}
+
+fn is_diverging_macro(mac: &syn::Macro) -> bool {
+    mac.path.segments.last().is_some_and(|segment| {
+        ["panic", "unreachable", "todo", "unimplemented"].contains(&&*segment.ident.to_string())
+    })
+}
+
+fn contains_break(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => ident == "break",
+        TokenTree::Group(group) => contains_break(group.stream()),
+        _ => false,
+    })
+}
+
+/// Whether the statement never finishes. A `loop` with a `break` anywhere in it, even one that belongs to a
+/// nested loop, counts as finishing.
+fn diverges(stmt: &Stmt) -> bool {
+    let expr = match stmt {
+        Stmt::Expr(expr) | Stmt::Semi(expr, _) => expr,
+        Stmt::Item(Item::Macro(mac)) => return mac.ident.is_none() && is_diverging_macro(&mac.mac),
+        _ => return false,
+    };
+    match expr {
+        Expr::Return(_) | Expr::Break(_) | Expr::Continue(_) => true,
+        Expr::Loop(loop_) => !contains_break(loop_.body.to_token_stream()),
+        Expr::Macro(mac) => is_diverging_macro(&mac.mac),
+        _ => false,
+    }
+}
+
+/// Macro statements are parsed as items, but they run like the other statements.
+fn is_item(stmt: &Stmt) -> bool {
+    matches!(stmt, Stmt::Item(item) if !matches!(item, Item::Macro(mac) if mac.ident.is_none()))
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        if let Some(index) = block.stmts.iter().position(diverges) {
+            let unreachable = block.stmts[index + 1..].iter().any(|stmt| !is_item(stmt));
+            if unreachable {
+                self.current_path
+                    .push(format!("after {}", block.stmts[index].to_token_stream()));
+                let can_process = self.checker.can_process(&self.current_path);
+                self.current_path.pop();
+                if can_process {
+                    self.process_state = ProcessState::Changed;
+                    let after = block.stmts.split_off(index + 1);
+                    block.stmts.extend(after.into_iter().filter(is_item));
+                }
+            }
+        }
+
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+
+    tracking!();
+}
+
+fn delete_unreachable(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct UnreachableDeleter;
+
+impl Pass for UnreachableDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        delete_unreachable(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-unreachable"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::delete_unreachable;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn delete(src: &str) -> String {
+        apply_all(src, delete_unreachable)
+    }
+
+    #[test]
+    fn deletes_statements_after_return() {
+        assert_eq!(
+            delete(
+                "fn f() -> u8 { let a = 1; return a; let b = 2; fn inner() {} b } \
+                 fn g() { loop { break; } h(); panic!(); h(); }"
+            ),
+            tokens(
+                "fn f() -> u8 { let a = 1; return a; fn inner() {} } \
+                 fn g() { loop { break; } h(); panic!(); }"
+            )
+        );
+    }
+}
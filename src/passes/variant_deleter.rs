@@ -0,0 +1,68 @@
+use quote::ToTokens;
+use syn::visit_mut::VisitMut;
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+    process_state: ProcessState,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            current_path: Vec::new(),
+            checker,
+            process_state: ProcessState::NoChange,
+        }
+    }
+
+    fn consider_deleting_variant(&mut self, name: String) -> bool {
+        self.current_path.push(name);
+        let can_process = self.checker.can_process(&self.current_path);
+        if can_process {
+            self.process_state = ProcessState::Changed;
+        }
+        self.current_path.pop();
+        !can_process
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_enum_mut(&mut self, enum_: &mut syn::ItemEnum) {
+        self.current_path.push(enum_.ident.to_string());
+
+        enum_.variants = enum_
+            .variants
+            .clone()
+            .into_pairs()
+            .filter(|pair| self.consider_deleting_variant(pair.value().ident.to_string()))
+            .collect();
+
+        syn::visit_mut::visit_item_enum_mut(self, enum_);
+        self.current_path.pop();
+    }
+
+    tracking!();
+}
+
+#[derive(Default)]
+pub struct VariantDeleter;
+
+impl Pass for VariantDeleter {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let mut visitor = Visitor::new(checker);
+        visitor.visit_file_mut(krate);
+        visitor.process_state
+    }
+
+    fn name(&self) -> &'static str {
+        "delete-variants"
+    }
+}
@@ -0,0 +1,80 @@
+//! Makes items private that `privatize` made `pub(crate)`, together with other restricted visibilities like
+//! `pub(super)`. Items that are used from other modules are kept visible, as the build fails without that.
+
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Visibility};
+
+use crate::processor::{tracking, Pass, PassController, ProcessState, SourceFile};
+
+struct Visitor<'a> {
+    process_state: ProcessState,
+    current_path: Vec<String>,
+    checker: &'a mut PassController,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(checker: &'a mut PassController) -> Self {
+        Self {
+            process_state: ProcessState::NoChange,
+            current_path: Vec::new(),
+            checker,
+        }
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_visibility_mut(&mut self, vis: &mut Visibility) {
+        if let Visibility::Restricted(_) | Visibility::Crate(_) = vis {
+            if self.checker.can_process(&self.current_path) {
+                self.process_state = ProcessState::Changed;
+                *vis = Visibility::Inherited;
+            }
+        }
+    }
+
+    tracking!();
+}
+
+fn remove_visibility(krate: &mut syn::File, checker: &mut PassController) -> ProcessState {
+    let mut visitor = Visitor::new(checker);
+    visitor.visit_file_mut(krate);
+    visitor.process_state
+}
+
+#[derive(Default)]
+pub struct VisibilityRemover;
+
+impl Pass for VisibilityRemover {
+    fn process_file(
+        &mut self,
+        krate: &mut syn::File,
+        _: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        remove_visibility(krate, checker)
+    }
+
+    fn name(&self) -> &'static str {
+        "remove-visibility"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::remove_visibility;
+    use crate::passes::tests::{apply_all, tokens};
+
+    fn remove(src: &str) -> String {
+        apply_all(src, remove_visibility)
+    }
+
+    #[test]
+    fn pub_crate_becomes_private() {
+        assert_eq!(
+            remove(
+                "pub(crate) fn f() {} pub(crate) struct S { pub(super) a: u8 } pub fn main() {}"
+            ),
+            tokens("fn f() {} struct S { a: u8 } pub fn main() {}")
+        );
+    }
+}
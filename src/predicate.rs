@@ -0,0 +1,366 @@
+//! The predicates of `--reproduces`, which combine several conditions on the output of a build, like
+//! `exit == 101 && stderr matches "panicked at"`.
+//!
+//! The grammar, from the loosest to the tightest binding:
+//! - `a || b` or `a OR b`
+//! - `a && b` or `a AND b`
+//! - `!a` or `NOT a`
+//! - `(a)`, `ice`, `exit <op> <code>` with one of `==`, `!=`, `<`, `<=`, `>`, `>=`, and
+//!   `stderr matches "<regex>"`, `stdout matches "<regex>"` or `output matches "<regex>"` for either of them.
+
+use std::{fmt::Display, str::FromStr};
+
+use regex::Regex;
+
+/// What a predicate is evaluated against.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BuildOutput<'a> {
+    /// The exit code, `None` if the build was killed by a signal.
+    pub(crate) exit: Option<i32>,
+    pub(crate) stderr: &'a str,
+    pub(crate) stdout: &'a str,
+    /// Whether the build reproduces the issue without a predicate.
+    pub(crate) ice: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Stderr,
+    Stdout,
+    Output,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Ice,
+    Exit(Comparison, i32),
+    Matches(Stream, Regex),
+}
+
+impl Expr {
+    fn eval(&self, output: &BuildOutput<'_>) -> bool {
+        match self {
+            Self::Or(a, b) => a.eval(output) || b.eval(output),
+            Self::And(a, b) => a.eval(output) && b.eval(output),
+            Self::Not(a) => !a.eval(output),
+            Self::Ice => output.ice,
+            // A build that was killed has no exit code, so no comparison holds.
+            Self::Exit(comparison, expected) => output.exit.is_some_and(|code| match comparison {
+                Comparison::Eq => code == *expected,
+                Comparison::Ne => code != *expected,
+                Comparison::Lt => code < *expected,
+                Comparison::Le => code <= *expected,
+                Comparison::Gt => code > *expected,
+                Comparison::Ge => code >= *expected,
+            }),
+            Self::Matches(stream, regex) => match stream {
+                Stream::Stderr => regex.is_match(output.stderr),
+                Stream::Stdout => regex.is_match(output.stdout),
+                Stream::Output => regex.is_match(output.stderr) || regex.is_match(output.stdout),
+            },
+        }
+    }
+}
+
+/// A condition on the output of a build that has to hold for the issue to reproduce.
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    source: String,
+    expr: Expr,
+}
+
+impl Predicate {
+    pub(crate) fn eval(&self, output: &BuildOutput<'_>) -> bool {
+        self.expr.eval(output)
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!(
+                "unexpected `{token}` after the end of the predicate"
+            ));
+        }
+        Ok(Self {
+            source: s.to_owned(),
+            expr,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Int(i32),
+    Op(&'static str),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(word) => f.write_str(word),
+            Self::Str(string) => write!(f, "{string:?}"),
+            Self::Int(int) => write!(f, "{int}"),
+            Self::Op(op) => f.write_str(op),
+        }
+    }
+}
+
+/// Longer operators first, so that `<=` isn't read as `<`.
+const OPERATORS: &[&str] = &["&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")"];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else if c == '"' {
+            // Only `\"` and `\\` are escapes, other backslashes belong to the regex.
+            let mut string = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped @ ('"' | '\\'))) => string.push(escaped),
+                        Some((_, other)) => {
+                            string.push('\\');
+                            string.push(other);
+                        }
+                        None => return Err("unterminated string".to_owned()),
+                    },
+                    Some((_, other)) => string.push(other),
+                    None => return Err("unterminated string".to_owned()),
+                }
+            };
+            tokens.push(Token::Str(string));
+            rest = &rest[end..];
+        } else if c == '-' || c.is_ascii_digit() {
+            let end = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(rest.len(), |i| i + 1);
+            let int = rest[..end]
+                .parse()
+                .map_err(|err| format!("invalid exit code `{}`: {err}", &rest[..end]))?;
+            tokens.push(Token::Int(int));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() {
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..end].to_owned()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("unexpected character `{c}`"));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or("unexpected end of the predicate")?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is one of `alternatives`.
+    fn eat(&mut self, alternatives: &[Token]) -> bool {
+        let matches = self
+            .tokens
+            .get(self.pos)
+            .is_some_and(|token| alternatives.contains(token));
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat(&[Token::Op("||"), Token::Word("OR".to_owned())]) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat(&[Token::Op("&&"), Token::Word("AND".to_owned())]) {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat(&[Token::Op("!"), Token::Word("NOT".to_owned())]) {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.next()?;
+        let stream = match &token {
+            Token::Op("(") => {
+                let expr = self.or()?;
+                return match self.next()? {
+                    Token::Op(")") => Ok(expr),
+                    token => Err(format!("expected `)`, found `{token}`")),
+                };
+            }
+            Token::Word(word) if word == "ice" => return Ok(Expr::Ice),
+            Token::Word(word) if word == "exit" => {
+                let comparison = match self.next()? {
+                    Token::Op("==") => Comparison::Eq,
+                    Token::Op("!=") => Comparison::Ne,
+                    Token::Op("<") => Comparison::Lt,
+                    Token::Op("<=") => Comparison::Le,
+                    Token::Op(">") => Comparison::Gt,
+                    Token::Op(">=") => Comparison::Ge,
+                    token => return Err(format!("expected a comparison, found `{token}`")),
+                };
+                return match self.next()? {
+                    Token::Int(code) => Ok(Expr::Exit(comparison, code)),
+                    token => Err(format!("expected an exit code, found `{token}`")),
+                };
+            }
+            Token::Word(word) if word == "stderr" => Stream::Stderr,
+            Token::Word(word) if word == "stdout" => Stream::Stdout,
+            Token::Word(word) if word == "output" => Stream::Output,
+            token => {
+                return Err(format!(
+                    "expected `ice`, `exit`, `stderr`, `stdout`, `output` or `(`, found `{token}`"
+                ))
+            }
+        };
+        match self.next()? {
+            Token::Word(word) if word == "matches" => {}
+            token => return Err(format!("expected `matches`, found `{token}`")),
+        }
+        match self.next()? {
+            Token::Str(regex) => Regex::new(&regex)
+                .map(|regex| Expr::Matches(stream, regex))
+                .map_err(|err| format!("invalid regex: {err}")),
+            token => Err(format!("expected a string, found `{token}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuildOutput, Predicate};
+
+    const ICE: BuildOutput<'static> = BuildOutput {
+        exit: Some(101),
+        stderr: "error: internal compiler error: unexpected type\nthread 'rustc' panicked at",
+        stdout: "",
+        ice: true,
+    };
+
+    const TYPE_ERROR: BuildOutput<'static> = BuildOutput {
+        exit: Some(1),
+        stderr: "error[E0308]: mismatched types",
+        stdout: "test result: FAILED",
+        ice: false,
+    };
+
+    const KILLED: BuildOutput<'static> = BuildOutput {
+        exit: None,
+        stderr: "",
+        stdout: "",
+        ice: false,
+    };
+
+    fn eval(predicate: &str, output: BuildOutput<'_>) -> bool {
+        predicate.parse::<Predicate>().unwrap().eval(&output)
+    }
+
+    #[test]
+    fn combines_conditions() {
+        let predicate = r#"exit == 101 AND stderr matches "internal compiler error""#;
+        assert!(eval(predicate, ICE));
+        assert!(!eval(predicate, TYPE_ERROR));
+
+        let predicate = r#"stderr matches "E0308" && !(exit == 101)"#;
+        assert!(!eval(predicate, ICE));
+        assert!(eval(predicate, TYPE_ERROR));
+
+        let predicate = r#"ice || stdout matches "FAILED""#;
+        assert!(eval(predicate, ICE));
+        assert!(eval(predicate, TYPE_ERROR));
+        assert!(!eval(predicate, KILLED));
+
+        // `&&` binds tighter than `||`.
+        let predicate = "exit == 1 || exit == 101 && exit != 101";
+        assert!(eval(predicate, TYPE_ERROR));
+        assert!(!eval(predicate, ICE));
+
+        assert!(eval("exit >= 1 AND exit < 101", TYPE_ERROR));
+        assert!(!eval("exit >= 1 AND exit < 101", ICE));
+        assert!(eval(r#"output matches "FAILED""#, TYPE_ERROR));
+        assert!(eval(r#"stderr matches "error\[E0308\]""#, TYPE_ERROR));
+        // A killed build has no exit code, so no comparison holds.
+        assert!(!eval("exit != 0", KILLED));
+        assert!(eval(r#"NOT exit == 0 AND NOT stdout matches "\"""#, KILLED));
+    }
+
+    #[test]
+    fn rejects_invalid_predicates() {
+        for predicate in [
+            "",
+            "exit 101",
+            "exit == ice",
+            "stderr \"ICE\"",
+            "stderr matches \"(\"",
+            "(ice",
+            "ice ice",
+            "stderr matches \"unterminated",
+            "exit == 101 &",
+        ] {
+            assert!(
+                predicate.parse::<Predicate>().is_err(),
+                "`{predicate}` should not parse"
+            );
+        }
+    }
+}
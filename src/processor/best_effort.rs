@@ -0,0 +1,138 @@
+//! Mirrors the smallest state that is known to reproduce the issue into the `--best-effort-output`
+//! directory. It is updated after the initial build and after every change that was kept, so even if the
+//! minimization crashes or is killed in the middle of trying a change, the directory holds a usable reduction.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::SourceFile;
+use crate::{build::Build, Options};
+
+#[derive(Debug)]
+pub(crate) struct BestEffortOutput {
+    dir: Option<PathBuf>,
+}
+
+impl BestEffortOutput {
+    pub(crate) fn new(options: &Options) -> Result<Self> {
+        if let Some(dir) = &options.best_effort_output {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("creating best effort output {}", dir.display()))?;
+        }
+        Ok(Self {
+            dir: options.best_effort_output.clone(),
+        })
+    }
+
+    /// Writes the current state of the files to the mirror. Only call this when the state reproduces.
+    pub(crate) fn update(&self, build: &Build, files: &[SourceFile]) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        for file in files {
+            let path = file.path_no_fs_interact();
+            // Files outside of the project directory are put at the top of the mirror.
+            let relative = build
+                .relative(path)
+                .unwrap_or_else(|_| path.file_name().map(PathBuf::from).unwrap_or_default());
+            let mirrored = dir.join(relative);
+            if file.is_deleted() {
+                match std::fs::remove_file(&mirrored) {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(err)
+                            .with_context(|| format!("deleting {}", mirrored.display()));
+                    }
+                    _ => {}
+                }
+            } else {
+                write_atomically(&mirrored, &file.content_str())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the file by renaming a temporary file over it, so that a crash never leaves a half-written file.
+fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    std::fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    let temp = path.with_extension("rs.tmp");
+    std::fs::write(&temp, content).with_context(|| format!("writing {}", temp.display()))?;
+    std::fs::rename(&temp, path).with_context(|| format!("writing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        panic::AssertUnwindSafe,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use anyhow::Result;
+
+    use crate::{
+        build::Build,
+        passes::StatementDeleter,
+        processor::{Minimizer, Pass, PassController, ProcessState, SourceFile},
+        Options,
+    };
+
+    /// A pass that crashes the minimization.
+    struct Crash;
+
+    impl Pass for Crash {
+        fn process_file(
+            &mut self,
+            krate: &mut syn::File,
+            _: &SourceFile,
+            _: &mut PassController,
+        ) -> ProcessState {
+            krate.items.clear();
+            panic!("crashing the minimization");
+        }
+
+        fn name(&self) -> &'static str {
+            "crash"
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn crash_leaves_reproducing_snapshot() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        let output = dir.path().join("output");
+        fs::create_dir(&src)?;
+        let script = dir.path().join("script.sh");
+        fs::write(&script, "#!/bin/sh\ngrep -q needed src/main.rs\n")?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        fs::write(
+            src.join("main.rs"),
+            "fn main() {\n    1;\n    \"needed\";\n    2;\n}\n",
+        )?;
+
+        let options = Options {
+            path: src.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            best_effort_output: Some(output.clone()),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let crashed = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            minimizer.run_passes([StatementDeleter.boxed(), Crash.boxed()])
+        }));
+        assert!(crashed.is_err());
+
+        let snapshot = fs::read_to_string(output.join("src/main.rs"))?;
+        assert_eq!(snapshot, "fn main() {\n    \"needed\";\n}\n");
+        assert!(!output.join("src/main.rs.tmp").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! Records every change that was kept, so that the minimization can be replayed without building.
+//! Every line of the log is a JSON object for one change.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::Options;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct LoggedChange {
+    pub(crate) pass: String,
+    pub(crate) file: PathBuf,
+    /// The candidates that the pass applied.
+    pub(crate) paths: Vec<Vec<String>>,
+    /// The content of the file after the change, for changes that depend on compiler diagnostics
+    /// and therefore can't be replayed from the paths.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) content: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ChangeLog {
+    file: Option<File>,
+}
+
+impl ChangeLog {
+    pub(crate) fn new(options: &Options) -> Result<Self> {
+        let file = options
+            .change_log
+            .as_ref()
+            .map(|path| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("opening change log {}", path.display()))
+            })
+            .transpose()?;
+        Ok(Self { file })
+    }
+
+    pub(crate) fn record(&self, change: &LoggedChange) -> Result<()> {
+        let Some(mut file) = self.file.as_ref() else {
+            return Ok(());
+        };
+        let line = serde_json::to_string(change)?;
+        writeln!(file, "{line}").context("writing to change log")
+    }
+
+    pub(crate) fn read(path: &Path) -> Result<Vec<LoggedChange>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("reading change log {}", path.display()))?;
+        content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("parsing line {} of {}", i + 1, path.display()))
+            })
+            .collect()
+    }
+}
@@ -25,9 +25,37 @@ impl Debug for AstPath {
 #[derive(Debug)]
 pub(crate) struct PassController {
     state: PassControllerState,
+    /// The candidates that the pass was allowed to apply since the last `take_applied`.
+    applied: Vec<AstPath>,
+    /// The number of different candidates from the initial collection, for the progress bar.
+    collected: usize,
+    /// Shuffles the candidates before they are split with `--seed`.
+    shuffle: Option<Shuffle>,
     pub(crate) options: Options,
 }
 
+/// A small pseudo random number generator (SplitMix64), so that the order of the candidates only depends on
+/// the seed.
+#[derive(Debug)]
+struct Shuffle(u64);
+
+impl Shuffle {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
 /// The current state of the bisection.
 #[derive(Debug)]
 enum PassControllerState {
@@ -49,9 +77,13 @@ enum PassControllerState {
         current: BTreeSet<AstPath>,
         /// The list of `current`s that we want to try in the future.
         worklist: Worklist,
+        /// The sets of candidates that are tried at the same time with `--bisect-jobs`.
+        batch: Vec<BTreeSet<AstPath>>,
     },
     /// Bisection is over and all candidates were able to be committed or thrown away.
     Success,
+    /// Exactly these candidates are applied, as they were in a previous run. Nothing is bisected.
+    Replay { candidates: BTreeSet<AstPath> },
 }
 
 mod worklist {
@@ -75,6 +107,11 @@ mod worklist {
         pub(super) fn pop(&mut self) -> Option<Vec<AstPath>> {
             self.0.pop()
         }
+
+        /// The number of candidates in all sets of the worklist.
+        pub(super) fn candidates(&self) -> usize {
+            self.0.iter().map(Vec::len).sum()
+        }
     }
 }
 
@@ -84,13 +121,30 @@ impl PassController {
             state: PassControllerState::InitialCollection {
                 candidates: Vec::new(),
             },
+            applied: Vec::new(),
+            collected: 0,
+            shuffle: options.seed.map(Shuffle),
+            options,
+        }
+    }
+
+    /// A controller that lets the pass apply exactly these candidates once.
+    pub fn replay(options: Options, candidates: Vec<Vec<String>>) -> Self {
+        Self {
+            state: PassControllerState::Replay {
+                candidates: candidates.into_iter().map(AstPath).collect(),
+            },
+            applied: Vec::new(),
+            collected: 0,
+            shuffle: None,
             options,
         }
     }
 
     pub fn reproduces(&mut self) {
         match &mut self.state {
-            PassControllerState::InitialCollection { .. } => {
+            PassControllerState::InitialCollection { candidates } => {
+                self.collected = candidates.iter().collect::<BTreeSet<_>>().len();
                 self.state = PassControllerState::Success;
             }
             PassControllerState::Bisecting {
@@ -98,12 +152,14 @@ impl PassController {
                 failed: _,
                 current,
                 worklist: _,
+                batch: _,
             } => {
                 committed.extend(mem::take(current));
 
                 self.next_in_worklist();
             }
-            PassControllerState::Success { .. } => unreachable!("Processed after success"),
+            PassControllerState::Success => unreachable!("Processed after success"),
+            PassControllerState::Replay { .. } => unreachable!("Built while replaying"),
         }
     }
 
@@ -111,8 +167,21 @@ impl PassController {
     pub fn does_not_reproduce(&mut self) {
         match &mut self.state {
             PassControllerState::InitialCollection { candidates } => {
+                // A pass may use the same path for several sites. They are a single candidate, otherwise
+                // one half could remove all of them and leave nothing to change for the other half.
+                let mut seen = BTreeSet::new();
+                candidates.retain(|candidate| seen.insert(candidate.clone()));
+                self.collected = candidates.len();
+
+                if candidates.len() == 1 {
+                    // There is nothing to bisect, the only candidate is bad.
+                    self.state = PassControllerState::Success;
+                    return;
+                }
+
                 // Applying them all was too much, let's bisect!
-                let (current, first_worklist_item) = split_owned(mem::take(candidates));
+                let (current, first_worklist_item) =
+                    split(&mut self.shuffle, mem::take(candidates));
 
                 let mut worklist = Worklist::new();
                 worklist.push(first_worklist_item);
@@ -122,6 +191,7 @@ impl PassController {
                     failed: BTreeSet::new(),
                     current,
                     worklist,
+                    batch: Vec::new(),
                 };
             }
             PassControllerState::Bisecting {
@@ -129,6 +199,7 @@ impl PassController {
                 failed,
                 current,
                 worklist,
+                batch: _,
             } => {
                 debug!(
                     ?committed,
@@ -143,7 +214,7 @@ impl PassController {
                     failed.extend(mem::take(current));
                 } else {
                     // Split it further and add it to the worklist.
-                    let (first_half, second_half) = split_owned(mem::take(current));
+                    let (first_half, second_half) = split(&mut self.shuffle, mem::take(current));
 
                     worklist.push(first_half);
                     worklist.push(second_half);
@@ -151,7 +222,8 @@ impl PassController {
 
                 self.next_in_worklist()
             }
-            PassControllerState::Success { .. } => unreachable!("Processed after success"),
+            PassControllerState::Success => unreachable!("Processed after success"),
+            PassControllerState::Replay { .. } => unreachable!("Built while replaying"),
         }
     }
 
@@ -168,7 +240,7 @@ impl PassController {
             PassControllerState::Bisecting { current, .. } => {
                 unreachable!("Pass said it didn't change anything in the bisection phase, nils forgot what this means: {current:?}");
             }
-            PassControllerState::Success { .. } => {}
+            PassControllerState::Success | PassControllerState::Replay { .. } => {}
         }
     }
 
@@ -176,23 +248,137 @@ impl PassController {
         match &mut self.state {
             PassControllerState::InitialCollection { .. } => false,
             PassControllerState::Bisecting { .. } => false,
-            PassControllerState::Success { .. } => true,
+            PassControllerState::Success => true,
+            PassControllerState::Replay { .. } => true,
+        }
+    }
+
+    /// The number of candidates that the pass has collected in its initial application.
+    pub fn candidate_count(&self) -> usize {
+        match &self.state {
+            PassControllerState::InitialCollection { candidates } => candidates.len(),
+            _ => unreachable!("candidate_count called after the initial collection"),
+        }
+    }
+
+    /// How many candidates have been decided on and how many are still left to try.
+    pub fn candidate_progress(&self) -> (usize, usize) {
+        match &self.state {
+            PassControllerState::InitialCollection { candidates } => (0, candidates.len()),
+            PassControllerState::Bisecting {
+                current, worklist, ..
+            } => {
+                let remaining = current.len() + worklist.candidates();
+                (self.collected - remaining, remaining)
+            }
+            PassControllerState::Success | PassControllerState::Replay { .. } => {
+                (self.collected, 0)
+            }
         }
     }
 
     /// Checks whether a pass may apply the changes for a minimization site.
     pub fn can_process(&mut self, path: &[String]) -> bool {
-        match &mut self.state {
+        let can_process = match &mut self.state {
             PassControllerState::InitialCollection { candidates } => {
                 // For the initial collection, we collect the candidate and apply them all.
                 candidates.push(AstPath(path.to_owned()));
                 true
             }
             PassControllerState::Bisecting { current, .. } => current.contains(path),
-            PassControllerState::Success { .. } => {
+            PassControllerState::Replay { candidates } => candidates.contains(path),
+            PassControllerState::Success => {
                 unreachable!("Processed further after success");
             }
+        };
+        if can_process {
+            self.applied.push(AstPath(path.to_owned()));
+        }
+        can_process
+    }
+
+    /// The candidates that the pass applied since this was last called, to log the change that they made.
+    pub fn take_applied(&mut self) -> Vec<Vec<String>> {
+        mem::take(&mut self.applied)
+            .into_iter()
+            .map(|path| path.0)
+            .collect()
+    }
+
+    /// Starts trying several sets of candidates at once, up to `--bisect-jobs` of them. Returns the number
+    /// of sets. For every set, the pass is applied after `select_in_batch`, and then everything is built at
+    /// once. Outside of bisection, there is only ever one set.
+    pub fn start_batch(&mut self) -> usize {
+        let jobs = self.options.bisect_jobs as usize;
+        match &mut self.state {
+            PassControllerState::Bisecting {
+                current,
+                worklist,
+                batch,
+                ..
+            } => {
+                *batch = vec![current.clone()];
+                while batch.len() < jobs {
+                    match worklist.pop() {
+                        Some(next) => batch.push(next.into_iter().collect()),
+                        None => break,
+                    }
+                }
+                batch.len()
+            }
+            _ => 1,
+        }
+    }
+
+    /// The pass applies the set with this index of the batch next.
+    pub fn select_in_batch(&mut self, index: usize) {
+        let PassControllerState::Bisecting { current, batch, .. } = &mut self.state else {
+            unreachable!("select_in_batch called on non-bisecting state");
+        };
+        *current = batch[index].clone();
+    }
+
+    /// The results of building the sets of the batch, `None` for sets where the pass didn't change anything.
+    /// The first set that reproduced has been committed. The other sets that reproduced were built without
+    /// it and may not work together with it, so they are tried again on top of it.
+    pub fn batch_results(&mut self, results: &[Option<bool>]) {
+        let Self {
+            state:
+                PassControllerState::Bisecting {
+                    committed,
+                    failed,
+                    worklist,
+                    batch,
+                    ..
+                },
+            shuffle,
+            ..
+        } = self
+        else {
+            unreachable!("batch_results called on non-bisecting state");
+        };
+        assert_eq!(batch.len(), results.len());
+
+        let first_reproducing = results.iter().position(|&result| result == Some(true));
+        let batch = mem::take(batch);
+        debug!(?committed, ?failed, ?batch, ?results, "Built batch");
+
+        // In reverse, so that the worklist is popped in the order of the batch again.
+        for (index, (set, result)) in batch.into_iter().zip(results).enumerate().rev() {
+            match result {
+                None => {}
+                Some(true) if Some(index) == first_reproducing => committed.extend(set),
+                Some(true) => worklist.push(set.into_iter().collect()),
+                Some(false) if set.len() == 1 => failed.extend(set),
+                Some(false) => {
+                    let (first_half, second_half) = split(shuffle, set);
+                    worklist.push(first_half);
+                    worklist.push(second_half);
+                }
+            }
         }
+
+        self.next_in_worklist();
     }
 
     fn next_in_worklist(&mut self) {
@@ -225,6 +411,18 @@ pub const fn div_ceil(lhs: usize, rhs: usize) -> usize {
     }
 }
 
+/// Splits the candidates in half, after shuffling them with `--seed`.
+fn split<A: FromIterator<AstPath>, B: FromIterator<AstPath>>(
+    shuffle: &mut Option<Shuffle>,
+    candidates: impl IntoIterator<Item = AstPath>,
+) -> (A, B) {
+    let mut candidates = candidates.into_iter().collect::<Vec<_>>();
+    if let Some(shuffle) = shuffle {
+        shuffle.shuffle(&mut candidates);
+    }
+    split_owned(candidates)
+}
+
 /// Splits an owned container in half.
 fn split_owned<T, From: IntoIterator<Item = T>, A: FromIterator<T>, B: FromIterator<T>>(
     vec: From,
@@ -239,3 +437,159 @@ fn split_owned<T, From: IntoIterator<Item = T>, A: FromIterator<T>, B: FromItera
 
     (first_half, second_half)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::Options;
+
+    use super::PassController;
+
+    /// Drives a `PassController` like `Minimizer::process_file` does, with a fake pass that
+    /// tries to apply every candidate that hasn't been applied yet. The issue reproduces as long
+    /// as none of the `required` candidates have been applied. Returns the applied candidates and
+    /// how many times the minimizer had to wait for builds.
+    fn minimize(candidates: usize, required: &[usize], jobs: u32) -> (BTreeSet<usize>, usize) {
+        let mut checker = PassController::new(Options {
+            bisect_jobs: jobs,
+            ..Options::default()
+        });
+        let mut applied = BTreeSet::new();
+        let mut builds = 0;
+
+        loop {
+            let batch_size = checker.start_batch();
+            let attempts = (0..batch_size)
+                .map(|index| {
+                    if batch_size > 1 {
+                        checker.select_in_batch(index);
+                    }
+                    (0..candidates)
+                        .filter(|c| !applied.contains(c))
+                        .filter(|c| checker.can_process(&[c.to_string()]))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let results = attempts
+                .iter()
+                .map(|attempt| {
+                    (!attempt.is_empty()).then(|| !attempt.iter().any(|c| required.contains(c)))
+                })
+                .collect::<Vec<_>>();
+
+            if results.iter().any(Option::is_some) {
+                builds += 1;
+            }
+            if let Some(first) = results.iter().position(|&result| result == Some(true)) {
+                applied.extend(attempts[first].iter().copied());
+            }
+
+            if batch_size > 1 {
+                checker.batch_results(&results);
+            } else {
+                match results[0] {
+                    None => checker.no_change(),
+                    Some(true) => checker.reproduces(),
+                    Some(false) => checker.does_not_reproduce(),
+                }
+            }
+
+            if checker.is_finished() {
+                break;
+            }
+            assert!(builds < 1000, "bisection did not converge");
+        }
+
+        (applied, builds)
+    }
+
+    fn expected(candidates: usize, required: &[usize]) -> BTreeSet<usize> {
+        (0..candidates).filter(|c| !required.contains(c)).collect()
+    }
+
+    #[test]
+    fn no_candidates() {
+        let (applied, builds) = minimize(0, &[], 1);
+        assert!(applied.is_empty());
+        assert_eq!(builds, 0);
+    }
+
+    #[test]
+    fn all_candidates_apply() {
+        let (applied, builds) = minimize(10, &[], 1);
+        assert_eq!(applied, expected(10, &[]));
+        assert_eq!(builds, 1);
+    }
+
+    #[test]
+    fn single_bad_candidate() {
+        let (applied, builds) = minimize(1, &[0], 1);
+        assert!(applied.is_empty());
+        assert_eq!(builds, 1);
+    }
+
+    #[test]
+    fn converges_to_minimal_set() {
+        for candidates in 1..20 {
+            for required in [vec![0], vec![candidates - 1], vec![0, candidates / 2]] {
+                let (applied, _) = minimize(candidates, &required, 1);
+                assert_eq!(
+                    applied,
+                    expected(candidates, &required),
+                    "{candidates} candidates with {required:?} required"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn everything_required() {
+        let required = (0..8).collect::<Vec<_>>();
+        let (applied, _) = minimize(8, &required, 1);
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn seed_shuffles_candidates() {
+        // The candidates that are tried first after applying all of them failed.
+        let first_half = |seed| {
+            let mut checker = PassController::new(Options {
+                seed,
+                ..Options::default()
+            });
+            for c in 0..20 {
+                checker.can_process(&[c.to_string()]);
+            }
+            checker.does_not_reproduce();
+            (0..20)
+                .filter(|c| checker.can_process(&[c.to_string()]))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(first_half(None), (0..10).collect::<Vec<_>>());
+        assert_eq!(first_half(Some(1)), first_half(Some(1)));
+        assert_ne!(first_half(Some(1)), first_half(Some(2)));
+        assert_ne!(first_half(Some(1)), first_half(None));
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        for candidates in 1..40 {
+            for required in [
+                vec![],
+                vec![0],
+                vec![candidates - 1],
+                vec![1, candidates / 2],
+            ] {
+                let (sequential, sequential_builds) = minimize(candidates, &required, 1);
+                let (parallel, parallel_builds) = minimize(candidates, &required, 4);
+                assert_eq!(
+                    parallel, sequential,
+                    "{candidates} candidates with {required:?} required"
+                );
+                assert!(parallel_builds <= sequential_builds);
+            }
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Saves the progress of the minimization so that an interrupted run can be resumed.
+//! We don't need to save the bisection state of the file that was being processed: apart from the change that
+//! is being built, the files on disk only contain changes that reproduce, so we can just start over with that
+//! file. The content from before the change that is being built is saved too, as a run that is killed during
+//! the build leaves the change on disk. Resuming writes the content back.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::Options;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    /// The passes of the run, to make sure that we resume the same thing.
+    passes: Vec<String>,
+    /// The number of passes that have finished, in the current cycle of `--fixpoint`.
+    finished_passes: usize,
+    /// The files that the current round of the current pass has finished.
+    finished_files: BTreeSet<PathBuf>,
+    /// Whether the current round of the current pass has made any changes.
+    round_had_changes: bool,
+    /// The file that has a change on disk that isn't known to reproduce yet, with its content from before.
+    #[serde(default)]
+    pending: Option<(PathBuf, String)>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Checkpoint {
+    path: Option<PathBuf>,
+    state: RefCell<State>,
+}
+
+impl Checkpoint {
+    pub(crate) fn new(options: &Options) -> Result<Self> {
+        let state = match &options.checkpoint {
+            Some(path) if options.resume => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("reading checkpoint {}", path.display()))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("parsing checkpoint {}", path.display()))?
+            }
+            _ => State::default(),
+        };
+
+        let checkpoint = Self {
+            path: options.checkpoint.clone(),
+            state: RefCell::new(state),
+        };
+        checkpoint.restore_pending()?;
+        Ok(checkpoint)
+    }
+
+    /// Writes the content from before the change back that was being built when the run was interrupted.
+    fn restore_pending(&self) -> Result<()> {
+        let Some((path, content)) = self.state.borrow_mut().pending.take() else {
+            return Ok(());
+        };
+        warn!(
+            "Restoring {}, the run was interrupted while a change to it was built",
+            path.display()
+        );
+        std::fs::write(&path, content).with_context(|| format!("restoring {}", path.display()))?;
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string_pretty(&*self.state.borrow())?;
+        std::fs::write(path, content)
+            .with_context(|| format!("writing checkpoint {}", path.display()))
+    }
+
+    pub(crate) fn start(&self, passes: &[String]) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if state.passes.is_empty() {
+            state.passes = passes.to_owned();
+        } else if state.passes != passes {
+            bail!(
+                "Cannot resume, the checkpoint is for the passes {}",
+                state.passes.join(",")
+            );
+        } else {
+            info!("Resuming after {} finished passes", state.finished_passes);
+        }
+        drop(state);
+        self.save()
+    }
+
+    pub(crate) fn pass_finished(&self, index: usize) -> bool {
+        index < self.state.borrow().finished_passes
+    }
+
+    pub(crate) fn finish_pass(&self, index: usize) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.finished_passes = index + 1;
+        state.finished_files.clear();
+        state.round_had_changes = false;
+        drop(state);
+        self.save()
+    }
+
+    pub(crate) fn finish_cycle(&self) -> Result<()> {
+        self.state.borrow_mut().finished_passes = 0;
+        self.save()
+    }
+
+    /// Saves the content from before a change, before the change is written to the file to build it.
+    pub(crate) fn start_change(&self, path: &Path, before: &str) -> Result<()> {
+        if self.path.is_none() {
+            return Ok(());
+        }
+        self.state.borrow_mut().pending = Some((path.to_owned(), before.to_owned()));
+        self.save()
+    }
+
+    /// The change was committed or rolled back, the file on disk reproduces again.
+    pub(crate) fn finish_change(&self) -> Result<()> {
+        if self.state.borrow_mut().pending.take().is_none() {
+            return Ok(());
+        }
+        self.save()
+    }
+
+    pub(crate) fn file_finished(&self, path: &Path) -> bool {
+        self.state.borrow().finished_files.contains(path)
+    }
+
+    pub(crate) fn finish_file(&self, path: &Path, had_changes: bool) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.finished_files.insert(path.to_owned());
+        state.round_had_changes |= had_changes;
+        drop(state);
+        self.save()
+    }
+
+    /// Returns whether any file of the round made changes, including the files from before we resumed.
+    pub(crate) fn finish_round(&self) -> Result<bool> {
+        let mut state = self.state.borrow_mut();
+        state.finished_files.clear();
+        let had_changes = std::mem::take(&mut state.round_had_changes);
+        drop(state);
+        self.save()?;
+        Ok(had_changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        fs,
+        rc::Rc,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use anyhow::Result;
+    use quote::ToTokens;
+
+    use super::Checkpoint;
+    use crate::{minimize_with_fn, Options};
+
+    #[test]
+    fn resume_skips_finished_passes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let checkpoint = dir.path().join("checkpoint.json");
+        fs::write(&file, "pub fn main() { let x = 1; }")?;
+
+        let options = Options {
+            path: file.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            passes: Some("privatize,everybody-loops".into()),
+            checkpoint: Some(checkpoint.clone()),
+            ..Options::default()
+        };
+        let minimize = |options: Options| {
+            let builds = Rc::new(Cell::new(0));
+            let counter = Rc::clone(&builds);
+            minimize_with_fn(
+                options,
+                Box::new(move |_| {
+                    counter.set(counter.get() + 1);
+                    true
+                }),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .map(|()| builds.get())
+        };
+
+        // The initial build, privatize and everybody-loops, which is interrupted before it finishes.
+        minimize(Options {
+            max_builds: Some(3),
+            ..options.clone()
+        })?;
+        let state: super::State = serde_json::from_str(&fs::read_to_string(&checkpoint)?)?;
+        assert_eq!(state.finished_passes, 1);
+        assert!(state.pending.is_none());
+
+        // Only the initial build runs, privatize is skipped and everybody-loops has nothing left to do.
+        let builds = minimize(Options {
+            resume: true,
+            ..options
+        })?;
+        assert_eq!(builds, 1);
+
+        let content = fs::read_to_string(&file)?;
+        let tokens = syn::parse_file(&content)?.into_token_stream().to_string();
+        assert_eq!(tokens, "pub (crate) fn main () { loop { } }");
+
+        let state: super::State = serde_json::from_str(&fs::read_to_string(&checkpoint)?)?;
+        assert_eq!(state.finished_passes, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn resume_restores_change_that_was_built() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let options = Options {
+            checkpoint: Some(dir.path().join("checkpoint.json")),
+            ..Options::default()
+        };
+
+        let checkpoint = Checkpoint::new(&options)?;
+        checkpoint.start(&["delete-statements".to_owned()])?;
+        checkpoint.start_change(&file, "fn main() { needed(); }")?;
+        // The run is killed while the change is built.
+        fs::write(&file, "fn main() {}")?;
+
+        Checkpoint::new(&Options {
+            resume: true,
+            ..options
+        })?;
+        assert_eq!(fs::read_to_string(&file)?, "fn main() { needed(); }");
+
+        Ok(())
+    }
+}
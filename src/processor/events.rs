@@ -0,0 +1,79 @@
+//! Progress events for `--output-format json`. Every event is printed to stdout as a line of JSON,
+//! so that other tools can follow the minimization. The human-readable logs still go to stderr.
+
+use std::{cell::RefCell, io::Write, path::Path};
+
+use serde::Serialize;
+
+use crate::{Options, OutputFormat};
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub(crate) enum Event<'a> {
+    PassStarted {
+        pass: &'a str,
+    },
+    /// The result of a build. `pass` and `file` are missing for the initial build.
+    Build {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pass: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<&'a Path>,
+        reproduces: bool,
+    },
+    ChangeCommitted {
+        pass: &'a str,
+        file: &'a Path,
+    },
+    ChangeRolledBack {
+        pass: &'a str,
+        file: &'a Path,
+    },
+    /// The pass is done with the file for the current round.
+    FileProcessed {
+        pass: &'a str,
+        file: &'a Path,
+    },
+    PassFinished {
+        pass: &'a str,
+    },
+}
+
+pub(crate) struct Events {
+    out: Option<RefCell<Box<dyn Write>>>,
+}
+
+impl std::fmt::Debug for Events {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Events")
+            .field("enabled", &self.out.is_some())
+            .finish()
+    }
+}
+
+impl Events {
+    pub(crate) fn new(options: &Options) -> Self {
+        match options.output_format {
+            OutputFormat::Human => Self { out: None },
+            OutputFormat::Json => Self::to_writer(Box::new(std::io::stdout())),
+        }
+    }
+
+    pub(crate) fn to_writer(out: Box<dyn Write>) -> Self {
+        Self {
+            out: Some(RefCell::new(out)),
+        }
+    }
+
+    pub(crate) fn emit(&self, event: Event<'_>) {
+        let Some(out) = &self.out else {
+            return;
+        };
+        let line = serde_json::to_string(&event).expect("serializing event");
+        let mut out = out.borrow_mut();
+        // Failing to report progress shouldn't stop the minimization.
+        if let Err(err) = writeln!(out, "{line}").and_then(|()| out.flush()) {
+            warn!("Failed to write event: {err}");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Expands all macros before minimizing, see `--expand`. The compiler prints the expanded crate as a single
+//! file, which replaces the crate root. The files of its modules are inlined by the expansion, so they are
+//! deleted once nothing refers to them anymore.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use quote::ToTokens;
+use syn::{Item, ItemExternCrate};
+
+use super::{files::Changes, modules, Minimizer};
+use crate::passes::invoked_macros;
+
+pub(crate) const EXPAND_MACROS: &str = "expand-macros";
+
+impl Minimizer {
+    /// Replaces the crate root with its expanded code. Expansion failing doesn't stop the minimization,
+    /// the unexpanded code is minimized instead.
+    pub(crate) fn expand_macros(&self) -> Result<()> {
+        let live_files = || {
+            self.files
+                .iter()
+                .filter(|file| !file.is_deleted() && !file.runs_at_build_time())
+        };
+        let referenced = live_files()
+            .flat_map(|file| file.referenced_files())
+            .collect::<Vec<_>>();
+        let roots = live_files()
+            .filter(|file| {
+                !referenced
+                    .iter()
+                    .any(|path| modules::same_file(path, file.path_no_fs_interact()))
+            })
+            .collect::<Vec<_>>();
+        let [file] = roots[..] else {
+            warn!(
+                "Not expanding macros, there has to be exactly one crate root but there are {}: {roots:?}",
+                roots.len()
+            );
+            return Ok(());
+        };
+
+        let expanded = match self.build.expand(file.edition()) {
+            Ok(expanded) => expanded,
+            Err(err) => {
+                warn!("Not expanding macros, {err:#}");
+                return Ok(());
+            }
+        };
+        let mut krate = match file.edition().parse_file(&expanded) {
+            Ok(krate) => krate,
+            Err(err) => {
+                warn!("Not expanding macros, the expanded code doesn't parse: {err}");
+                return Ok(());
+            }
+        };
+        remove_injected_prelude(&mut krate);
+        remove_expanded_macros(&mut krate);
+
+        let mut changes = Changes::default();
+        let mut change = file.try_change(&mut changes)?;
+        self.checkpoint
+            .start_change(change.path, change.before_content().0)?;
+        change.write(krate)?;
+
+        let after = self.build.build(&self.files)?;
+        info!("{file:?}: After {EXPAND_MACROS}: {after}");
+        if after.reproduces_issue() {
+            self.commit_change(EXPAND_MACROS, change, Vec::new())?;
+            Ok(())
+        } else {
+            warn!("Not expanding macros, the expanded code doesn't reproduce the issue");
+            self.rollback_change(EXPAND_MACROS, change)
+        }
+    }
+}
+
+/// Removes the standard library prelude that the compiler injects into every crate. It is written with
+/// unstable attributes that don't build on stable compilers, and the crate gets it anyways. The injected
+/// `extern crate std;` (or `core` with `#![no_std]`) comes first, older compilers put `#[macro_use]` on it.
+fn remove_injected_prelude(krate: &mut syn::File) {
+    let len = krate.attrs.len();
+    krate.attrs.retain(|attr| {
+        !(attr.path.is_ident("feature") && attr.tokens.to_string() == "(prelude_import)")
+    });
+    if krate.attrs.len() == len {
+        return;
+    }
+    let injected = krate.items.iter().position(|item| {
+        matches!(item, Item::ExternCrate(ItemExternCrate { ident, rename: None, .. })
+            if ident == "std" || ident == "core")
+    });
+    if let Some(injected) = injected {
+        krate.items.remove(injected);
+    }
+    krate.items.retain(|item| match item {
+        Item::Use(use_) => !use_
+            .attrs
+            .iter()
+            .any(|attr| attr.path.is_ident("prelude_import")),
+        _ => true,
+    });
+}
+
+/// Removes the `macro_rules!` definitions that the compiler keeps in the expanded code. All of their
+/// invocations are expanded, so only the ones that are exported or still invoked from other macros are kept.
+fn remove_expanded_macros(krate: &mut syn::File) {
+    let mut invoked = HashSet::new();
+    invoked_macros(krate.to_token_stream(), &mut invoked);
+    remove_macro_definitions(&mut krate.items, &invoked);
+}
+
+fn remove_macro_definitions(items: &mut Vec<Item>, invoked: &HashSet<String>) {
+    items.retain_mut(|item| match item {
+        Item::Macro(mac) if mac.mac.path.is_ident("macro_rules") => {
+            mac.attrs
+                .iter()
+                .any(|attr| attr.path.is_ident("macro_export"))
+                || mac
+                    .ident
+                    .as_ref()
+                    .is_some_and(|ident| invoked.contains(&ident.to_string()))
+        }
+        Item::Mod(module) => {
+            if let Some((_, items)) = &mut module.content {
+                remove_macro_definitions(items, invoked);
+            }
+            true
+        }
+        _ => true,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use quote::ToTokens;
+
+    use super::{remove_expanded_macros, remove_injected_prelude};
+    use crate::{minimize_sources, Options, Sources};
+
+    #[test]
+    fn removes_injected_prelude() {
+        let mut krate = syn::parse_str(
+            "#![feature(prelude_import)]\n#[prelude_import]\nuse std::prelude::rust_2021::*;\n\
+             #[macro_use]\nextern crate std;\nfn main() {}\n",
+        )
+        .unwrap();
+        remove_injected_prelude(&mut krate);
+        assert_eq!(krate.into_token_stream().to_string(), "fn main () { }");
+
+        // Newer compilers don't put `#[macro_use]` on it.
+        let mut krate = syn::parse_str(
+            "#![feature(prelude_import)]\nextern crate std;\n#[prelude_import]\n\
+             use std::prelude::rust_2021::*;\nextern crate alloc;\nfn main() {}\n",
+        )
+        .unwrap();
+        remove_injected_prelude(&mut krate);
+        assert_eq!(
+            krate.into_token_stream().to_string(),
+            "extern crate alloc ; fn main () { }"
+        );
+    }
+
+    #[test]
+    fn removes_expanded_macros() {
+        let mut krate = syn::parse_str(
+            "macro_rules! expanded { () => {} } \
+             macro_rules! inner { () => {} } \
+             macro_rules! outer { () => { inner!() } } \
+             #[macro_export] macro_rules! exported { () => {} } \
+             mod m { macro_rules! nested { () => {} } } \
+             fn main() { outer!(); }",
+        )
+        .unwrap();
+        remove_expanded_macros(&mut krate);
+        assert_eq!(
+            krate.into_token_stream().to_string(),
+            syn::parse_str::<syn::File>(
+                "macro_rules! inner { () => {} } \
+                 macro_rules! outer { () => { inner!() } } \
+                 #[macro_export] macro_rules! exported { () => {} } \
+                 mod m {} \
+                 fn main() { outer!(); }"
+            )
+            .unwrap()
+            .into_token_stream()
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn minimizes_expanded_macro() {
+        let main = PathBuf::from("src/main.rs");
+        let sources = Sources::from([
+            (
+                PathBuf::from("Cargo.toml"),
+                "[package]\nname = \"expand\"\nversion = \"0.1.0\"\nedition = \"2021\"\n".to_owned(),
+            ),
+            (
+                main.clone(),
+                "macro_rules! make {\n    ($name:ident) => {\n        fn $name() {\n            1;\n            2;\n        }\n    };\n}\n\n\
+                 make!(generated);\n\nfn main() {\n    generated();\n}\n"
+                    .to_owned(),
+            ),
+        ]);
+        let options = Options {
+            expand: true,
+            passes: Some("delete-statements".to_owned()),
+            ..Options::default()
+        };
+
+        let minimized = minimize_sources(&sources, options, |sources| {
+            sources[&PathBuf::from("src/main.rs")].contains("fn generated()")
+        })
+        .unwrap();
+
+        let content = &minimized[&main];
+        assert!(!content.contains("make!(generated)"), "{content}");
+        assert!(!content.contains("macro_rules!"), "{content}");
+        assert!(!content.contains("prelude_import"), "{content}");
+        assert!(!content.contains("extern crate std"), "{content}");
+        assert!(content.contains("fn generated()"), "{content}");
+        assert!(!content.contains("1;"), "{content}");
+    }
+}
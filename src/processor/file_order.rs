@@ -0,0 +1,112 @@
+//! Orders the files before minimizing them, see `--file-order`.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::{Minimizer, SourceFile};
+use crate::FileOrder;
+
+impl Minimizer {
+    /// Sorts the files in the order given by the options. The ordering by mentions needs the output of
+    /// the initial build, the build is cached so it is only run once anyways.
+    pub(crate) fn order_files(&mut self) -> Result<()> {
+        let order = self.options.file_order;
+        if order == FileOrder::Walk {
+            return Ok(());
+        }
+
+        let mentioned = match order {
+            FileOrder::UnmentionedFirst => {
+                let build = self.build.build(&self.files)?;
+                let relative = |path: &Path| self.build.relative(path).ok();
+                self.files
+                    .iter()
+                    .filter(|file| {
+                        let path = file.path_no_fs_interact();
+                        build.mentions(path)
+                            || relative(path).is_some_and(|relative| build.mentions(&relative))
+                    })
+                    .map(|file| file.path_no_fs_interact().to_owned())
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        sort_files(&mut self.files, order, |path| {
+            mentioned.iter().any(|mentioned| mentioned == path)
+        });
+        info!(
+            "Minimizing files in this order: {}",
+            self.files
+                .iter()
+                .map(|file| format!("{file:?}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(())
+    }
+}
+
+/// Sorts the files stably, so files that are equal for the ordering stay in the order they were found.
+fn sort_files(files: &mut [SourceFile], order: FileOrder, mentioned: impl Fn(&Path) -> bool) {
+    match order {
+        FileOrder::Walk => {}
+        FileOrder::Largest => files.sort_by_key(|file| std::cmp::Reverse(file.size().bytes)),
+        FileOrder::UnmentionedFirst => files.sort_by_key(|file| {
+            (
+                mentioned(file.path_no_fs_interact()),
+                std::cmp::Reverse(file.size().bytes),
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use anyhow::Result;
+
+    use super::sort_files;
+    use crate::{edition::Edition, processor::SourceFile, FileOrder};
+
+    #[test]
+    fn unmentioned_files_first() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let open = |name: &str, content: &str| -> Result<SourceFile> {
+            let path = dir.path().join(name);
+            fs::write(&path, content)?;
+            SourceFile::open(path, Edition::E2021)
+        };
+        let mut files = vec![
+            open("main.rs", "fn main() {}\n")?,
+            open("small.rs", "fn a() {}\n")?,
+            open("big.rs", "fn a() {}\nfn b() {}\nfn c() {}\n")?,
+            open("ice.rs", "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\n")?,
+        ];
+        let output = format!(
+            "error: internal compiler error: broken MIR\n --> {}:1:1\n",
+            dir.path().join("ice.rs").display()
+        );
+
+        let names = |files: &[SourceFile]| {
+            files
+                .iter()
+                .map(|file| {
+                    let name = file.path_no_fs_interact().file_name().unwrap();
+                    name.to_str().unwrap().to_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+        let mentioned = |path: &Path| output.contains(&path.display().to_string());
+
+        sort_files(&mut files, FileOrder::Largest, mentioned);
+        assert_eq!(names(&files), ["ice.rs", "big.rs", "main.rs", "small.rs"]);
+
+        sort_files(&mut files, FileOrder::UnmentionedFirst, mentioned);
+        assert_eq!(names(&files), ["big.rs", "main.rs", "small.rs", "ice.rs"]);
+
+        Ok(())
+    }
+}
@@ -1,16 +1,23 @@
 use anyhow::Result;
-use std::{fs, path::Path};
+use std::path::Path;
 
 pub(crate) use self::file::SourceFile;
 
 mod file {
     use anyhow::{Context, Result};
+    use quote::ToTokens;
     use std::{
-        cell::RefCell,
+        cell::{Cell, RefCell},
+        collections::{BTreeSet, HashSet},
+        hash::{Hash, Hasher},
         path::{Path, PathBuf},
     };
 
     use super::{Changes, FileChange};
+    use crate::{
+        edition::Edition,
+        processor::{focus, modules, splice, summary::Size},
+    };
 
     /// The representation of a source file, with the cached AST.
     /// IMPORTANT INVARIANT: All file system operations MUST go through this type.
@@ -20,23 +27,97 @@ mod file {
         path: PathBuf,
         content_str: RefCell<String>,
         content: RefCell<syn::File>,
+        initial_size: Size,
+        edition: Edition,
+        /// The file was deleted because nothing refers to it anymore.
+        deleted: Cell<bool>,
+        /// The code runs while building, in a build script or a proc-macro crate.
+        runs_at_build_time: bool,
+        /// The names of the items between `// MINIMIZE-START` and `// MINIMIZE-END`, if it has markers.
+        marked: Option<HashSet<String>>,
+        /// Changes are spliced into the original text instead of formatting the whole file.
+        preserve_formatting: bool,
     }
 
+    const RESTORE_ATTEMPTS: usize = 3;
+
     impl SourceFile {
-        pub(crate) fn open(path: PathBuf) -> Result<Self> {
+        /// Reads and parses the file. After this, the cached AST is used and kept up to date when writing.
+        /// The text is only parsed again to check a change before it is kept, see `round_trips`.
+        pub(crate) fn open(path: PathBuf, edition: Edition) -> Result<Self> {
             let string = std::fs::read_to_string(&path)
                 .with_context(|| format!("reading file {}", path.display()))?;
-            let content = syn::parse_file(&string)
+            let content = edition
+                .parse_file(&string)
                 .with_context(|| format!("parsing file {}", path.display()))?;
+            let marked = focus::marked_items(&string, &content)
+                .with_context(|| format!("reading the markers of {}", path.display()))?;
             Ok(SourceFile {
                 path,
+                initial_size: Size::of(&string, &content),
                 content_str: RefCell::new(string),
                 content: RefCell::new(content),
+                edition,
+                deleted: Cell::new(false),
+                runs_at_build_time: false,
+                marked,
+                preserve_formatting: false,
             })
         }
 
+        /// The names of the items that are marked to be minimized, if the file has markers.
+        pub(crate) fn marked_items(&self) -> Option<&HashSet<String>> {
+            self.marked.as_ref()
+        }
+
+        pub(crate) fn set_runs_at_build_time(&mut self) {
+            self.runs_at_build_time = true;
+        }
+
+        pub(crate) fn runs_at_build_time(&self) -> bool {
+            self.runs_at_build_time
+        }
+
+        pub(crate) fn set_preserve_formatting(&mut self) {
+            self.preserve_formatting = true;
+        }
+
+        /// The text of the file with the new content. It isn't checked here, as that would parse every
+        /// candidate, see `round_trips` for that.
+        pub(crate) fn render(&self, new: &syn::File) -> Result<String> {
+            if self.preserve_formatting {
+                if let Some(string) = splice::splice(&self.content_str(), new, self.edition) {
+                    return Ok(string);
+                }
+            }
+            crate::formatting::format(new.clone())
+        }
+
+        /// Whether the text of the file parses back into the cached AST. This isn't the case when the
+        /// formatter can't print an AST that a pass built, keeping such a change would leave a file that
+        /// can't be parsed anymore, or that contains different code than the cached AST.
+        pub(crate) fn round_trips(&self) -> bool {
+            let string = self.content_str();
+            let Ok(reparsed) = self.edition.parse_file(&string) else {
+                return false;
+            };
+            if self.preserve_formatting {
+                // Spliced text keeps the original formatting, so only the code can be compared.
+                let tokens = |krate: &syn::File| krate.to_token_stream().to_string();
+                return tokens(&reparsed) == tokens(&self.content.borrow());
+            }
+            crate::formatting::format(reparsed).is_ok_and(|again| again == string)
+        }
+
+        /// Writes the new content without going through a change.
+        #[cfg(test)]
         pub(crate) fn write(&self, new: syn::File) -> Result<()> {
-            let string = crate::formatting::format(new.clone())?;
+            let string = self.render(&new)?;
+            self.write_rendered(new, string)
+        }
+
+        /// Writes the text that `render` returned for the new content.
+        pub(crate) fn write_rendered(&self, new: syn::File, string: String) -> Result<()> {
             std::fs::write(&self.path, &string)
                 .with_context(|| format!("writing file {}", self.path.display()))?;
             *self.content_str.borrow_mut() = string;
@@ -44,9 +125,92 @@ mod file {
             Ok(())
         }
 
+        /// Writes the content from before a change back. The content is written exactly as it was, without
+        /// formatting it again. Writing is retried a few times, as losing the original source is the worst
+        /// thing that can happen to the user.
+        pub(crate) fn restore(&self, content_str: &str, content: &syn::File) -> Result<()> {
+            let mut result = std::fs::write(&self.path, content_str);
+            for _ in 1..RESTORE_ATTEMPTS {
+                let Err(err) = &result else {
+                    break;
+                };
+                warn!(
+                    "Failed to restore {}, trying again: {err}",
+                    self.path.display()
+                );
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                result = std::fs::write(&self.path, content_str);
+            }
+            result.with_context(|| {
+                format!(
+                    "restoring {}. The file now contains a change that was supposed to be rolled back",
+                    self.path.display()
+                )
+            })?;
+
+            *self.content_str.borrow_mut() = content_str.to_owned();
+            *self.content.borrow_mut() = content.clone();
+            Ok(())
+        }
+
+        /// Deletes the file from disk. It won't be minimized anymore.
+        pub(crate) fn delete(&self) -> Result<()> {
+            std::fs::remove_file(&self.path)
+                .with_context(|| format!("deleting file {}", self.path.display()))?;
+            self.deleted.set(true);
+            Ok(())
+        }
+
+        pub(crate) fn is_deleted(&self) -> bool {
+            self.deleted.get()
+        }
+
+        /// The files that this file pulls in with `mod foo;` and `include!`.
+        pub(crate) fn referenced_files(&self) -> BTreeSet<PathBuf> {
+            modules::referenced_files(&self.path, &self.content.borrow())
+        }
+
         pub(crate) fn path_no_fs_interact(&self) -> &Path {
             &self.path
         }
+
+        pub(crate) fn content_str(&self) -> String {
+            self.content_str.borrow().clone()
+        }
+
+        pub(crate) fn content(&self) -> syn::File {
+            self.content.borrow().clone()
+        }
+
+        pub(crate) fn edition(&self) -> Edition {
+            self.edition
+        }
+
+        /// The size of the file when it was opened.
+        pub(crate) fn initial_size(&self) -> Size {
+            self.initial_size
+        }
+
+        pub(crate) fn size(&self) -> Size {
+            if self.is_deleted() {
+                return Size::default();
+            }
+            Size::of(&self.content_str.borrow(), &self.content.borrow())
+        }
+
+        /// Feeds the path and current content of the file into the hasher.
+        pub(crate) fn hash_content(&self, state: &mut impl Hasher) {
+            self.path.hash(state);
+            self.is_deleted().hash(state);
+            self.content_str.borrow().hash(state);
+        }
+
+        /// Like `hash_content`, as if the file contained `content`.
+        pub(crate) fn hash_with_content(&self, content: &str, state: &mut impl Hasher) {
+            self.path.hash(state);
+            false.hash(state);
+            content.hash(state);
+        }
     }
 
     impl PartialEq for SourceFile {
@@ -74,6 +238,7 @@ mod file {
             &'file self,
             changes: &'change mut Changes,
         ) -> Result<FileChange<'file, 'change>> {
+            assert!(!self.is_deleted(), "changing deleted file {self:?}");
             let path = &self.path;
             Ok(FileChange {
                 path,
@@ -101,22 +266,56 @@ pub(crate) struct FileChange<'a, 'b> {
     has_written_change: bool,
 }
 
-impl FileChange<'_, '_> {
+impl<'a> FileChange<'a, '_> {
     pub(crate) fn before_content(&self) -> (&str, &syn::File) {
         (&self.before_content_str, &self.before_content)
     }
 
+    /// Nothing is written if the new code can't be formatted, so there is nothing to roll back.
     pub(crate) fn write(&mut self, new: syn::File) -> Result<()> {
+        let string = self.source_file.render(&new)?;
+        self.write_rendered(new, string)
+    }
+
+    /// Writes the text that `SourceFile::render` returned for the new content.
+    pub(crate) fn write_rendered(&mut self, new: syn::File, string: String) -> Result<()> {
         self.has_written_change = true;
-        self.source_file.write(new)?;
+        self.source_file.write_rendered(new, string)?;
         Ok(())
     }
 
+    /// A unified diff between the content before the change and the current content.
+    pub(crate) fn diff(&self, color: bool) -> String {
+        unified_diff(
+            self.path,
+            &self.before_content_str,
+            &self.source_file.content_str(),
+            color,
+        )
+    }
+
+    pub(crate) fn source_file(&self) -> &'a SourceFile {
+        self.source_file
+    }
+
+    /// The content of the file with the change.
+    pub(crate) fn after_content_str(&self) -> String {
+        self.source_file.content_str()
+    }
+
+    /// The number of lines that the change removed, negative if it added lines.
+    pub(crate) fn removed_lines(&self) -> isize {
+        let before = self.before_content_str.lines().count();
+        let after = self.source_file.content_str().lines().count();
+        before as isize - after as isize
+    }
+
     pub(crate) fn rollback(mut self) -> Result<()> {
         assert!(self.has_written_change);
+        // Even if restoring fails, there's nothing more that dropping the change could do.
         self.has_written_change = false;
-        self.source_file.write(self.before_content.clone())?;
-        Ok(())
+        self.source_file
+            .restore(&self.before_content_str, &self.before_content)
     }
 
     pub(crate) fn commit(mut self) {
@@ -129,7 +328,12 @@ impl FileChange<'_, '_> {
 impl Drop for FileChange<'_, '_> {
     fn drop(&mut self) {
         if self.has_written_change {
-            fs::write(self.path, self.before_content().0).ok();
+            if let Err(err) = self
+                .source_file
+                .restore(&self.before_content_str, &self.before_content)
+            {
+                error!("{err:#}");
+            }
             if !std::thread::panicking() {
                 panic!("File contains unsaved changes!");
             }
@@ -137,8 +341,140 @@ impl Drop for FileChange<'_, '_> {
     }
 }
 
+fn unified_diff(path: &Path, before: &str, after: &str, color: bool) -> String {
+    use owo_colors::OwoColorize;
+
+    let path = path.display().to_string();
+    let diff = similar::TextDiff::from_lines(before, after)
+        .unified_diff()
+        .header(&path, &path)
+        .to_string();
+
+    if !color {
+        return diff;
+    }
+
+    diff.lines()
+        .map(|line| match line.chars().next() {
+            Some('+') => format!("{}\n", line.green()),
+            Some('-') => format!("{}\n", line.red()),
+            Some('@') => format!("{}\n", line.cyan()),
+            _ => format!("{line}\n"),
+        })
+        .collect()
+}
+
 impl Changes {
     pub(crate) fn had_changes(&self) -> bool {
         self.any_change
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use quote::ToTokens;
+
+    use super::{unified_diff, Changes, SourceFile};
+    use crate::edition::Edition;
+
+    #[test]
+    fn rollback_reports_write_failure() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("main.rs");
+        fs::write(&path, "fn main() {}\n")?;
+        let file = SourceFile::open(path.clone(), Edition::E2021)?;
+
+        let mut changes = Changes::default();
+        let mut change = file.try_change(&mut changes)?;
+        change.write(syn::parse_str("")?)?;
+        // Writing to a directory fails, even when running as root.
+        fs::remove_file(&path)?;
+        fs::create_dir(&path)?;
+
+        let err = change.rollback().unwrap_err();
+        assert!(format!("{err:#}").contains(&path.display().to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_shebang_and_crate_attributes() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("main.rs");
+        let header = "#!/usr/bin/env run-cargo-script\n#![feature(foo)]\n#![allow(dead_code)]\n";
+        fs::write(&path, format!("{header}\nfn main() {{}}\n"))?;
+        let file = SourceFile::open(path.clone(), Edition::E2021)?;
+
+        file.write(file.content())?;
+
+        let written = fs::read_to_string(&path)?;
+        assert!(written.starts_with(header), "{written}");
+        let reparsed = syn::parse_file(&written)?;
+        let original = file.content();
+        assert_eq!(reparsed.shebang, original.shebang);
+        assert_eq!(
+            reparsed
+                .attrs
+                .iter()
+                .map(|attr| attr.to_token_stream().to_string())
+                .collect::<Vec<_>>(),
+            ["# ! [feature (foo)]", "# ! [allow (dead_code)]"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_code_that_does_not_round_trip() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("main.rs");
+        fs::write(&path, "fn main() {\n    a();\n    b();\n}\n")?;
+        let file = SourceFile::open(path.clone(), Edition::E2021)?;
+
+        // An expression without a semicolon that isn't the last statement is printed as `a() b()`,
+        // which doesn't parse.
+        let mut krate = file.content();
+        let syn::Item::Fn(main) = &mut krate.items[0] else {
+            unreachable!()
+        };
+        let syn::Stmt::Semi(call, _) = main.block.stmts[0].clone() else {
+            unreachable!()
+        };
+        main.block.stmts[0] = syn::Stmt::Expr(call);
+
+        let mut changes = Changes::default();
+        let mut change = file.try_change(&mut changes)?;
+        change.write(krate)?;
+        assert!(!file.round_trips());
+        change.rollback()?;
+        assert!(file.round_trips());
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "fn main() {\n    a();\n    b();\n}\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_privatize() {
+        let before = "pub fn function() {}\nfn main() {}\n";
+        let after = "pub(crate) fn function() {}\nfn main() {}\n";
+
+        let diff = unified_diff(Path::new("src/main.rs"), before, after, false);
+
+        assert_eq!(
+            diff,
+            "\
+--- src/main.rs
++++ src/main.rs
+@@ -1,2 +1,2 @@
+-pub fn function() {}
++pub(crate) fn function() {}
+ fn main() {}
+"
+        );
+    }
+}
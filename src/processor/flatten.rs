@@ -0,0 +1,84 @@
+//! Merges the files of the crate into the file of the crate root, for a single-file reproduction that can
+//! be put into a bug report or a playground. Module declarations become inline modules, so paths stay the
+//! same. This is meant to run last, when there is not much code left.
+
+use std::{path::Path, time::Instant};
+
+use anyhow::Result;
+
+use super::{events::Event, files::Changes, modules, Minimizer};
+
+pub(crate) const FLATTEN_CRATE: &str = "flatten-crate";
+
+impl Minimizer {
+    /// Inlines the files of all module declarations into the crate roots, which are the files that no other
+    /// file refers to. The change to every root is kept if it still reproduces, the inlined files are
+    /// deleted then.
+    pub(crate) fn flatten_crate(&self) -> Result<()> {
+        let start = Instant::now();
+        self.events.emit(Event::PassStarted {
+            pass: FLATTEN_CRATE,
+        });
+
+        let live_files = || self.files.iter().filter(|file| !file.is_deleted());
+        let referenced = live_files()
+            .flat_map(|file| file.referenced_files())
+            .collect::<Vec<_>>();
+        let roots = live_files()
+            .filter(|file| {
+                !referenced
+                    .iter()
+                    .any(|path| modules::same_file(path, file.path_no_fs_interact()))
+            })
+            .collect::<Vec<_>>();
+
+        let content = |path: &Path| {
+            live_files()
+                .find(|file| modules::same_file(file.path_no_fs_interact(), path))
+                .map(|file| file.content())
+        };
+
+        for file in roots {
+            // An earlier root may have deleted it already if it was both a root and a module.
+            if file.is_deleted() {
+                continue;
+            }
+
+            let mut changes = Changes::default();
+            let mut change = file.try_change(&mut changes)?;
+            let mut krate = change.before_content().1.clone();
+            let transform_start = Instant::now();
+            let inlined =
+                modules::inline_module_files(file.path_no_fs_interact(), &mut krate, &content);
+            self.timing(FLATTEN_CRATE).transform += transform_start.elapsed();
+            if !inlined {
+                continue;
+            }
+            self.checkpoint
+                .start_change(change.path, change.before_content().0)?;
+            change.write(krate)?;
+
+            let build_start = Instant::now();
+            let after = self.build.build(&self.files)?;
+            self.timing(FLATTEN_CRATE).build += build_start.elapsed();
+            info!("{file:?}: After {FLATTEN_CRATE}: {after}");
+            self.events.emit(Event::Build {
+                pass: Some(FLATTEN_CRATE),
+                file: Some(file.path_no_fs_interact()),
+                reproduces: after.reproduces_issue(),
+            });
+
+            if after.reproduces_issue() {
+                self.commit_change(FLATTEN_CRATE, change, Vec::new())?;
+            } else {
+                self.rollback_change(FLATTEN_CRATE, change)?;
+            }
+        }
+
+        self.timing(FLATTEN_CRATE).total += start.elapsed();
+        self.events.emit(Event::PassFinished {
+            pass: FLATTEN_CRATE,
+        });
+        Ok(())
+    }
+}
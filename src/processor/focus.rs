@@ -0,0 +1,166 @@
+//! Restricts the minimization to the items between `// MINIMIZE-START` and `// MINIMIZE-END` comments, for
+//! when the user knows where the issue is. The markers are read when the file is opened, as comments don't
+//! survive formatting. If any file has markers, only the marked items are minimized, everything else is left
+//! as it is. The passes never see the other items, so they can't change them.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{bail, Result};
+use syn::{spanned::Spanned, Item};
+
+use crate::passes::item_name;
+
+const START: &str = "// MINIMIZE-START";
+const END: &str = "// MINIMIZE-END";
+
+/// The names of the top level items between the markers, `None` if the file doesn't have any.
+pub(crate) fn marked_items(content: &str, krate: &syn::File) -> Result<Option<HashSet<String>>> {
+    let mut regions = Vec::new();
+    let mut start = None;
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        match (line.trim(), start) {
+            (START, None) => start = Some(number),
+            (END, Some(first)) => {
+                regions.push(first..number);
+                start = None;
+            }
+            (START, Some(_)) => bail!("line {number}: `{START}` inside of a marked region"),
+            (END, None) => bail!("line {number}: `{END}` without a `{START}` before it"),
+            _ => {}
+        }
+    }
+    if let Some(first) = start {
+        bail!("line {first}: `{START}` without a `{END}` after it");
+    }
+    if regions.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        krate
+            .items
+            .iter()
+            .filter(|item| {
+                let span = item.span();
+                regions.iter().any(|region| {
+                    region.contains(&span.start().line) && region.contains(&span.end().line)
+                })
+            })
+            .map(item_name)
+            .collect(),
+    ))
+}
+
+/// The items that are hidden from a pass.
+pub(crate) struct Hidden {
+    /// The names of the visible items in their original order.
+    visible: Vec<String>,
+    /// The hidden items, with the number of visible items before them.
+    items: Vec<(usize, Item)>,
+}
+
+/// Takes the items that aren't marked out of the file.
+pub(crate) fn hide(krate: &mut syn::File, marked: &HashSet<String>) -> Hidden {
+    let mut hidden = Hidden {
+        visible: Vec::new(),
+        items: Vec::new(),
+    };
+    for item in std::mem::take(&mut krate.items) {
+        let name = item_name(&item);
+        if marked.contains(&name) {
+            hidden.visible.push(name);
+            krate.items.push(item);
+        } else {
+            hidden.items.push((hidden.visible.len(), item));
+        }
+    }
+    hidden
+}
+
+/// Puts the hidden items back, after the closest visible item that came before them and is still there.
+pub(crate) fn restore(krate: &mut syn::File, hidden: Hidden) {
+    let present = krate.items.iter().map(item_name).collect::<HashSet<_>>();
+    let mut leading = Vec::new();
+    let mut after = BTreeMap::<String, Vec<Item>>::new();
+    for (visible_before, item) in hidden.items {
+        let anchor = hidden.visible[..visible_before]
+            .iter()
+            .rev()
+            .find(|name| present.contains(*name));
+        match anchor {
+            Some(anchor) => after.entry(anchor.clone()).or_default().push(item),
+            None => leading.push(item),
+        }
+    }
+
+    let mut items = leading;
+    for item in std::mem::take(&mut krate.items) {
+        let following = after.remove(&item_name(&item));
+        items.push(item);
+        items.extend(following.into_iter().flatten());
+    }
+    krate.items = items;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use anyhow::Result;
+    use quote::ToTokens;
+
+    use super::marked_items;
+    use crate::{
+        build::Build,
+        passes::EverybodyLoops,
+        processor::{Minimizer, Pass},
+        Options,
+    };
+
+    #[test]
+    fn rejects_unbalanced_markers() {
+        let krate = syn::parse_str("fn f() {}").unwrap();
+        assert!(marked_items("// MINIMIZE-START\nfn f() {}\n", &krate).is_err());
+        assert!(marked_items("fn f() {}\n// MINIMIZE-END\n", &krate).is_err());
+        assert!(marked_items("fn f() {}\n", &krate).unwrap().is_none());
+    }
+
+    #[test]
+    fn only_marked_function_is_minimized() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        fs::write(
+            &file,
+            "fn before() {\n    1;\n}\n\
+             // MINIMIZE-START\n\
+             fn marked() {\n    2;\n}\n\
+             // MINIMIZE-END\n\
+             fn main() {\n    3;\n}\n",
+        )?;
+
+        let options = Options {
+            path: file.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([EverybodyLoops.boxed()])?;
+
+        let minimized = syn::parse_file(&fs::read_to_string(&file)?)?;
+        let expected = syn::parse_str::<syn::File>(
+            "fn before() { 1; } fn marked() { loop {} } fn main() { 3; }",
+        )?;
+        assert_eq!(
+            minimized.into_token_stream().to_string(),
+            expected.into_token_stream().to_string()
+        );
+
+        Ok(())
+    }
+}
@@ -1,16 +1,56 @@
+mod best_effort;
+mod change_log;
 mod checker;
+mod checkpoint;
+mod events;
+mod expand;
+mod file_order;
 mod files;
+mod flatten;
+mod focus;
+mod modules;
+mod progress;
 mod reaper;
+mod splice;
+mod summary;
+mod validate;
 
 pub(crate) use self::files::SourceFile;
-use crate::{build::Build, processor::files::Changes, Options};
+use crate::{
+    build::Build,
+    edition::Edition,
+    processor::{
+        best_effort::BestEffortOutput,
+        change_log::{ChangeLog, LoggedChange},
+        checkpoint::Checkpoint,
+        events::{Event, Events},
+        files::{Changes, FileChange},
+        progress::ProgressBar,
+        summary::{FileSummary, PassTiming, Summary},
+    },
+    workspace, Options,
+};
 use anyhow::{bail, Context, Result};
+use ignore::overrides::{Override, OverrideBuilder};
 use owo_colors::OwoColorize;
+use quote::ToTokens;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::{collections::HashSet, ffi::OsStr, fmt::Debug, sync::atomic::AtomicBool};
+use std::{
+    cell::{RefCell, RefMut},
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    ffi::OsStr,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+    time::Instant,
+};
 
 pub(crate) use self::checker::PassController;
+pub(crate) use self::expand::EXPAND_MACROS;
+pub(crate) use self::flatten::FLATTEN_CRATE;
+pub(crate) use self::reaper::DELETE_UNUSED_FUNCTIONS;
 
 pub(crate) trait Pass {
     fn refresh_state(&mut self) -> Result<()> {
@@ -19,7 +59,8 @@ pub(crate) trait Pass {
 
     /// Process a file. The state of the processor might get invalidated in the process as signaled with
     /// `ProcessState::FileInvalidated`. When a file is invalidated, the minimizer will call `Processor::refersh_state`
-    /// before calling the this function on the same file again.
+    /// before calling the this function on the same file again. The files that use items of the invalidated file
+    /// are invalidated with it.
     fn process_file(
         &mut self,
         krate: &mut syn::File,
@@ -43,6 +84,51 @@ impl Debug for dyn Pass {
     }
 }
 
+/// A step of the minimization. Most steps run a pass on every file, the others work on the whole crate.
+pub(crate) enum Step<'a> {
+    Pass(Box<dyn Pass + 'a>),
+    /// Deletes the functions that the `dead_code` lint reports, see `Minimizer::delete_dead_code`.
+    DeleteUnusedFunctions,
+    /// Merges the files into the crate roots, see `Minimizer::flatten_crate`.
+    FlattenCrate,
+}
+
+impl Step<'_> {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Step::Pass(pass) => pass.name(),
+            Step::DeleteUnusedFunctions => DELETE_UNUSED_FUNCTIONS,
+            Step::FlattenCrate => FLATTEN_CRATE,
+        }
+    }
+}
+
+impl<'a> From<Box<dyn Pass + 'a>> for Step<'a> {
+    fn from(pass: Box<dyn Pass + 'a>) -> Self {
+        Step::Pass(pass)
+    }
+}
+
+/// The most times that `--fixpoint` runs all steps.
+const MAX_FIXPOINT_CYCLES: u32 = 10;
+
+/// How much a pass has shrunk a file, to stop passes that never converge.
+struct Progress {
+    /// The size in bytes of the smallest version of the file that the pass has produced.
+    smallest: usize,
+    rounds_without_progress: u32,
+    given_up: bool,
+    /// The hashes of the versions of the file that the pass has produced, with the round after which the
+    /// file had it. A version that comes back means that the pass is going in circles.
+    states: HashMap<u64, u32>,
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) enum ProcessState {
     NoChange,
@@ -56,32 +142,219 @@ pub(crate) struct Minimizer {
     build: Build,
     options: Options,
     cancel: Arc<AtomicBool>,
+    checkpoint: Checkpoint,
+    change_log: ChangeLog,
+    best_effort: BestEffortOutput,
+    events: Events,
+    progress: ProgressBar,
+    removed_lines: RefCell<BTreeMap<&'static str, isize>>,
+    /// How long every pass took, in the order the passes ran.
+    timings: RefCell<Vec<(&'static str, PassTiming)>>,
 }
 
 impl Minimizer {
-    fn pass_disabled(&self, name: &str) -> bool {
-        if let Some(passes) = &self.options.passes {
-            if !passes.split(",").any(|allowed| name == allowed) {
-                return true;
-            }
-        }
-        false
-    }
-
     pub(crate) fn new_glob_dir(
         options: Options,
         build: Build,
         cancel: Arc<AtomicBool>,
     ) -> Result<Self> {
         let path = &options.path;
-        let walk = walkdir::WalkDir::new(path);
+        // Restores the file that was being changed when the run was interrupted, before it is read.
+        let checkpoint = Checkpoint::new(&options)?;
+
+        let project_dir = match &options.project_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir().context("finding project directory")?,
+        };
+        // The roots are the directories that are minimized and the directories of their packages.
+        let roots = if options.workspace {
+            workspace::member_dirs(&project_dir)?
+                .into_iter()
+                .map(|member| {
+                    let edition = Edition::from_manifest_dir(&member)?;
+                    Ok((member.join("src"), Some(member), edition))
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            let package = (!options.rustc).then(|| project_dir.clone());
+            let edition = Edition::detect(&options)?;
+            let mut roots = vec![(path.clone(), package, edition)];
+            // The sources of tests, benches and examples are next to `src`, not in it.
+            let target_dir = options
+                .cargo_target
+                .as_ref()
+                .and_then(|target| target.dir())
+                .map(|dir| project_dir.join(dir))
+                .filter(|dir| dir.is_dir() && !same_or_inside(dir, path));
+            if let Some(dir) = target_dir {
+                roots.push((dir, None, edition));
+            }
+            roots
+        };
+
+        build.cancel_on(Arc::clone(&cancel));
+
+        let overrides = Self::file_globs(&options)?;
+        let mut files = Vec::new();
+        for (root, package, edition) in &roots {
+            info!("Parsing files in {} as edition {edition}", root.display());
+            let mut root_files =
+                Self::collect_files(&options, &overrides, root, &project_dir, *edition);
+            if let Some(package) = package {
+                Self::add_build_time_files(&mut root_files, root, package, *edition)?;
+            }
+            files.extend(root_files);
+        }
+
+        if options.preserve_formatting {
+            files
+                .iter_mut()
+                .for_each(SourceFile::set_preserve_formatting);
+        }
+
+        if files.is_empty() {
+            bail!("Did not find any files for path {}", path.display());
+        }
+
+        if options.rustc && files.len() > 1 {
+            bail!("Found more than one file. --rustc only works with a single file.");
+        }
+
+        let change_log = ChangeLog::new(&options)?;
+        let best_effort = BestEffortOutput::new(&options)?;
+        let events = Events::new(&options);
+        let progress = ProgressBar::new(&options);
+
+        Ok(Self {
+            files,
+            build,
+            options,
+            cancel,
+            checkpoint,
+            change_log,
+            best_effort,
+            events,
+            progress,
+            removed_lines: RefCell::new(BTreeMap::new()),
+            timings: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// The `--include` and `--exclude` globs, as overrides. When there are any `--include` globs, only the
+    /// files that they match are collected. The `--exclude` globs ignore the files they match.
+    fn file_globs(options: &Options) -> Result<Override> {
+        let project_dir = match &options.project_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir().context("finding project directory")?,
+        };
+        let mut builder = OverrideBuilder::new(project_dir);
+        for glob in &options.include {
+            builder
+                .add(glob)
+                .with_context(|| format!("invalid --include glob `{glob}`"))?;
+        }
+        for glob in &options.exclude {
+            builder
+                .add(&format!("!{glob}"))
+                .with_context(|| format!("invalid --exclude glob `{glob}`"))?;
+        }
+        builder
+            .build()
+            .context("building --include and --exclude globs")
+    }
+
+    /// Marks the files of a proc-macro crate as running at build time and adds the build script of the
+    /// package when its `src` directory is minimized.
+    fn add_build_time_files(
+        files: &mut Vec<SourceFile>,
+        root: &Path,
+        package: &Path,
+        edition: Edition,
+    ) -> Result<()> {
+        let targets = workspace::build_time_targets(package)?;
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        let root = canonical(root);
+        let package = canonical(package);
+
+        if targets.proc_macro && root.starts_with(&package) {
+            info!("Minimizing a proc-macro crate");
+            files
+                .iter_mut()
+                .for_each(SourceFile::set_runs_at_build_time);
+        }
+
+        if let Some(script) = targets.build_script {
+            if root == package.join("src") && !canonical(&script).starts_with(&root) {
+                match SourceFile::open(script, edition) {
+                    Ok(mut file) => {
+                        info!("Collecting build script: {file:?}");
+                        file.set_runs_at_build_time();
+                        files.push(file);
+                    }
+                    Err(err) => warn!("Skipping build script: {err:#}"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Where a symlinked source file is minimized, if it is minimized at all. Writing through a symlink could
+    /// clobber files anywhere, so the link is resolved and its target has to be in the project directory.
+    /// Targets in the minimized directory are collected on their own, so the link is skipped.
+    fn resolve_symlink(link: &Path, root: &Path, project_dir: &Path) -> Option<PathBuf> {
+        let target = match link.canonicalize() {
+            Ok(target) => target,
+            Err(err) => {
+                warn!("Skipping broken symlink {}: {err}", link.display());
+                return None;
+            }
+        };
+        if same_or_inside(&target, root) {
+            info!(
+                "Skipping symlink {} to {}, which is collected on its own",
+                link.display(),
+                target.display()
+            );
+            return None;
+        }
+        if !same_or_inside(&target, project_dir) {
+            warn!(
+                "Skipping symlink {} to {}, which is outside of the project directory",
+                link.display(),
+                target.display()
+            );
+            return None;
+        }
+        Some(target)
+    }
+
+    fn collect_files(
+        options: &Options,
+        overrides: &Override,
+        path: &Path,
+        project_dir: &Path,
+        edition: Edition,
+    ) -> Vec<SourceFile> {
+        let mut walk = ignore::WalkBuilder::new(path);
+        // Sorted, so that the files are minimized in the same order every time and runs are reproducible.
+        walk.standard_filters(!options.no_ignore)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .hidden(false)
+            .require_git(false)
+            .overrides(overrides.clone());
+        if !options.no_ignore {
+            walk.filter_entry(|entry| {
+                let is_dir = entry.file_type().is_some_and(|ty| ty.is_dir());
+                !(is_dir && entry.file_name() == "target")
+            });
+        }
 
-        let files = walk
-            .into_iter()
+        let mut seen = HashSet::new();
+        walk.build()
             .filter_map(|entry| match entry {
                 Ok(entry) => Some(entry),
                 Err(err) => {
-                    warn!("Error during walkdir: {err}");
+                    warn!("Error while collecting files: {err}");
                     None
                 }
             })
@@ -98,64 +371,263 @@ impl Minimizer {
                     true
                 }
             })
-            .map(|entry| SourceFile::open(entry.into_path()))
-            .inspect(|file| {
-                if let Ok(file) = file {
+            .filter_map(|entry| {
+                if entry.path_is_symlink() {
+                    Self::resolve_symlink(entry.path(), path, project_dir)
+                } else {
+                    Some(entry.into_path())
+                }
+            })
+            // Several links can point to the same file.
+            .filter(|file| seen.insert(file.clone()))
+            .filter_map(|file| match SourceFile::open(file, edition) {
+                Ok(file) => {
                     info!("Collecting file: {file:?}");
+                    Some(file)
+                }
+                // A single file with syntax that syn doesn't understand shouldn't stop us from
+                // minimizing all the other files.
+                Err(err) => {
+                    warn!("Skipping file: {err:#}");
+                    None
                 }
             })
-            .collect::<Result<Vec<_>>>()?;
+            .collect()
+    }
 
-        if files.is_empty() {
-            bail!("Did not find any files for path {}", path.display());
-        }
+    /// Starts a run of these passes, or resumes it from the checkpoint.
+    pub(crate) fn start(&self, passes: &[String]) -> Result<()> {
+        self.checkpoint.start(passes)
+    }
 
-        if options.rustc && files.len() > 1 {
-            bail!("Found more than one file. --rustc only works with a single file.");
+    /// The hash of all files, to tell whether anything changed.
+    fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for file in &self.files {
+            file.hash_content(&mut hasher);
         }
-
-        Ok(Self {
-            files,
-            build,
-            options,
-            cancel,
-        })
+        hasher.finish()
     }
 
+    /// Runs the steps in order, skipping the ones that finished before resuming. With `--fixpoint`, all steps
+    /// run again as long as they changed something.
     pub(crate) fn run_passes<'a>(
         &self,
-        passes: impl IntoIterator<Item = Box<dyn Pass + 'a>>,
+        steps: impl IntoIterator<Item = impl Into<Step<'a>>>,
     ) -> Result<()> {
-        let inital_build = self.build.build()?;
+        let mut steps = steps.into_iter().map(Into::into).collect::<Vec<_>>();
+
+        let inital_build = self.build.build(&self.files)?;
         info!("Initial build: {inital_build}");
+        self.events.emit(Event::Build {
+            pass: None,
+            file: None,
+            reproduces: inital_build.reproduces_issue(),
+        });
         inital_build.require_reproduction("Initial")?;
+        self.best_effort.update(&self.build, &self.files)?;
 
-        for mut pass in passes {
-            if self.pass_disabled(pass.name()) {
-                continue;
+        let mut cycle = 1;
+        'cycles: loop {
+            // Steps that ran before resuming may have made changes that the hash doesn't see.
+            let resumed = self.checkpoint.pass_finished(0);
+            let before = self.content_hash();
+
+            for (i, step) in steps.iter_mut().enumerate() {
+                let name = step.name();
+                if self.checkpoint.pass_finished(i) {
+                    info!("Skipping {name}, it has finished before");
+                    continue;
+                }
+
+                match step {
+                    Step::Pass(pass) => self.run_pass(&mut **pass)?,
+                    Step::DeleteUnusedFunctions => {
+                        self.delete_dead_code().context("deleting dead code")?
+                    }
+                    Step::FlattenCrate => self.flatten_crate().context("flattening crate")?,
+                }
+
+                if self.build.out_of_builds() {
+                    info!("Stopping in {name}, all builds of --max-builds have run");
+                    break 'cycles;
+                }
+                self.checkpoint.finish_pass(i)?;
+
+                if self.options.stop_after.as_deref() == Some(name) {
+                    info!("Stopping after {name}");
+                    break 'cycles;
+                }
+            }
+
+            if !self.options.fixpoint {
+                break;
+            }
+            if !resumed && self.content_hash() == before {
+                info!("Reached a fixpoint after {cycle} cycles");
+                break;
             }
-            self.run_pass(&mut *pass)?;
+            if cycle == MAX_FIXPOINT_CYCLES {
+                info!("Stopping after {cycle} cycles without reaching a fixpoint");
+                break;
+            }
+            self.checkpoint.finish_cycle()?;
+            cycle += 1;
+            info!("Running all passes again, cycle {cycle}");
         }
 
         Ok(())
     }
 
+    /// Applies the pass to every file like the first attempt of a round does, but without writing or
+    /// building anything. Returns the number of candidates that the pass would start bisecting in each file.
+    pub(crate) fn count_candidates(
+        &self,
+        pass: &mut dyn Pass,
+    ) -> Result<Vec<(&SourceFile, usize)>> {
+        self.files
+            .iter()
+            .filter(|file| !file.is_deleted())
+            .map(|file| {
+                let mut changes = Changes::default();
+                let change = file.try_change(&mut changes)?;
+                let (_, krate) = change.before_content();
+                let mut krate = krate.clone();
+                let mut checker = PassController::new(self.options.clone());
+                self.apply_pass(pass, &mut krate, file, &mut checker);
+                Ok((file, checker.candidate_count()))
+            })
+            .collect()
+    }
+
+    /// Files are processed one after another. With `--bisect-jobs`, the candidates of one file are built in
+    /// parallel in scratch directories, see `process_batch`. With `--file-jobs`, files that don't use each
+    /// other are processed together, see `process_files`.
     fn run_pass(&self, pass: &mut dyn Pass) -> Result<()> {
         let mut invalidated_files = HashSet::new();
         let mut refresh_and_try_again = false;
+        // Keyed by path, which stays the same while the file changes.
+        let mut progress = HashMap::<&Path, Progress>::new();
+        let start = Instant::now();
+        self.events.emit(Event::PassStarted { pass: pass.name() });
+        // With markers in any file, the files without markers are left alone.
+        let has_markers = self.files.iter().any(|file| file.marked_items().is_some());
+        let mut round = 0;
         loop {
+            round += 1;
             let span = info_span!("Starting round of pass", name = pass.name());
             let _enter = span.enter();
             let mut changes = Changes::default();
+            self.progress.start_round(
+                pass.name(),
+                self.files.iter().filter(|file| !file.is_deleted()).count(),
+            );
 
-            for file in &self.files {
-                if invalidated_files.contains(file) {
-                    continue;
+            let mut files = self.files.iter().peekable();
+            loop {
+                // With `--file-jobs`, the next files that don't use each other are processed together.
+                let mut group = Vec::<&SourceFile>::new();
+                while group.len() < self.options.file_jobs as usize {
+                    let Some(&file) = files.peek() else {
+                        break;
+                    };
+                    let path = file.path_no_fs_interact();
+                    if invalidated_files.contains(path)
+                        || file.is_deleted()
+                        || (has_markers && file.marked_items().is_none())
+                        || progress.get(path).is_some_and(|progress| progress.given_up)
+                        || self.checkpoint.file_finished(path)
+                    {
+                        files.next();
+                        continue;
+                    }
+                    if group.iter().any(|other| related(file, other)) {
+                        break;
+                    }
+                    group.push(file);
+                    files.next();
+                }
+                if group.is_empty() {
+                    break;
+                }
+
+                // The state is the code, not its text, which differs between the original and the formatted file.
+                let before = group
+                    .iter()
+                    .map(|file| {
+                        let state = content_hash(&file.content().into_token_stream().to_string());
+                        (file.content_str(), state)
+                    })
+                    .collect::<Vec<_>>();
+                match group[..] {
+                    [file] => {
+                        self.process_file(pass, file, &mut invalidated_files, &mut changes)?
+                    }
+                    _ => self.process_files(pass, &group, &mut invalidated_files, &mut changes)?,
+                }
+                if self.build.out_of_builds() {
+                    // The files aren't finished, so a resumed run picks them up again.
+                    break;
+                }
+                for (file, (before, before_state)) in group.into_iter().zip(before) {
+                    let path = file.path_no_fs_interact();
+                    if invalidated_files.contains(path) {
+                        self.invalidate_dependents(file, &mut invalidated_files);
+                    }
+                    if file.content_str() != before {
+                        // The file didn't change in the rounds before, so it is the same as before the pass.
+                        let progress = progress.entry(path).or_insert_with(|| Progress {
+                            smallest: before.len(),
+                            rounds_without_progress: 0,
+                            given_up: false,
+                            states: HashMap::from([(before_state, round - 1)]),
+                        });
+                        let state = content_hash(&file.content().into_token_stream().to_string());
+                        if let Some(earlier) = progress.states.insert(state, round) {
+                            let earlier = match earlier {
+                                0 => "before the pass".to_owned(),
+                                earlier => format!("after round {earlier}"),
+                            };
+                            bail!(
+                                "{} is stuck in a loop on {file:?}: the file after round {round} is the same as {earlier}, \
+                                 so the pass would keep changing it forever. This is a bug in the pass, \
+                                 skip it with `--passes`",
+                                pass.name()
+                            );
+                        }
+                        if file.size().bytes < progress.smallest {
+                            progress.smallest = file.size().bytes;
+                            progress.rounds_without_progress = 0;
+                        } else {
+                            progress.rounds_without_progress += 1;
+                            if progress.rounds_without_progress
+                                >= self.options.max_rounds_without_progress
+                            {
+                                warn!(
+                                    "{file:?}: {} keeps changing the file without making it smaller, skipping the file",
+                                    pass.name()
+                                );
+                                progress.given_up = true;
+                            }
+                        }
+                    }
+                    self.events.emit(Event::FileProcessed {
+                        pass: pass.name(),
+                        file: file.path_no_fs_interact(),
+                    });
+                    self.progress.file_done();
+                    self.checkpoint
+                        .finish_file(file.path_no_fs_interact(), changes.had_changes())?;
                 }
-                self.process_file(pass, file, &mut invalidated_files, &mut changes)?;
             }
 
-            if !changes.had_changes() {
+            if self.build.out_of_builds() {
+                self.progress.finish();
+                self.timing(pass.name()).total += start.elapsed();
+                return Ok(());
+            }
+
+            if !self.checkpoint.finish_round()? {
                 if !refresh_and_try_again && !invalidated_files.is_empty() {
                     pass.refresh_state().context("refreshing state for pass")?;
                     invalidated_files.clear();
@@ -164,7 +636,10 @@ impl Minimizer {
                     continue;
                 }
 
+                self.progress.finish();
                 info!("Finished {}", pass.name());
+                self.timing(pass.name()).total += start.elapsed();
+                self.events.emit(Event::PassFinished { pass: pass.name() });
 
                 return Ok(());
             } else {
@@ -173,12 +648,221 @@ impl Minimizer {
         }
     }
 
+    /// Applies the pass to the file. If the file has markers, the pass only gets to see the marked items.
+    fn apply_pass(
+        &self,
+        pass: &mut dyn Pass,
+        krate: &mut syn::File,
+        file: &SourceFile,
+        checker: &mut PassController,
+    ) -> ProcessState {
+        let Some(marked) = file.marked_items() else {
+            return pass.process_file(krate, file, checker);
+        };
+        let hidden = focus::hide(krate, marked);
+        let has_made_change = pass.process_file(krate, file, checker);
+        focus::restore(krate, hidden);
+        has_made_change
+    }
+
+    /// Invalidates the files that may use items of the invalidated file too, as the change to it can also
+    /// make the state that the pass has about them outdated, like deleting a `pub` item that they import.
+    fn invalidate_dependents<'a>(
+        &'a self,
+        file: &SourceFile,
+        invalidated_files: &mut HashSet<&'a Path>,
+    ) {
+        for dependent in &self.files {
+            if !dependent.is_deleted()
+                && !invalidated_files.contains(dependent.path_no_fs_interact())
+                && modules::depends_on(
+                    dependent.path_no_fs_interact(),
+                    &dependent.content(),
+                    file.path_no_fs_interact(),
+                )
+            {
+                debug!("{dependent:?} uses {file:?}, invalidating it too");
+                invalidated_files.insert(dependent.path_no_fs_interact());
+            }
+        }
+    }
+
+    /// Keeps a change that reproduces the issue. `paths` are the candidates that the pass applied for it.
+    /// The change is rolled back instead if its text doesn't parse back into the same code. This is only
+    /// checked here, so that the text of the candidates that are built isn't parsed. Returns whether the
+    /// change was kept.
+    fn commit_change(
+        &self,
+        pass_name: &'static str,
+        change: FileChange<'_, '_>,
+        paths: Vec<Vec<String>>,
+    ) -> Result<bool> {
+        if !change.source_file().round_trips() {
+            warn!(
+                "{:?}: The code after {pass_name} doesn't parse back after formatting, skipping the change",
+                change.source_file()
+            );
+            self.rollback_change(pass_name, change)?;
+            return Ok(false);
+        }
+        if self.options.diff {
+            info!("Applied change:\n{}", change.diff(!self.options.no_color));
+        }
+        self.events.emit(Event::ChangeCommitted {
+            pass: pass_name,
+            file: change.path,
+        });
+        *self
+            .removed_lines
+            .borrow_mut()
+            .entry(pass_name)
+            .or_default() += change.removed_lines();
+        self.change_log.record(&LoggedChange {
+            pass: pass_name.to_owned(),
+            file: change.path.to_owned(),
+            paths,
+            content: [DELETE_UNUSED_FUNCTIONS, FLATTEN_CRATE, EXPAND_MACROS]
+                .contains(&pass_name)
+                .then(|| change.after_content_str()),
+        })?;
+
+        let referenced_before = modules::referenced_files(change.path, change.before_content().1);
+        let file = change.source_file();
+        change.commit();
+        self.checkpoint.finish_change()?;
+        for path in referenced_before.difference(&file.referenced_files()) {
+            self.delete_unreferenced(path)?;
+        }
+        self.best_effort.update(&self.build, &self.files)?;
+        Ok(true)
+    }
+
+    /// Deletes the file if no other file refers to it with `mod foo;` or `include!` anymore,
+    /// together with the files that only it referred to.
+    fn delete_unreferenced(&self, path: &Path) -> Result<()> {
+        let live_files = || self.files.iter().filter(|file| !file.is_deleted());
+        let Some(file) =
+            live_files().find(|file| modules::same_file(file.path_no_fs_interact(), path))
+        else {
+            return Ok(());
+        };
+        let still_referenced = live_files().any(|other| {
+            other
+                .referenced_files()
+                .iter()
+                .any(|referenced| modules::same_file(referenced, path))
+        });
+        if still_referenced {
+            return Ok(());
+        }
+
+        let referenced = file.referenced_files();
+        file.delete()?;
+        info!("Deleted {file:?}, its module declaration was removed");
+        for path in &referenced {
+            self.delete_unreferenced(path)?;
+        }
+        Ok(())
+    }
+
+    fn rollback_change(&self, pass_name: &'static str, change: FileChange<'_, '_>) -> Result<()> {
+        self.events.emit(Event::ChangeRolledBack {
+            pass: pass_name,
+            file: change.path,
+        });
+        change.rollback()?;
+        self.checkpoint.finish_change()
+    }
+
+    /// Logs the result of applying the pass once. With the progress bar, this would be too noisy.
+    fn log_attempt(&self, file: &SourceFile, pass: &str, result: &dyn std::fmt::Display) {
+        if self.progress.is_enabled() {
+            debug!("{file:?}: After {pass}: {result}");
+        } else {
+            info!("{file:?}: After {pass}: {result}");
+        }
+    }
+
+    /// Applies the changes from a change log of a previous run, without building.
+    pub(crate) fn replay(&self, log: &Path) -> Result<()> {
+        for logged in ChangeLog::read(log)? {
+            let Some(file) = self
+                .files
+                .iter()
+                .find(|file| file.path_no_fs_interact() == logged.file)
+            else {
+                bail!(
+                    "The change log contains a change to {}, which is not being minimized",
+                    logged.file.display()
+                );
+            };
+
+            let mut changes = Changes::default();
+            let mut change = file.try_change(&mut changes)?;
+            let (pass_name, krate) = match &logged.content {
+                Some(content) => (
+                    match logged.pass.as_str() {
+                        FLATTEN_CRATE => FLATTEN_CRATE,
+                        EXPAND_MACROS => EXPAND_MACROS,
+                        _ => DELETE_UNUSED_FUNCTIONS,
+                    },
+                    syn::parse_file(content).context("parsing file from change log")?,
+                ),
+                None => {
+                    let Some(Step::Pass(mut pass)) = crate::passes::by_name(&logged.pass) else {
+                        bail!("The change log contains the unknown pass `{}`", logged.pass);
+                    };
+                    let mut krate = change.before_content().1.clone();
+                    let mut checker =
+                        PassController::replay(self.options.clone(), logged.paths.clone());
+                    self.apply_pass(pass.as_mut(), &mut krate, file, &mut checker);
+                    (pass.name(), krate)
+                }
+            };
+            change.write(krate)?;
+            info!("{file:?}: Replayed {pass_name}");
+            self.commit_change(pass_name, change, logged.paths)?;
+        }
+        Ok(())
+    }
+
+    /// How much smaller the files have gotten since they were opened.
+    pub(crate) fn summary(&self) -> Summary {
+        Summary {
+            files: self
+                .files
+                .iter()
+                .map(|file| FileSummary {
+                    path: file.path_no_fs_interact().to_owned(),
+                    before: file.initial_size(),
+                    after: file.size(),
+                })
+                .collect(),
+            removed_lines: self.removed_lines.borrow().clone(),
+            timings: self.timings.borrow().clone(),
+        }
+    }
+
+    /// The timing of the pass, to add the time that something took to it.
+    fn timing(&self, pass_name: &'static str) -> RefMut<'_, PassTiming> {
+        RefMut::map(self.timings.borrow_mut(), |timings| {
+            let index = match timings.iter().position(|&(pass, _)| pass == pass_name) {
+                Some(index) => index,
+                None => {
+                    timings.push((pass_name, PassTiming::default()));
+                    timings.len() - 1
+                }
+            };
+            &mut timings[index].1
+        })
+    }
+
     #[instrument(skip(self, pass, invalidated_files, changes), fields(pass = %pass.name()), level = "debug")]
     fn process_file<'file>(
         &self,
         pass: &mut dyn Pass,
         file: &'file SourceFile,
-        invalidated_files: &mut HashSet<&'file SourceFile>,
+        invalidated_files: &mut HashSet<&'file Path>,
         changes: &mut Changes,
     ) -> Result<()> {
         // The core logic of minimization.
@@ -188,39 +872,77 @@ impl Minimizer {
 
         let mut checker = PassController::new(self.options.clone());
         loop {
+            if self.build.out_of_builds() {
+                break;
+            }
+            let batch_size = checker.start_batch();
+            if batch_size > 1 {
+                self.process_batch(
+                    pass,
+                    file,
+                    invalidated_files,
+                    changes,
+                    &mut checker,
+                    batch_size,
+                )?;
+                if checker.is_finished() {
+                    break;
+                }
+                continue;
+            }
+
+            // This works on the AST that is cached in the file, so nothing is parsed again here.
             let mut change = file.try_change(changes)?;
             let (_, krate) = change.before_content();
             let mut krate = krate.clone();
-            let has_made_change = pass.process_file(&mut krate, file, &mut checker);
+            let start = Instant::now();
+            let has_made_change = self.apply_pass(pass, &mut krate, file, &mut checker);
+            self.timing(pass.name()).transform += start.elapsed();
+            let applied = checker.take_applied();
 
             match has_made_change {
                 ProcessState::Changed | ProcessState::FileInvalidated => {
-                    change.write(krate)?;
+                    let rendered = file.render(&krate)?;
+                    self.checkpoint
+                        .start_change(change.path, change.before_content().0)?;
+                    change.write_rendered(krate, rendered)?;
 
-                    let after = self.build.build()?;
-                    info!("{file:?}: After {}: {after}", pass.name());
+                    let start = Instant::now();
+                    let after = self.build.build(&self.files)?;
+                    self.timing(pass.name()).build += start.elapsed();
+                    self.log_attempt(file, pass.name(), &after);
+                    self.events.emit(Event::Build {
+                        pass: Some(pass.name()),
+                        file: Some(file.path_no_fs_interact()),
+                        reproduces: after.reproduces_issue(),
+                    });
 
-                    if after.reproduces_issue() {
-                        change.commit();
+                    let kept = if after.reproduces_issue() {
+                        self.commit_change(pass.name(), change, applied)?
+                    } else {
+                        self.rollback_change(pass.name(), change)?;
+                        false
+                    };
+                    if kept {
                         checker.reproduces();
                     } else {
-                        change.rollback()?;
                         checker.does_not_reproduce();
                     }
 
                     if has_made_change == ProcessState::FileInvalidated {
-                        invalidated_files.insert(file);
+                        invalidated_files.insert(file.path_no_fs_interact());
                     }
                 }
                 ProcessState::NoChange => {
                     if self.options.no_color {
-                        info!("{file:?}: After {}: no changes", pass.name());
+                        self.log_attempt(file, pass.name(), &"no changes");
                     } else {
-                        info!("{file:?}: After {}: {}", pass.name(), "no changes".yellow());
+                        self.log_attempt(file, pass.name(), &"no changes".yellow());
                     }
                     checker.no_change();
                 }
             }
+            self.progress.candidates(checker.candidate_progress());
 
             if self.cancel.load(Ordering::SeqCst) {
                 info!("Exiting early.");
@@ -235,6 +957,226 @@ impl Minimizer {
     }
 }
 
+impl Minimizer {
+    /// Processes files that don't use each other together, like `process_file` does for one file. The pass is
+    /// applied to every file, and then the changes to all of them are built at the same time. Changes that
+    /// reproduce on their own are built together once more, as they can still affect each other. If that
+    /// doesn't reproduce, only the first of them is kept, and the others are bisected further.
+    fn process_files<'file>(
+        &self,
+        pass: &mut dyn Pass,
+        group: &[&'file SourceFile],
+        invalidated_files: &mut HashSet<&'file Path>,
+        changes: &mut Changes,
+    ) -> Result<()> {
+        let mut checkers = group
+            .iter()
+            .map(|_| PassController::new(self.options.clone()))
+            .collect::<Vec<_>>();
+        while !checkers.iter_mut().all(PassController::is_finished) {
+            if self.build.out_of_builds() {
+                break;
+            }
+
+            let start = Instant::now();
+            let mut attempts = Vec::new();
+            for (&file, checker) in group.iter().zip(&mut checkers) {
+                if checker.is_finished() {
+                    continue;
+                }
+                let mut krate = file.content().clone();
+                let has_made_change = self.apply_pass(pass, &mut krate, file, checker);
+                let applied = checker.take_applied();
+                if has_made_change == ProcessState::NoChange {
+                    self.log_attempt(file, pass.name(), &"no changes");
+                    checker.no_change();
+                    continue;
+                }
+                attempts.push((file, checker, has_made_change, krate, applied));
+            }
+            self.timing(pass.name()).transform += start.elapsed();
+
+            let rendered = attempts
+                .iter()
+                .map(|(file, _, _, krate, _)| file.render(krate))
+                .collect::<Result<Vec<_>>>()?;
+            let candidates = attempts
+                .iter()
+                .zip(&rendered)
+                .map(|(&(file, ..), content)| vec![(file, content.as_str())])
+                .collect::<Vec<_>>();
+            let start = Instant::now();
+            let builds = self.build.build_parallel(&self.files, &candidates)?;
+            self.timing(pass.name()).build += start.elapsed();
+            for (&(file, ..), after) in attempts.iter().zip(&builds) {
+                self.log_attempt(file, pass.name(), after);
+                self.events.emit(Event::Build {
+                    pass: Some(pass.name()),
+                    file: Some(file.path_no_fs_interact()),
+                    reproduces: after.reproduces_issue(),
+                });
+            }
+
+            let reproducing = candidates
+                .into_iter()
+                .zip(&builds)
+                .filter(|(_, after)| after.reproduces_issue())
+                .flat_map(|(candidate, _)| candidate)
+                .collect::<Vec<_>>();
+            let together = reproducing.len() > 1 && {
+                let start = Instant::now();
+                let after = self
+                    .build
+                    .build_parallel(&self.files, &[reproducing])?
+                    .remove(0);
+                self.timing(pass.name()).build += start.elapsed();
+                info!("After {} together: {after}", pass.name());
+                self.events.emit(Event::Build {
+                    pass: Some(pass.name()),
+                    file: None,
+                    reproduces: after.reproduces_issue(),
+                });
+                after.reproduces_issue()
+            };
+
+            // The changes are kept in order. Every change was built on its own, but the later ones were only built
+            // with the earlier ones if they reproduced together, and never without an earlier one that isn't kept.
+            let mut committed = false;
+            let mut skipped = false;
+            for ((file, checker, has_made_change, krate, applied), (rendered, after)) in
+                attempts.into_iter().zip(rendered.into_iter().zip(builds))
+            {
+                let mut kept = false;
+                if after.reproduces_issue() && (!committed || (together && !skipped)) {
+                    let mut change = file.try_change(changes)?;
+                    self.checkpoint
+                        .start_change(change.path, change.before_content().0)?;
+                    change.write_rendered(krate, rendered)?;
+                    kept = self.commit_change(pass.name(), change, applied)?;
+                    skipped |= !kept;
+                }
+                committed |= kept;
+
+                if kept {
+                    checker.reproduces();
+                    if has_made_change == ProcessState::FileInvalidated {
+                        invalidated_files.insert(file.path_no_fs_interact());
+                    }
+                } else {
+                    checker.does_not_reproduce();
+                }
+                self.progress.candidates(checker.candidate_progress());
+            }
+
+            if self.cancel.load(Ordering::SeqCst) {
+                info!("Exiting early.");
+                std::process::exit(0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the pass once for every set of candidates in the batch and builds them all at the same time.
+    /// The first one that reproduces is committed.
+    fn process_batch<'file>(
+        &self,
+        pass: &mut dyn Pass,
+        file: &'file SourceFile,
+        invalidated_files: &mut HashSet<&'file Path>,
+        changes: &mut Changes,
+        checker: &mut PassController,
+        batch_size: usize,
+    ) -> Result<()> {
+        let base = file.content();
+
+        let start = Instant::now();
+        let mut attempts = Vec::new();
+        for index in 0..batch_size {
+            checker.select_in_batch(index);
+            let mut krate = base.clone();
+            let has_made_change = self.apply_pass(pass, &mut krate, file, checker);
+            attempts.push((has_made_change, krate, checker.take_applied()));
+        }
+        self.timing(pass.name()).transform += start.elapsed();
+
+        let rendered = attempts
+            .iter()
+            .map(|(has_made_change, krate, _)| match has_made_change {
+                ProcessState::NoChange => Ok(None),
+                _ => file.render(krate).map(Some),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let candidates = rendered
+            .iter()
+            .flatten()
+            .map(|content| vec![(file, content.as_str())])
+            .collect::<Vec<_>>();
+        let start = Instant::now();
+        let mut builds = self
+            .build
+            .build_parallel(&self.files, &candidates)?
+            .into_iter();
+        self.timing(pass.name()).build += start.elapsed();
+
+        let mut results = Vec::new();
+        let mut committed = false;
+        for ((has_made_change, krate, applied), rendered) in attempts.into_iter().zip(rendered) {
+            if has_made_change == ProcessState::NoChange {
+                self.log_attempt(file, pass.name(), &"no changes");
+                results.push(None);
+                continue;
+            }
+            let rendered = rendered.expect("rendered every change");
+
+            let after = builds.next().expect("built every change");
+            self.log_attempt(file, pass.name(), &after);
+            self.events.emit(Event::Build {
+                pass: Some(pass.name()),
+                file: Some(file.path_no_fs_interact()),
+                reproduces: after.reproduces_issue(),
+            });
+            let mut reproduces = after.reproduces_issue();
+
+            if reproduces && !committed {
+                let mut change = file.try_change(changes)?;
+                change.write_rendered(krate, rendered)?;
+                if self.commit_change(pass.name(), change, applied)? {
+                    committed = true;
+                    if has_made_change == ProcessState::FileInvalidated {
+                        invalidated_files.insert(file.path_no_fs_interact());
+                    }
+                } else {
+                    // The next attempt that reproduces can still be kept.
+                    reproduces = false;
+                }
+            }
+            results.push(Some(reproduces));
+        }
+
+        checker.batch_results(&results);
+        self.progress.candidates(checker.candidate_progress());
+
+        if self.cancel.load(Ordering::SeqCst) {
+            info!("Exiting early.");
+            std::process::exit(0);
+        }
+        Ok(())
+    }
+}
+
+/// Whether one of the files may use items of the other, so that their changes can't be built separately.
+fn related(a: &SourceFile, b: &SourceFile) -> bool {
+    let (a_path, b_path) = (a.path_no_fs_interact(), b.path_no_fs_interact());
+    modules::depends_on(a_path, &a.content(), b_path)
+        || modules::depends_on(b_path, &b.content(), a_path)
+}
+
+/// Whether `path` is `dir` or somewhere inside of it.
+fn same_or_inside(path: &Path, dir: &Path) -> bool {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_owned());
+    canonical(path).starts_with(canonical(dir))
+}
+
 macro_rules! tracking {
     () => {
         tracking!(visit_item_fn_mut);
@@ -299,3 +1241,1117 @@ macro_rules! tracking {
     };
 }
 pub(crate) use tracking;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::{atomic::AtomicBool, Arc},
+        time::Duration,
+    };
+
+    use anyhow::Result;
+    use quote::ToTokens;
+
+    use super::{files::Changes, Minimizer, Pass, SourceFile};
+    use crate::{
+        build::Build,
+        passes::{Privatize, StatementDeleter},
+        CargoTarget, Options,
+    };
+
+    fn collect_files(options: Options) -> Result<Vec<PathBuf>> {
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let mut files = minimizer
+            .files
+            .iter()
+            .map(|file| file.path_no_fs_interact().to_owned())
+            .collect::<Vec<_>>();
+        files.sort();
+        Ok(files)
+    }
+
+    #[test]
+    fn skips_target_and_ignored_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("target"))?;
+        fs::write(src.join("main.rs"), "fn main() {}")?;
+        fs::write(src.join("generated.rs"), "fn generated() {}")?;
+        fs::write(src.join("target").join("build.rs"), "fn build() {}")?;
+        fs::write(src.join(".gitignore"), "generated.rs\n")?;
+
+        let options = Options {
+            path: src.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+
+        assert_eq!(collect_files(options.clone())?, [src.join("main.rs")]);
+
+        assert_eq!(
+            collect_files(Options {
+                no_ignore: true,
+                ..options
+            })?,
+            [
+                src.join("generated.rs"),
+                src.join("main.rs"),
+                src.join("target").join("build.rs"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_stay_in_project_dir() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let outside = tempfile::tempdir()?;
+        fs::write(
+            outside.path().join("outside.rs"),
+            "pub fn outside() {\n    1;\n}\n",
+        )?;
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        let shared = dir.path().join("shared");
+        fs::create_dir_all(&src)?;
+        fs::create_dir_all(&shared)?;
+        fs::write(src.join("main.rs"), "fn main() {}")?;
+        fs::write(shared.join("shared.rs"), "pub fn shared() {\n    2;\n}\n")?;
+        symlink(outside.path().join("outside.rs"), src.join("outside.rs"))?;
+        symlink(src.join("main.rs"), src.join("again.rs"))?;
+        symlink(shared.join("shared.rs"), src.join("shared.rs"))?;
+
+        let options = Options {
+            path: src.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        assert_eq!(
+            collect_files(options.clone())?,
+            [
+                shared.canonicalize()?.join("shared.rs"),
+                src.join("main.rs"),
+            ]
+        );
+
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+        assert_eq!(
+            fs::read_to_string(outside.path().join("outside.rs"))?,
+            "pub fn outside() {\n    1;\n}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(shared.join("shared.rs"))?,
+            crate::formatting::format(syn::parse_str("pub fn shared() {}")?)?
+        );
+        assert!(fs::symlink_metadata(src.join("shared.rs"))?.is_symlink());
+
+        Ok(())
+    }
+
+    #[test]
+    fn skips_excluded_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("generated"))?;
+        fs::write(src.join("main.rs"), "mod generated;\nfn main() {}\n")?;
+        fs::write(src.join("generated.rs"), "mod a;\n")?;
+        fs::write(
+            src.join("generated").join("a.rs"),
+            "pub fn a() {\n    1;\n}\n",
+        )?;
+
+        let options = Options {
+            path: src.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(PathBuf::from("true")),
+            exclude: vec!["src/generated/*.rs".to_owned()],
+            ..Options::default()
+        };
+
+        assert_eq!(
+            collect_files(options.clone())?,
+            [src.join("generated.rs"), src.join("main.rs")]
+        );
+
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([Privatize::default().boxed(), StatementDeleter.boxed()])?;
+        assert_eq!(
+            fs::read_to_string(src.join("generated").join("a.rs"))?,
+            "pub fn a() {\n    1;\n}\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_collects_included_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("main.rs"), "mod index;\nfn main() {\n    1;\n}\n")?;
+        fs::write(src.join("index.rs"), "pub fn index() {\n    2;\n}\n")?;
+
+        let options = Options {
+            path: src.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(PathBuf::from("true")),
+            include: vec!["src/index.rs".to_owned()],
+            ..Options::default()
+        };
+
+        assert_eq!(collect_files(options.clone())?, [src.join("index.rs")]);
+
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+        assert_eq!(
+            fs::read_to_string(src.join("main.rs"))?,
+            "mod index;\nfn main() {\n    1;\n}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(src.join("index.rs"))?,
+            crate::formatting::format(syn::parse_str("pub fn index() {}")?)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn count_privatize_candidates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        fs::write(
+            &file,
+            "pub struct A { pub a: u8, b: u8 }\npub fn f() {}\nfn main() {}\n",
+        )?;
+
+        let options = Options {
+            path: file.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+
+        let counts = minimizer.count_candidates(&mut Privatize::default())?;
+        let counts = counts
+            .iter()
+            .map(|(file, count)| (file.path_no_fs_interact(), *count))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [(file.as_path(), 3)]);
+        // Nothing was written.
+        assert!(fs::read_to_string(&file)?.starts_with("pub struct A"));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn verify_loop_rejects_flaky_reproduction() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        // The original code always reproduces, changed code only reproduces every other time.
+        fs::write(
+            &script,
+            "#!/bin/sh\n\
+             grep -q original main.rs && exit 0\n\
+             n=$(($(cat count 2>/dev/null || echo 0) + 1))\n\
+             echo $n > count\n\
+             [ $((n % 2)) = 1 ]\n",
+        )?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+
+        let minimize = |verify_loop| -> Result<String> {
+            fs::write(&file, "fn main() {\n    \"original\";\n}\n")?;
+            let _ = fs::remove_file(dir.path().join("count"));
+            let options = Options {
+                path: file.clone(),
+                project_dir: Some(dir.path().to_owned()),
+                script_path: Some(script.clone()),
+                verify_loop,
+                ..Options::default()
+            };
+            let build = Build::new(&options)?;
+            let minimizer =
+                Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+            minimizer.run_passes([StatementDeleter.boxed()])?;
+            Ok(fs::read_to_string(&file)?)
+        };
+
+        assert!(!minimize(1)?.contains("original"));
+        assert!(minimize(2)?.contains("original"));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn initial_build_explains_failure() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        fs::write(
+            &script,
+            "#!/bin/sh\necho 'error: no such file' >&2\nexit 3\n",
+        )?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        fs::write(&file, "fn main() {}\n")?;
+
+        let options = Options {
+            path: file,
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let err = minimizer
+            .run_passes([StatementDeleter.boxed()])
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            err.contains("Initial build must reproduce the issue"),
+            "{err}"
+        );
+        assert!(err.contains("Expected exit code 0."), "{err}");
+        assert!(err.contains("exit status: 3"), "{err}");
+        assert!(err.contains("stderr:\nerror: no such file"), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn cancelling_kills_build() -> Result<()> {
+        use std::{os::unix::fs::PermissionsExt, sync::atomic::Ordering, time::Instant};
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        fs::write(&script, "#!/bin/sh\nsleep 60\n")?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        fs::write(&file, "fn main() {\n    1;\n}\n")?;
+
+        let options = Options {
+            path: file.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::clone(&cancel))?;
+
+        let start = Instant::now();
+        let setter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            cancel.store(true, Ordering::SeqCst);
+        });
+        let err = minimizer
+            .run_passes([StatementDeleter.boxed()])
+            .unwrap_err();
+        setter.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(
+            err.to_string().contains("Initial build was cancelled"),
+            "{err}"
+        );
+        assert_eq!(fs::read_to_string(&file)?, "fn main() {\n    1;\n}\n");
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn process_file_uses_cached_file() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        fs::write(&script, "#!/bin/sh\ngrep -q needed main.rs\n")?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        fs::write(
+            &file,
+            "fn main() {\n    1;\n    2;\n    \"needed\";\n    3;\n    4;\n}\n",
+        )?;
+
+        let options = Options {
+            path: file.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        // The passes work on the file that was parsed when it was opened, so they never see this.
+        fs::write(&file, "this is not rust, but it is needed")?;
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+
+        assert_eq!(
+            fs::read_to_string(&file)?,
+            "fn main() {\n    \"needed\";\n}\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn parallel_bisection_matches_sequential() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let minimize = |bisect_jobs| -> Result<String> {
+            let dir = tempfile::tempdir()?;
+            let file = dir.path().join("main.rs");
+            let script = dir.path().join("script.sh");
+            fs::write(
+                &script,
+                "#!/bin/sh\ngrep -q needed_a main.rs && grep -q needed_b main.rs\n",
+            )?;
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+            let statements = (0..20)
+                .map(|n| match n {
+                    5 => "    \"needed_a\";\n".to_owned(),
+                    13 => "    \"needed_b\";\n".to_owned(),
+                    n => format!("    {n};\n"),
+                })
+                .collect::<String>();
+            fs::write(&file, format!("fn main() {{\n{statements}}}\n"))?;
+
+            let options = Options {
+                path: file.clone(),
+                project_dir: Some(dir.path().to_owned()),
+                script_path: Some(script),
+                bisect_jobs,
+                ..Options::default()
+            };
+            let build = Build::new(&options)?;
+            let minimizer =
+                Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+            minimizer.run_passes([StatementDeleter.boxed()])?;
+            Ok(fs::read_to_string(&file)?)
+        };
+
+        let sequential = minimize(1)?;
+        assert_eq!(sequential.matches(';').count(), 2);
+        assert_eq!(minimize(4)?, sequential);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn parallel_files_match_sequential() -> Result<()> {
+        use std::{os::unix::fs::PermissionsExt, time::Instant};
+
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let minimize = |file_jobs| -> Result<(Vec<String>, Duration)> {
+            let dir = tempfile::tempdir()?;
+            let src = dir.path().join("src");
+            fs::create_dir(&src)?;
+            let script = dir.path().join("script.sh");
+            // Either `a` or `b` has to keep its needed statement, so deleting both at once doesn't reproduce.
+            let checks = names[2..]
+                .iter()
+                .map(|name| format!("grep -q needed src/{name}.rs && "))
+                .collect::<String>();
+            fs::write(
+                &script,
+                format!(
+                    "#!/bin/sh\nsleep 0.1\n\
+                     {checks}{{ grep -q needed src/a.rs || grep -q needed src/b.rs; }}\n"
+                ),
+            )?;
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+            let modules = names
+                .iter()
+                .map(|name| format!("mod {name};\n"))
+                .collect::<String>();
+            fs::write(src.join("main.rs"), format!("{modules}fn main() {{}}\n"))?;
+            for (n, name) in names.iter().enumerate() {
+                let statements = (0..12)
+                    .map(|i| match i == 5 * n % 12 {
+                        true => "    \"needed\";\n".to_owned(),
+                        false => format!("    {i};\n"),
+                    })
+                    .collect::<String>();
+                fs::write(
+                    src.join(format!("{name}.rs")),
+                    format!("pub fn f() {{\n{statements}}}\n"),
+                )?;
+            }
+
+            let options = Options {
+                path: src.clone(),
+                project_dir: Some(dir.path().to_owned()),
+                script_path: Some(script),
+                file_jobs,
+                ..Options::default()
+            };
+            let build = Build::new(&options)?;
+            let minimizer =
+                Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+            let start = Instant::now();
+            minimizer.run_passes([StatementDeleter.boxed()])?;
+            let elapsed = start.elapsed();
+            let files = names
+                .iter()
+                .chain(&["main"])
+                .map(|name| fs::read_to_string(src.join(format!("{name}.rs"))))
+                .collect::<Result<_, _>>()?;
+            Ok((files, elapsed))
+        };
+
+        let (sequential, sequential_time) = minimize(1)?;
+        assert_eq!(sequential[0], "pub fn f() { }\n");
+        for file in &sequential[1..names.len()] {
+            assert_eq!(file, "pub fn f() {\n    \"needed\";\n}\n");
+        }
+        let (parallel, parallel_time) = minimize(8)?;
+        assert_eq!(parallel, sequential);
+        // The files besides `main.rs` don't use each other, so their candidates are built at the same time.
+        assert!(
+            parallel_time * 3 < sequential_time * 2,
+            "{parallel_time:?} with --file-jobs, {sequential_time:?} without"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rustc_with_extra_args() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        fs::write(
+            &file,
+            "pub fn f() {\n    let unused = 1;\n    let a = 2;\n    let _ = a;\n}\n",
+        )?;
+
+        let options = Options {
+            path: file.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            rustc: true,
+            // Without `--crate-type=lib`, the missing `main` would be an error and nothing would be emitted.
+            extra_args: Some("--emit=metadata --crate-type=lib".to_owned()),
+            regex: Some(regex::Regex::new("unused variable: `unused`")?),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+
+        let minimized = fs::read_to_string(&file)?;
+        assert!(minimized.contains("let unused = 1;"));
+        assert!(!minimized.contains("let a"));
+        assert!(dir.path().join("libmain.rmeta").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn replay_change_log() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        let log = dir.path().join("changes.jsonl");
+        fs::write(&script, "#!/bin/sh\ngrep -q needed main.rs\n")?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        let original = "pub fn f() {\n    1;\n    \"needed\";\n    2;\n}\nfn main() {\n    3;\n}\n";
+        fs::write(&file, original)?;
+
+        let options = Options {
+            path: file.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            change_log: Some(log.clone()),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([StatementDeleter.boxed(), Privatize::default().boxed()])?;
+        let minimized = fs::read_to_string(&file)?;
+        assert_ne!(minimized, original);
+
+        fs::write(&file, original)?;
+        // Replaying never builds, so it doesn't matter that this never reproduces.
+        let options = Options {
+            path: file.clone(),
+            script_path: Some(PathBuf::from("false")),
+            replay: Some(log),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.replay(minimizer.options.replay.as_ref().unwrap())?;
+        assert_eq!(fs::read_to_string(&file)?, minimized);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn runs_are_reproducible() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        let script = dir.path().join("script.sh");
+        fs::create_dir_all(&src)?;
+        fs::write(
+            &script,
+            "#!/bin/sh
+grep -q needed src/c.rs
+",
+        )?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        let originals = ["a.rs", "b.rs", "c.rs", "d.rs", "main.rs"].map(|name| {
+            let content = format!(
+                "pub fn f() {{\n    1;\n    \"needed\";\n    2;\n}}\npub fn g() {{\n    \"{name}\";\n}}\n"
+            );
+            (src.join(name), content)
+        });
+
+        let minimize = |log: PathBuf| -> Result<(Vec<String>, String)> {
+            for (path, content) in &originals {
+                fs::write(path, content)?;
+            }
+            let options = Options {
+                path: src.clone(),
+                project_dir: Some(dir.path().to_owned()),
+                script_path: Some(script.clone()),
+                change_log: Some(log.clone()),
+                ..Options::default()
+            };
+            let build = Build::new(&options)?;
+            let minimizer =
+                Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+            minimizer.run_passes([StatementDeleter.boxed(), Privatize::default().boxed()])?;
+            let files = originals
+                .iter()
+                .map(|(path, _)| fs::read_to_string(path))
+                .collect::<Result<_, _>>()?;
+            Ok((files, fs::read_to_string(log)?))
+        };
+
+        let first = minimize(dir.path().join("first.jsonl"))?;
+        let second = minimize(dir.path().join("second.jsonl"))?;
+        assert!(first.0[2].contains("needed"));
+        assert!(!first.0[0].contains("needed"));
+        assert_eq!(first, second);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deleting_module_deletes_its_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("unused"))?;
+        fs::write(
+            src.join("main.rs"),
+            "mod unused;\nmod used;\nfn main() {}\n",
+        )?;
+        fs::write(src.join("used.rs"), "")?;
+        fs::write(
+            src.join("unused.rs"),
+            "mod nested;\ninclude!(\"../included.rs\");\n",
+        )?;
+        fs::write(src.join("unused").join("nested.rs"), "fn nested() {}\n")?;
+        fs::write(dir.path().join("included.rs"), "fn included() {}\n")?;
+
+        let options = Options {
+            path: src.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let file = minimizer
+            .files
+            .iter()
+            .find(|file| file.path_no_fs_interact().ends_with("main.rs"))
+            .unwrap();
+        let mut changes = Changes::default();
+        let mut change = file.try_change(&mut changes)?;
+        change.write(syn::parse_str("mod used; fn main() {}")?)?;
+        minimizer.commit_change("item-deleter", change, Vec::new())?;
+
+        assert!(src.join("used.rs").exists());
+        assert!(!src.join("unused.rs").exists());
+        assert!(!src.join("unused").join("nested.rs").exists());
+        // Not a file that is being minimized.
+        assert!(dir.path().join("included.rs").exists());
+        assert_eq!(
+            minimizer
+                .files
+                .iter()
+                .filter(|file| file.is_deleted())
+                .count(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn proc_macro_crate_with_build_script() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        use crate::passes::{EverybodyLoops, ItemDeleter};
+
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("script.sh");
+        fs::write(
+            &script,
+            "#!/bin/sh\ngrep -q 'pub fn m' src/lib.rs && grep -q 'fn main' build.rs\n",
+        )?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"pm\"\nversion = \"0.1.0\"\n[lib]\nproc-macro = true\n",
+        )?;
+        fs::write(
+            dir.path().join("build.rs"),
+            "fn main() {\n    let _ = 1;\n}\n",
+        )?;
+        fs::write(
+            src.join("lib.rs"),
+            "extern crate proc_macro;\n\
+             use proc_macro::TokenStream;\n\
+             #[proc_macro]\n\
+             pub fn m(input: TokenStream) -> TokenStream {\n    helper();\n    input\n}\n\
+             fn helper() {}\n",
+        )?;
+
+        let options = Options {
+            path: src.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        assert_eq!(minimizer.files.len(), 2);
+        assert!(minimizer.files.iter().all(SourceFile::runs_at_build_time));
+
+        minimizer.run_passes([EverybodyLoops.boxed(), ItemDeleter.boxed()])?;
+
+        // The bodies must not hang the build.
+        let lib = fs::read_to_string(src.join("lib.rs"))?;
+        assert!(lib.contains("panic!()"), "{lib}");
+        assert!(!lib.contains("helper"), "{lib}");
+        let build_script = fs::read_to_string(dir.path().join("build.rs"))?;
+        assert!(build_script.contains("panic!()"), "{build_script}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn minimizes_test_target() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        let tests = dir.path().join("tests");
+        fs::create_dir_all(&src)?;
+        fs::create_dir_all(&tests)?;
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"only_in_tests\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )?;
+        fs::write(src.join("lib.rs"), "pub fn answer() -> u32 {\n    42\n}\n")?;
+        fs::write(
+            tests.join("repro.rs"),
+            "#[test]\nfn repro() {\n    let unrelated = 1;\n    assert_eq!(only_in_tests::answer(), 41);\n}\n",
+        )?;
+
+        // `cargo build` doesn't even build the test, only running it panics.
+        let options = Options {
+            path: src.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            cargo_subcmd: "test".to_owned(),
+            cargo_target: Some(CargoTarget::Test("repro".to_owned())),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        assert_eq!(minimizer.files.len(), 2);
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+
+        let test = fs::read_to_string(tests.join("repro.rs"))?;
+        assert!(!test.contains("unrelated"), "{test}");
+        assert!(test.contains("assert_eq!"), "{test}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn flatten_crate() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("a"))?;
+        fs::write(src.join("main.rs"), "mod a;\nfn main() {}\n")?;
+        fs::write(src.join("a.rs"), "#![allow(unused)]\nmod b;\nfn a() {}\n")?;
+        fs::write(src.join("a").join("b.rs"), "fn needed() {}\n")?;
+
+        let options = Options {
+            path: src.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.flatten_crate()?;
+
+        assert!(!src.join("a.rs").exists());
+        assert!(!src.join("a").join("b.rs").exists());
+        let main = fs::read_to_string(src.join("main.rs"))?;
+        assert!(main.contains("mod a {"), "{main}");
+        assert!(main.contains("#![allow(unused)]"), "{main}");
+        assert!(main.contains("mod b {"), "{main}");
+        assert!(main.contains("fn needed()"), "{main}");
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn json_events() -> Result<()> {
+        use std::{cell::RefCell, io::Write, os::unix::fs::PermissionsExt, rc::Rc};
+
+        use super::events::Events;
+
+        #[derive(Clone, Default)]
+        struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+        impl Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        fs::write(&script, "#!/bin/sh\ngrep -q needed main.rs\n")?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        fs::write(&file, "fn main() {\n    1;\n    \"needed\";\n}\n")?;
+
+        let options = Options {
+            path: file.clone(),
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let mut minimizer =
+            Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let buffer = Buffer::default();
+        minimizer.events = Events::to_writer(Box::new(buffer.clone()));
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+
+        let output = String::from_utf8(buffer.0.take())?;
+        let events = output
+            .lines()
+            .map(|line| {
+                let event = serde_json::from_str::<serde_json::Value>(line)?;
+                Ok(event["event"].as_str().unwrap_or_default().to_owned())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Deleting both statements doesn't reproduce, so they are tried one by one. The second round
+        // only tries the needed statement again.
+        assert_eq!(
+            events,
+            [
+                "build",
+                "pass-started",
+                "build",
+                "change-rolled-back",
+                "build",
+                "change-committed",
+                "build",
+                "change-rolled-back",
+                "file-processed",
+                "build",
+                "change-rolled-back",
+                "file-processed",
+                "pass-finished",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stops_pass_that_never_converges() -> Result<()> {
+        use crate::processor::{PassController, ProcessState, SourceFile};
+
+        /// Makes the name of the function longer, forever.
+        struct Grow;
+
+        impl Pass for Grow {
+            fn process_file(
+                &mut self,
+                krate: &mut syn::File,
+                _: &SourceFile,
+                checker: &mut PassController,
+            ) -> ProcessState {
+                if !checker.can_process(&["grow".to_owned()]) {
+                    return ProcessState::NoChange;
+                }
+                let syn::Item::Fn(func) = &mut krate.items[0] else {
+                    unreachable!()
+                };
+                let name = format!("{}a", func.sig.ident);
+                func.sig.ident = syn::Ident::new(&name, func.sig.ident.span());
+                ProcessState::Changed
+            }
+
+            fn name(&self) -> &'static str {
+                "grow"
+            }
+        }
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        fs::write(&file, "fn a() {}\n")?;
+
+        let options = Options {
+            path: file.clone(),
+            script_path: Some(PathBuf::from("true")),
+            max_rounds_without_progress: 3,
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([Grow.boxed()])?;
+
+        // None of the renames make the file smaller, so it is skipped after three rounds: a -> aa -> aaa -> aaaa.
+        let krate = syn::parse_file(&fs::read_to_string(&file)?)?;
+        assert_eq!(krate.into_token_stream().to_string(), "fn aaaa () { }");
+
+        Ok(())
+    }
+
+    #[test]
+    fn detects_pass_going_in_circles() -> Result<()> {
+        use crate::processor::{PassController, ProcessState, SourceFile};
+
+        /// Renames `a` to `b` and back, forever.
+        struct Toggle;
+
+        impl Pass for Toggle {
+            fn process_file(
+                &mut self,
+                krate: &mut syn::File,
+                _: &SourceFile,
+                checker: &mut PassController,
+            ) -> ProcessState {
+                if !checker.can_process(&["toggle".to_owned()]) {
+                    return ProcessState::NoChange;
+                }
+                let syn::Item::Fn(func) = &mut krate.items[0] else {
+                    unreachable!()
+                };
+                let name = if func.sig.ident == "a" { "b" } else { "a" };
+                func.sig.ident = syn::Ident::new(name, func.sig.ident.span());
+                ProcessState::Changed
+            }
+
+            fn name(&self) -> &'static str {
+                "toggle"
+            }
+        }
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        fs::write(&file, "fn a() {}\n")?;
+
+        let options = Options {
+            path: file.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let err = minimizer
+            .run_passes([Toggle.boxed()])
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.starts_with("toggle is stuck in a loop on"), "{err}");
+        assert!(err.contains("main.rs"), "{err}");
+        assert!(
+            err.contains("the file after round 2 is the same as before the pass"),
+            "{err}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalidation_requeues_dependent_files() -> Result<()> {
+        use std::{cell::RefCell, rc::Rc};
+
+        use crate::{
+            processor::{PassController, ProcessState, SourceFile},
+            FileOrder,
+        };
+
+        /// Deletes the last function of `a.rs` once, which invalidates what it knows about the files.
+        struct DeleteOnce {
+            log: Rc<RefCell<Vec<String>>>,
+            deleted: bool,
+        }
+
+        impl Pass for DeleteOnce {
+            fn refresh_state(&mut self) -> Result<()> {
+                self.log.borrow_mut().push("refresh".to_owned());
+                Ok(())
+            }
+
+            fn process_file(
+                &mut self,
+                krate: &mut syn::File,
+                file: &SourceFile,
+                checker: &mut PassController,
+            ) -> ProcessState {
+                let name = file.path_no_fs_interact().file_name().unwrap();
+                self.log
+                    .borrow_mut()
+                    .push(name.to_string_lossy().into_owned());
+                if name != "a.rs" || self.deleted || !checker.can_process(&["g".to_owned()]) {
+                    return ProcessState::NoChange;
+                }
+                self.deleted = true;
+                krate.items.pop();
+                ProcessState::FileInvalidated
+            }
+
+            fn name(&self) -> &'static str {
+                "delete-once"
+            }
+        }
+
+        let dir = tempfile::tempdir()?;
+        fs::write(
+            dir.path().join("main.rs"),
+            "mod a;\nfn main() {\n    a::f();\n}\n",
+        )?;
+        // Larger than `main.rs`, so that it is processed first.
+        fs::write(
+            dir.path().join("a.rs"),
+            "pub fn f() {}\npub fn unused() {}\npub fn g() {}\n",
+        )?;
+
+        let options = Options {
+            path: dir.path().to_owned(),
+            script_path: Some(PathBuf::from("true")),
+            file_order: FileOrder::Largest,
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let log = Rc::new(RefCell::new(Vec::new()));
+        minimizer.run_passes([DeleteOnce {
+            log: Rc::clone(&log),
+            deleted: false,
+        }
+        .boxed()])?;
+
+        // `main.rs` uses `a`, so it waits for the state to be refreshed.
+        assert_eq!(*log.borrow(), ["a.rs", "refresh", "a.rs", "main.rs"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn summary_times_passes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        fs::write(&file, "pub fn f() {\n    let a = 1;\n}\n")?;
+
+        let options = Options {
+            path: file.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([StatementDeleter.boxed(), Privatize::default().boxed()])?;
+
+        let timings = minimizer.summary().timings;
+        let passes = timings.iter().map(|&(pass, _)| pass).collect::<Vec<_>>();
+        assert_eq!(passes, ["delete-statements", "privatize"]);
+        for (pass, timing) in timings {
+            assert!(timing.transform > Duration::ZERO, "{pass}: {timing:?}");
+            assert!(timing.build > Duration::ZERO, "{pass}: {timing:?}");
+            assert!(
+                timing.total >= timing.transform + timing.build,
+                "{pass}: {timing:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn summary_counts_lines() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        fs::write(
+            &file,
+            "fn main() {\n    let a = 1;\n    let b = 2;\n}\n\nfn unused() {}\n",
+        )?;
+
+        let options = Options {
+            path: file.clone(),
+            script_path: Some(PathBuf::from("true")),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+
+        let after = fs::read_to_string(&file)?;
+        let summary = minimizer.summary();
+        let (before_total, after_total) = summary.total();
+        assert_eq!(before_total.lines, 6);
+        assert_eq!(after_total.lines, after.lines().count());
+        assert_eq!(before_total.items, 2);
+        assert_eq!(after_total.items, 2);
+        assert_eq!(
+            summary.most_effective_pass(),
+            Some(("delete-statements", 6 - after.lines().count() as isize))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+//! Finds the files that a file pulls in with `mod foo;` and `include!`. These files are separate `SourceFile`s,
+//! so when their declaration is deleted, they have to be deleted too. For `flatten-crate`, the files of the
+//! module declarations are turned into inline modules. It also guesses which files use the items of a file,
+//! so that the state that a pass has about them is refreshed when the file changes.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use syn::{visit_mut::VisitMut, ItemMod, LitStr, Macro, Meta, UsePath};
+
+/// Returns the files that the module declarations and `include!`s in the file refer to, if they exist.
+pub(crate) fn referenced_files(path: &Path, krate: &syn::File) -> BTreeSet<PathBuf> {
+    let mut krate = krate.clone();
+    let Some(mut visitor) = Visitor::new(path, None) else {
+        return BTreeSet::new();
+    };
+    visitor.visit_file_mut(&mut krate);
+    visitor.referenced
+}
+
+/// Returns the AST of the file at the path.
+type FileContent<'a> = &'a dyn Fn(&Path) -> Option<syn::File>;
+
+/// Replaces the module declarations in the file with inline modules that contain the items of their files,
+/// recursively. `content` returns the AST of a file, files where it returns `None` are left alone.
+/// Returns whether any module was inlined.
+pub(crate) fn inline_module_files(
+    path: &Path,
+    krate: &mut syn::File,
+    content: FileContent<'_>,
+) -> bool {
+    let Some(mut visitor) = Visitor::new(path, Some(content)) else {
+        return false;
+    };
+    visitor.visit_file_mut(krate);
+    visitor.inlined
+}
+
+/// Whether the file at `dependent` may use items of the file at `path`: it declares the module of the file,
+/// or mentions the name of the module in a path or import. Without name resolution, this is a guess that
+/// errs on the side of finding too many files.
+pub(crate) fn depends_on(dependent: &Path, krate: &syn::File, path: &Path) -> bool {
+    if referenced_files(dependent, krate)
+        .iter()
+        .any(|referenced| same_file(referenced, path))
+    {
+        return true;
+    }
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some("main.rs" | "lib.rs") => "crate",
+        Some("mod.rs") => match path.parent().and_then(Path::file_name) {
+            Some(dir) => dir.to_str().unwrap_or_default(),
+            None => return false,
+        },
+        _ => match path.file_stem() {
+            Some(stem) => stem.to_str().unwrap_or_default(),
+            None => return false,
+        },
+    };
+    let mut mentions = Mentions { name, found: false };
+    mentions.visit_file_mut(&mut krate.clone());
+    mentions.found
+}
+
+/// Finds a path or import that goes through the module with the name.
+struct Mentions<'a> {
+    name: &'a str,
+    found: bool,
+}
+
+impl VisitMut for Mentions<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        // The last segment is the item itself, not a module.
+        let modules = path.segments.len().saturating_sub(1);
+        if path
+            .segments
+            .iter()
+            .take(modules)
+            .any(|segment| segment.ident == self.name)
+        {
+            self.found = true;
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_use_path_mut(&mut self, path: &mut UsePath) {
+        if path.ident == self.name {
+            self.found = true;
+        }
+        syn::visit_mut::visit_use_path_mut(self, path);
+    }
+}
+
+/// Whether the paths point to the same file.
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+struct Visitor<'a> {
+    /// `include!` and `#[path]` on top level modules are relative to the directory of the file.
+    file_dir: &'a Path,
+    /// The directory of the current module, where the files of its submodules are.
+    module_dir: PathBuf,
+    /// `#[path]` in inline modules is relative to the directory of the inline module.
+    in_inline_module: bool,
+    referenced: BTreeSet<PathBuf>,
+    /// Set for `inline_module_files`.
+    content: Option<FileContent<'a>>,
+    inlined: bool,
+}
+
+impl<'a> Visitor<'a> {
+    fn new(path: &'a Path, content: Option<FileContent<'a>>) -> Option<Self> {
+        let parent = path.parent()?;
+        let is_mod_rs = path
+            .file_name()
+            .is_some_and(|name| name == "mod.rs" || name == "main.rs" || name == "lib.rs");
+        let module_dir = match path.file_stem() {
+            Some(stem) if !is_mod_rs => parent.join(stem),
+            _ => parent.to_owned(),
+        };
+
+        Some(Visitor {
+            file_dir: parent,
+            module_dir,
+            in_inline_module: false,
+            referenced: BTreeSet::new(),
+            content,
+            inlined: false,
+        })
+    }
+
+    fn path_attr(module: &ItemMod) -> Option<String> {
+        module
+            .attrs
+            .iter()
+            .find_map(|attr| match attr.parse_meta() {
+                Ok(Meta::NameValue(meta)) if meta.path.is_ident("path") => match meta.lit {
+                    syn::Lit::Str(path) => Some(path.value()),
+                    _ => None,
+                },
+                _ => None,
+            })
+    }
+
+    fn add_if_exists(&mut self, path: PathBuf) {
+        if path.is_file() {
+            self.referenced.insert(path);
+        }
+    }
+
+    /// The file of a module declaration, if it exists.
+    fn module_file(&self, module: &ItemMod) -> Option<PathBuf> {
+        let existing = |path: PathBuf| path.is_file().then_some(path);
+        match Self::path_attr(module) {
+            Some(path) => {
+                let dir = if self.in_inline_module {
+                    &self.module_dir
+                } else {
+                    self.file_dir
+                };
+                existing(dir.join(path))
+            }
+            None => {
+                let ident = module.ident.to_string();
+                existing(self.module_dir.join(format!("{ident}.rs")))
+                    .or_else(|| existing(self.module_dir.join(&ident).join("mod.rs")))
+            }
+        }
+    }
+
+    /// Turns the module declaration into an inline module with the items of the file.
+    fn inline(&mut self, module: &mut ItemMod, path: &Path) {
+        let Some(content) = self.content else {
+            return;
+        };
+        let Some(mut file) = content(path) else {
+            return;
+        };
+        // The declarations in the file are relative to the file, so they are inlined before they move.
+        inline_module_files(path, &mut file, content);
+
+        module.attrs.retain(|attr| !attr.path.is_ident("path"));
+        // The inner attributes of the file become inner attributes of the module.
+        module.attrs.extend(file.attrs);
+        module.content = Some((Default::default(), file.items));
+        module.semi = None;
+        self.inlined = true;
+    }
+}
+
+impl VisitMut for Visitor<'_> {
+    fn visit_item_mod_mut(&mut self, module: &mut ItemMod) {
+        let path_attr = Self::path_attr(module);
+        let ident = module.ident.to_string();
+
+        if module.content.is_none() {
+            if let Some(path) = self.module_file(module) {
+                self.inline(module, &path);
+                self.referenced.insert(path);
+            }
+            return;
+        }
+
+        let outer = self.module_dir.clone();
+        let outer_inline = std::mem::replace(&mut self.in_inline_module, true);
+        self.module_dir = match path_attr {
+            Some(path) => self.module_dir.join(path),
+            None => self.module_dir.join(&ident),
+        };
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.module_dir = outer;
+        self.in_inline_module = outer_inline;
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        if mac.path.is_ident("include") || mac.path.is_ident("include_str") {
+            if let Ok(path) = mac.parse_body::<LitStr>() {
+                self.add_if_exists(self.file_dir.join(path.value()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::referenced_files;
+
+    #[test]
+    fn finds_module_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path();
+        fs::create_dir_all(src.join("b"))?;
+        fs::create_dir_all(src.join("inline"))?;
+        fs::create_dir_all(src.join("a"))?;
+        for file in [
+            "a.rs",
+            "a/nested.rs",
+            "b/mod.rs",
+            "inline/c.rs",
+            "other.rs",
+            "included.rs",
+        ] {
+            fs::write(src.join(file), "")?;
+        }
+
+        let main = syn::parse_str(
+            r#"mod a; mod b; mod inline { mod c; } #[path = "other.rs"] mod renamed; mod missing;
+               include!("included.rs");"#,
+        )?;
+        assert_eq!(
+            referenced_files(&src.join("main.rs"), &main),
+            [
+                src.join("a.rs"),
+                src.join("b/mod.rs"),
+                src.join("included.rs"),
+                src.join("inline/c.rs"),
+                src.join("other.rs"),
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        let a = syn::parse_str("mod nested;")?;
+        assert_eq!(
+            referenced_files(&src.join("a.rs"), &a),
+            [src.join("a/nested.rs")].into_iter().collect()
+        );
+
+        Ok(())
+    }
+}
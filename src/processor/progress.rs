@@ -0,0 +1,189 @@
+//! A progress bar for interactive terminals. It shows the current pass, how many files the current round
+//! has finished and how many candidates of the current file have been tried. While it is shown, the line
+//! that is logged for every build is only logged at the debug level.
+
+use std::{
+    cell::RefCell,
+    io::{IsTerminal, Write},
+};
+
+use crate::{Options, OutputFormat};
+
+const BAR_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ProgressState {
+    pub(crate) pass: &'static str,
+    pub(crate) files_done: usize,
+    pub(crate) files_total: usize,
+    pub(crate) candidates_tried: usize,
+    pub(crate) candidates_remaining: usize,
+}
+
+type Callback = Box<dyn FnMut(&ProgressState)>;
+
+pub(crate) struct ProgressBar {
+    state: RefCell<ProgressState>,
+    callback: Option<RefCell<Callback>>,
+}
+
+impl std::fmt::Debug for ProgressBar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressBar")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+impl ProgressBar {
+    /// The progress bar is drawn to stderr if it is a terminal. It is not shown with `--no-progress` or
+    /// `--output-format json`, plain logs work better there.
+    pub(crate) fn new(options: &Options) -> Self {
+        let enabled = !options.no_progress
+            && options.output_format == OutputFormat::Human
+            && std::io::stderr().is_terminal();
+        if enabled {
+            Self::with_callback(draw)
+        } else {
+            Self {
+                state: RefCell::default(),
+                callback: None,
+            }
+        }
+    }
+
+    pub(crate) fn with_callback(callback: impl FnMut(&ProgressState) + 'static) -> Self {
+        Self {
+            state: RefCell::default(),
+            callback: Some(RefCell::new(Box::new(callback))),
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.callback.is_some()
+    }
+
+    fn update(&self, update: impl FnOnce(&mut ProgressState)) {
+        let Some(callback) = &self.callback else {
+            return;
+        };
+        let mut state = self.state.borrow_mut();
+        update(&mut state);
+        (callback.borrow_mut())(&state);
+    }
+
+    /// A new round of the pass starts.
+    pub(crate) fn start_round(&self, pass: &'static str, files_total: usize) {
+        self.update(|state| {
+            *state = ProgressState {
+                pass,
+                files_total,
+                ..ProgressState::default()
+            }
+        });
+    }
+
+    pub(crate) fn candidates(&self, (tried, remaining): (usize, usize)) {
+        self.update(|state| {
+            state.candidates_tried = tried;
+            state.candidates_remaining = remaining;
+        });
+    }
+
+    pub(crate) fn file_done(&self) {
+        self.update(|state| state.files_done += 1);
+    }
+
+    /// Removes the progress bar, so that the logs after the pass start on an empty line.
+    pub(crate) fn finish(&self) {
+        if self.is_enabled() {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+fn draw(state: &ProgressState) {
+    let filled = (state.files_done * BAR_WIDTH)
+        .checked_div(state.files_total)
+        .unwrap_or(0);
+    let mut stderr = std::io::stderr().lock();
+    // Drawing the progress bar failing isn't worth stopping the minimization for.
+    let _ = write!(
+        stderr,
+        "\r\x1b[2K{} [{}{}] {}/{} files, {} candidates tried, {} left",
+        state.pass,
+        "=".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        state.files_done,
+        state.files_total,
+        state.candidates_tried,
+        state.candidates_remaining,
+    );
+    let _ = stderr.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        fs,
+        rc::Rc,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use anyhow::Result;
+
+    use super::{ProgressBar, ProgressState};
+    use crate::{
+        build::Build,
+        passes::StatementDeleter,
+        processor::{Minimizer, Pass},
+        Options,
+    };
+
+    #[test]
+    #[cfg(unix)]
+    fn reports_candidate_counts() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        fs::write(&script, "#!/bin/sh\ngrep -q needed main.rs\n")?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        fs::write(&file, "fn main() {\n    1;\n    \"needed\";\n    2;\n}\n")?;
+
+        let options = Options {
+            path: file,
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let mut minimizer =
+            Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        let states = Rc::new(RefCell::new(Vec::new()));
+        minimizer.progress = ProgressBar::with_callback({
+            let states = Rc::clone(&states);
+            move |state| states.borrow_mut().push(state.clone())
+        });
+        minimizer.run_passes([StatementDeleter.boxed()])?;
+
+        let states = states.borrow();
+        let state = |files_done, candidates_tried, candidates_remaining| ProgressState {
+            pass: "delete-statements",
+            files_done,
+            files_total: 1,
+            candidates_tried,
+            candidates_remaining,
+        };
+        assert_eq!(states[0], state(0, 0, 0));
+        // Applying all three statements at once doesn't reproduce, so the bisection starts with all of them.
+        assert!(states.contains(&state(0, 0, 3)), "{states:#?}");
+        assert!(states.contains(&state(0, 3, 0)), "{states:#?}");
+        assert!(states.contains(&state(1, 3, 0)), "{states:#?}");
+        assert_eq!(states.last(), Some(&state(1, 1, 0)));
+
+        Ok(())
+    }
+}
@@ -2,15 +2,19 @@
 
 use crate::build::Build;
 
-use super::{files::Changes, tracking, Minimizer, Pass, PassController, ProcessState, SourceFile};
+use super::{
+    events::Event, files::Changes, tracking, Minimizer, Pass, PassController, ProcessState,
+    SourceFile,
+};
 use anyhow::{Context, Result};
 use proc_macro2::Span;
 use quote::ToTokens;
 use rustfix::{diagnostics::Diagnostic, Suggestion};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashSet},
     ops::Range,
     path::{Path, PathBuf},
+    time::Instant,
 };
 use syn::{visit_mut::VisitMut, ImplItem, Item};
 
@@ -18,19 +22,11 @@ fn file_for_suggestion(suggestion: &Suggestion) -> &Path {
     Path::new(&suggestion.solutions[0].replacements[0].snippet.file_name)
 }
 
-const PASS_NAME: &str = "delete-unused-functions";
+pub(crate) const DELETE_UNUSED_FUNCTIONS: &str = "delete-unused-functions";
 
 impl Minimizer {
-    pub fn delete_dead_code(&mut self) -> Result<()> {
-        if self.pass_disabled(PASS_NAME) {
-            return Ok(());
-        }
-
-        let inital_build = self.build.build()?;
-        info!("Before reaper: {inital_build}");
-
-        inital_build.require_reproduction("Initial")?;
-
+    /// Removes the unused imports and then the unused functions that rustc reports.
+    pub(crate) fn delete_dead_code(&self) -> Result<()> {
         let (diags, suggestions) = self
             .build
             .get_diags()
@@ -38,7 +34,7 @@ impl Minimizer {
 
         debug!(?diags, "Got diagnostics");
 
-        let mut suggestions_for_file = HashMap::<_, Vec<_>>::new();
+        let mut suggestions_for_file = BTreeMap::<_, Vec<_>>::new();
         for suggestion in &suggestions {
             suggestions_for_file
                 .entry(file_for_suggestion(suggestion))
@@ -49,18 +45,13 @@ impl Minimizer {
         // Always unconditionally apply unused imports.
         self.apply_unused_imports(&suggestions_for_file)?;
 
-        self.run_passes([
-            Box::new(DeleteUnusedFunctions::new(self.build.clone(), diags)) as Box<dyn Pass>,
-        ])
-        .context("deleting unused functions")?;
+        self.run_pass(&mut DeleteUnusedFunctions::new(self.build.clone(), diags))
+            .context("deleting unused functions")?;
 
         Ok(())
     }
 
-    fn apply_unused_imports(
-        &mut self,
-        suggestions: &HashMap<&Path, Vec<&Suggestion>>,
-    ) -> Result<()> {
+    fn apply_unused_imports(&self, suggestions: &BTreeMap<&Path, Vec<&Suggestion>>) -> Result<()> {
         for (sugg_file, suggestions) in suggestions {
             let Some(file) = self.files.iter().find(|source| {
                 source.path_no_fs_interact().ends_with(sugg_file)
@@ -83,16 +74,25 @@ impl Minimizer {
             let result =
                 rustfix::apply_suggestions(change.before_content().0, &desired_suggestions)?;
             let result = syn::parse_file(&result).context("parsing file after rustfix")?;
+            self.checkpoint
+                .start_change(change.path, change.before_content().0)?;
             change.write(result)?;
 
-            let after = self.build.build()?;
+            let start = Instant::now();
+            let after = self.build.build(&self.files)?;
+            self.timing(DELETE_UNUSED_FUNCTIONS).build += start.elapsed();
 
             info!("{file:?}: After reaper: {after}");
+            self.events.emit(Event::Build {
+                pass: Some(DELETE_UNUSED_FUNCTIONS),
+                file: Some(file.path_no_fs_interact()),
+                reproduces: after.reproduces_issue(),
+            });
 
             if after.reproduces_issue() {
-                change.commit();
+                self.commit_change(DELETE_UNUSED_FUNCTIONS, change, Vec::new())?;
             } else {
-                change.rollback()?;
+                self.rollback_change(DELETE_UNUSED_FUNCTIONS, change)?;
             }
         }
 
@@ -146,7 +146,7 @@ impl Pass for DeleteUnusedFunctions {
     }
 
     fn name(&self) -> &'static str {
-        PASS_NAME
+        DELETE_UNUSED_FUNCTIONS
     }
 }
 
@@ -188,7 +188,7 @@ impl<'a> FindUnusedFunction<'a> {
                 if diag
                     .code
                     .as_ref()
-                    .map_or(false, |code| code.code != "dead_code")
+                    .is_some_and(|code| code.code != "dead_code")
                 {
                     return None;
                 }
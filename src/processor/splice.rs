@@ -0,0 +1,175 @@
+//! Writes changes into the original text of a file instead of formatting all of it, for `--preserve-formatting`.
+//! The top level items of the new file are matched up with the items of the original by their name. Items that
+//! didn't change keep their original text, including the comments and blank lines around them, so the lines of
+//! an error that depends on them stay where they were. Only the items that changed are formatted.
+
+use quote::ToTokens;
+use syn::{spanned::Spanned, Item};
+
+use crate::{edition::Edition, passes::item_name};
+
+/// What happens to an item of the original file.
+enum Edit<'a> {
+    Keep,
+    Replace(&'a Item),
+    Delete,
+}
+
+/// The new content of the file, with the original text wherever the items didn't change. `None` if it can't be
+/// done, in which case the whole file is formatted.
+pub(crate) fn splice(original: &str, new: &syn::File, edition: Edition) -> Option<String> {
+    let old = edition.parse_file(original).ok()?;
+    if old.shebang != new.shebang
+        || tokens(&old.attrs) != tokens(&new.attrs)
+        || old.items.is_empty()
+    {
+        return None;
+    }
+    let ranges = old
+        .items
+        .iter()
+        .map(|item| byte_range(original, item))
+        .collect::<Option<Vec<_>>>()?;
+
+    // The new items that go before the original item with the same index, and the ones at the end.
+    let mut edits = Vec::new();
+    let mut inserted = vec![Vec::new(); old.items.len() + 1];
+    let mut next = 0;
+    for item in &new.items {
+        let name = item_name(item);
+        let found = old.items[next..]
+            .iter()
+            .position(|old| item_name(old) == name);
+        let Some(offset) = found else {
+            inserted[next].push(item.clone());
+            continue;
+        };
+        edits.extend((0..offset).map(|_| Edit::Delete));
+        let old_item = &old.items[next + offset];
+        if old_item.to_token_stream().to_string() == item.to_token_stream().to_string() {
+            edits.push(Edit::Keep);
+        } else {
+            edits.push(Edit::Replace(item));
+        }
+        next += offset + 1;
+    }
+    edits.extend((next..old.items.len()).map(|_| Edit::Delete));
+
+    let mut out = original[..ranges[0].start].to_owned();
+    for (index, edit) in edits.into_iter().enumerate() {
+        for item in &inserted[index] {
+            out.push_str(&format_item(item)?);
+            out.push_str("\n\n");
+        }
+        let range = ranges[index].clone();
+        let gap_end = ranges
+            .get(index + 1)
+            .map_or(original.len(), |next| next.start);
+        match edit {
+            Edit::Keep => out.push_str(&original[range.start..gap_end]),
+            Edit::Replace(item) => {
+                out.push_str(&format_item(item)?);
+                out.push_str(&original[range.end..gap_end]);
+            }
+            // The whitespace after the item goes with it, comments stay.
+            Edit::Delete => out.push_str(original[range.end..gap_end].trim_start()),
+        }
+    }
+    // Deleting the last items leaves the blank lines before them behind.
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    for item in &inserted[old.items.len()] {
+        out.push_str("\n\n");
+        out.push_str(&format_item(item)?);
+    }
+    out.push('\n');
+
+    // The text has to be exactly the new file, in case the spans didn't point where they should.
+    let spliced = edition.parse_file(&out).ok()?;
+    (spliced.to_token_stream().to_string() == new.to_token_stream().to_string()).then_some(out)
+}
+
+fn tokens(attrs: &[syn::Attribute]) -> String {
+    attrs
+        .iter()
+        .map(|attr| attr.to_token_stream().to_string())
+        .collect()
+}
+
+/// The formatted item, without the newline at the end.
+fn format_item(item: &Item) -> Option<String> {
+    let file = syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item.clone()],
+    };
+    let formatted = crate::formatting::format(file).ok()?;
+    Some(formatted.trim_end().to_owned())
+}
+
+/// Where the item is in the text. Lines of spans start at 1, columns count characters.
+fn byte_range(text: &str, item: &Item) -> Option<std::ops::Range<usize>> {
+    let span = item.span();
+    let offset = |line: usize, column: usize| {
+        let start = match line {
+            0 => return None,
+            1 => 0,
+            _ => text.match_indices('\n').nth(line - 2)?.0 + 1,
+        };
+        let column = text[start..]
+            .char_indices()
+            .nth(column)
+            .map_or(text.len() - start, |(index, _)| index);
+        Some(start + column)
+    };
+    let start = offset(span.start().line, span.start().column)?;
+    let end = offset(span.end().line, span.end().column)?;
+    (start < end).then_some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+
+    use super::splice;
+    use crate::edition::Edition;
+
+    #[test]
+    fn unchanged_items_keep_their_text() {
+        let original = "// A comment that formatting would drop.\n\
+                        fn   kept( a :u8 ) {   a; }\n\
+                        \n\
+                        fn changed() {\n    1 + 1;\n}\n\
+                        \n\
+                        struct Deleted;\n\
+                        \n\
+                        const  ALSO_KEPT : u8 = 1;\n";
+        let new = syn::parse_str::<syn::File>(
+            "fn kept(a: u8) { a; } fn changed() { loop {} } const ALSO_KEPT: u8 = 1;",
+        )
+        .unwrap();
+
+        let spliced = splice(original, &new, Edition::E2021).unwrap();
+        assert!(
+            spliced.starts_with(
+                "// A comment that formatting would drop.\n\
+                 fn   kept( a :u8 ) {   a; }\n\
+                 \n\
+                 fn changed() {"
+            ),
+            "{spliced}"
+        );
+        assert!(
+            spliced.ends_with("}\n\nconst  ALSO_KEPT : u8 = 1;\n"),
+            "{spliced}"
+        );
+        assert!(!spliced.contains("Deleted"), "{spliced}");
+        assert_eq!(
+            syn::parse_file(&spliced)
+                .unwrap()
+                .into_token_stream()
+                .to_string(),
+            new.into_token_stream().to_string()
+        );
+    }
+}
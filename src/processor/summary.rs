@@ -0,0 +1,144 @@
+//! The statistics that are shown at the end of a run.
+
+use std::{collections::BTreeMap, fmt::Display, path::PathBuf, time::Duration};
+
+use syn::Item;
+
+/// The size of a file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Size {
+    pub(crate) lines: usize,
+    pub(crate) bytes: usize,
+    /// Items in inline modules are counted too.
+    pub(crate) items: usize,
+}
+
+impl Size {
+    pub(crate) fn of(content: &str, file: &syn::File) -> Self {
+        fn count_items(items: &[Item]) -> usize {
+            items
+                .iter()
+                .map(|item| match item {
+                    Item::Mod(syn::ItemMod {
+                        content: Some((_, items)),
+                        ..
+                    }) => 1 + count_items(items),
+                    _ => 1,
+                })
+                .sum()
+        }
+
+        Self {
+            lines: content.lines().count(),
+            bytes: content.len(),
+            items: count_items(&file.items),
+        }
+    }
+}
+
+impl std::ops::Add for Size {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            lines: self.lines + other.lines,
+            bytes: self.bytes + other.bytes,
+            items: self.items + other.items,
+        }
+    }
+}
+
+/// How long a pass took.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PassTiming {
+    /// The whole time, including writing files.
+    pub(crate) total: Duration,
+    /// The time that applying the pass to the code took.
+    pub(crate) transform: Duration,
+    /// The time spent building to check the changes.
+    pub(crate) build: Duration,
+}
+
+#[derive(Debug)]
+pub(crate) struct FileSummary {
+    pub(crate) path: PathBuf,
+    pub(crate) before: Size,
+    pub(crate) after: Size,
+}
+
+#[derive(Debug)]
+pub(crate) struct Summary {
+    pub(crate) files: Vec<FileSummary>,
+    /// The lines that every pass removed with the changes it committed.
+    pub(crate) removed_lines: BTreeMap<&'static str, isize>,
+    /// How long every pass took, in the order the passes ran.
+    pub(crate) timings: Vec<(&'static str, PassTiming)>,
+}
+
+impl Summary {
+    pub(crate) fn total(&self) -> (Size, Size) {
+        self.files.iter().fold(
+            (Size::default(), Size::default()),
+            |(before, after), file| (before + file.before, after + file.after),
+        )
+    }
+
+    /// The pass that removed the most lines.
+    pub(crate) fn most_effective_pass(&self) -> Option<(&'static str, isize)> {
+        self.removed_lines
+            .iter()
+            .map(|(&pass, &lines)| (pass, lines))
+            .max_by_key(|&(_, lines)| lines)
+    }
+}
+
+fn write_sizes(f: &mut std::fmt::Formatter<'_>, before: Size, after: Size) -> std::fmt::Result {
+    write!(
+        f,
+        "{} -> {} lines, {} -> {} bytes, {} -> {} items",
+        before.lines, after.lines, before.bytes, after.bytes, before.items, after.items
+    )
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Minimization summary:")?;
+        for file in &self.files {
+            write!(f, "  {}: ", file.path.display())?;
+            write_sizes(f, file.before, file.after)?;
+            writeln!(f)?;
+        }
+
+        let (before, after) = self.total();
+        write!(f, "  Total: ")?;
+        write_sizes(f, before, after)?;
+
+        if let Some((pass, lines)) = self.most_effective_pass() {
+            write!(f, "\n  Most lines were removed by {pass}: {lines}")?;
+        }
+
+        if !self.timings.is_empty() {
+            let width = self
+                .timings
+                .iter()
+                .map(|(pass, _)| pass.len())
+                .max()
+                .unwrap_or_default();
+            write!(
+                f,
+                "\n  Time per pass:\n    {:width$}  {:>10}  {:>10}  {:>10}",
+                "pass", "total", "transform", "build"
+            )?;
+            for (pass, timing) in &self.timings {
+                write!(
+                    f,
+                    "\n    {pass:width$}  {:>10}  {:>10}  {:>10}",
+                    format!("{:.2?}", timing.total),
+                    format!("{:.2?}", timing.transform),
+                    format!("{:.2?}", timing.build),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Checks the result after all passes ran. A pass that leaves the code in a broken state should fail loudly
+//! here instead of handing the user a reproduction that doesn't work.
+
+use anyhow::{Context, Result};
+
+use super::Minimizer;
+
+impl Minimizer {
+    /// Checks that every file still parses and can be formatted, and that the issue still reproduces.
+    pub(crate) fn validate(&self) -> Result<()> {
+        for file in self.files.iter().filter(|file| !file.is_deleted()) {
+            let krate = file
+                .edition()
+                .parse_file(&file.content_str())
+                .with_context(|| format!("Validation failed: {file:?} doesn't parse anymore"))?;
+            crate::formatting::format(krate).with_context(|| {
+                format!("Validation failed: {file:?} can't be formatted anymore")
+            })?;
+        }
+
+        self.build
+            .build(&self.files)?
+            .require_reproduction("Final")
+            .context("Validation failed: the minimized code doesn't reproduce the issue anymore")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use anyhow::Result;
+
+    use crate::{build::Build, processor::Minimizer, Options};
+
+    #[test]
+    #[cfg(unix)]
+    fn catches_broken_final_state() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("main.rs");
+        let script = dir.path().join("script.sh");
+        fs::write(&script, "#!/bin/sh\ngrep -q needed main.rs\n")?;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+        fs::write(&file, "fn main() {\n    \"needed\";\n}\n")?;
+
+        let options = Options {
+            path: file,
+            project_dir: Some(dir.path().to_owned()),
+            script_path: Some(script),
+            ..Options::default()
+        };
+        let build = Build::new(&options)?;
+        let minimizer = Minimizer::new_glob_dir(options, build, Arc::new(AtomicBool::new(false)))?;
+        minimizer.validate()?;
+
+        let source = &minimizer.files[0];
+        let validate = |content: &str| {
+            source.restore(content, &syn::parse_str("fn main() {}").unwrap())?;
+            Ok::<_, anyhow::Error>(format!("{:#}", minimizer.validate().unwrap_err()))
+        };
+
+        let err = validate("fn main( {\n")?;
+        assert!(err.contains("doesn't parse anymore"), "{err}");
+
+        let err = validate("fn main() {}\n")?;
+        assert!(err.contains("doesn't reproduce the issue anymore"), "{err}");
+        assert!(
+            err.contains("Final build must reproduce the issue"),
+            "{err}"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+//! Finds the member crates of a Cargo workspace and the code of a package that runs at build time.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+/// Returns the directories of all members of the workspace with the manifest in `root`.
+pub(crate) fn member_dirs(root: &Path) -> Result<Vec<PathBuf>> {
+    let path = root.join("Cargo.toml");
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let manifest = toml::from_str::<toml::Value>(&content)
+        .with_context(|| format!("parsing {}", path.display()))?;
+
+    let Some(workspace) = manifest.get("workspace") else {
+        bail!("{} does not contain a [workspace]", path.display());
+    };
+
+    let patterns = |key| {
+        workspace
+            .get(key)
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .collect::<Vec<_>>()
+    };
+    let excluded = patterns("exclude")
+        .into_iter()
+        .map(|exclude| root.join(exclude))
+        .collect::<Vec<_>>();
+
+    let mut members = Vec::new();
+    // The root manifest can be a package too.
+    if manifest.get("package").is_some() {
+        members.push(root.to_owned());
+    }
+    for pattern in patterns("members") {
+        for dir in expand_glob(root, pattern)? {
+            if !members.contains(&dir)
+                && !excluded.iter().any(|excluded| dir.starts_with(excluded))
+                && dir.join("Cargo.toml").exists()
+            {
+                members.push(dir);
+            }
+        }
+    }
+
+    Ok(members)
+}
+
+/// The targets of a package that run while it is being built.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct BuildTimeTargets {
+    /// The `build.rs` or the script from `package.build`, if it exists.
+    pub(crate) build_script: Option<PathBuf>,
+    /// The library is a proc-macro crate, so all of its code runs in the compiler.
+    pub(crate) proc_macro: bool,
+}
+
+/// Reads the build-time targets from the manifest in `dir`. A directory without a manifest has none.
+pub(crate) fn build_time_targets(dir: &Path) -> Result<BuildTimeTargets> {
+    let path = dir.join("Cargo.toml");
+    if !path.exists() {
+        return Ok(BuildTimeTargets::default());
+    }
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let manifest = toml::from_str::<toml::Value>(&content)
+        .with_context(|| format!("parsing {}", path.display()))?;
+
+    let build_script = match manifest
+        .get("package")
+        .and_then(|package| package.get("build"))
+    {
+        Some(toml::Value::String(script)) => Some(dir.join(script)),
+        Some(toml::Value::Boolean(false)) => None,
+        _ => Some(dir.join("build.rs")),
+    };
+    let proc_macro = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("proc-macro").or_else(|| lib.get("proc_macro")))
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(false);
+
+    Ok(BuildTimeTargets {
+        build_script: build_script.filter(|script| script.is_file()),
+        proc_macro,
+    })
+}
+
+/// Expands a member pattern like `crates/*`. Only `*` and `?` are supported.
+fn expand_glob(root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![root.to_owned()];
+
+    for component in Path::new(pattern).components() {
+        let component = component.as_os_str().to_string_lossy();
+        if !component.contains(['*', '?']) {
+            dirs = dirs.into_iter().map(|dir| dir.join(&*component)).collect();
+            continue;
+        }
+
+        let mut matching = Vec::new();
+        for dir in dirs {
+            let entries = std::fs::read_dir(&dir)
+                .with_context(|| format!("reading directory {}", dir.display()))?;
+            for entry in entries {
+                let entry = entry?;
+                if entry.file_type()?.is_dir()
+                    && matches_wildcard(&component, &entry.file_name().to_string_lossy())
+                {
+                    matching.push(entry.path());
+                }
+            }
+        }
+        matching.sort();
+        dirs = matching;
+    }
+
+    Ok(dirs)
+}
+
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    match pattern.chars().next() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len())
+            .filter(|&i| name.is_char_boundary(i))
+            .any(|i| matches_wildcard(&pattern[1..], &name[i..])),
+        Some(c) => {
+            let mut name_chars = name.chars();
+            match name_chars.next() {
+                Some(n) if c == '?' || c == n => {
+                    matches_wildcard(&pattern[c.len_utf8()..], name_chars.as_str())
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{build_time_targets, member_dirs, BuildTimeTargets};
+
+    #[test]
+    fn finds_members() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\", \"crates/*\"]\nexclude = [\"crates/excluded\"]\n",
+        )?;
+        for member in ["app", "crates/a", "crates/b", "crates/excluded"] {
+            fs::create_dir_all(root.join(member))?;
+            fs::write(root.join(member).join("Cargo.toml"), "[package]")?;
+        }
+        fs::create_dir_all(root.join("crates/not-a-crate"))?;
+
+        assert_eq!(
+            member_dirs(root)?,
+            [
+                root.join("app"),
+                root.join("crates/a"),
+                root.join("crates/b"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_build_time_targets() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path();
+        assert_eq!(build_time_targets(root)?, BuildTimeTargets::default());
+
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\n[lib]\nproc-macro = true\n",
+        )?;
+        assert_eq!(
+            build_time_targets(root)?,
+            BuildTimeTargets {
+                build_script: None,
+                proc_macro: true,
+            }
+        );
+
+        fs::write(root.join("build.rs"), "fn main() {}")?;
+        assert_eq!(
+            build_time_targets(root)?.build_script,
+            Some(root.join("build.rs"))
+        );
+
+        fs::write(root.join("Cargo.toml"), "[package]\nbuild = \"gen.rs\"\n")?;
+        fs::write(root.join("gen.rs"), "fn main() {}")?;
+        assert_eq!(
+            build_time_targets(root)?,
+            BuildTimeTargets {
+                build_script: Some(root.join("gen.rs")),
+                proc_macro: false,
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -160,6 +160,7 @@ fn build(cargo: &Path, path: &Path, regression_checker_path: &Path) -> Result<()
         .context("canonicalizing target/debug/cargo-minimize")?;
 
     let start_roots = get_roots(&proj_dir).context("getting initial MINIMIZE-ROOTs")?;
+    let args = get_args(&proj_dir).context("getting MINIMIZE-ARGS")?;
 
     let mut cmd = Command::new(cargo_minimize);
     cmd.current_dir(&proj_dir);
@@ -170,6 +171,7 @@ fn build(cargo: &Path, path: &Path, regression_checker_path: &Path) -> Result<()
         flag.push(regression_checker_path);
         flag
     });
+    cmd.args(args.iter().flat_map(|args| args.split_whitespace()));
 
     let minimize_roots = start_roots.join(",");
 
@@ -203,6 +205,12 @@ fn get_roots(path: &Path) -> Result<Vec<String>> {
     grep(path, &REGEX)
 }
 
+fn get_args(path: &Path) -> Result<Vec<String>> {
+    static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"~MINIMIZE-ARGS (.+)").unwrap());
+
+    grep(path, &REGEX)
+}
+
 fn get_required_deleted(path: &Path) -> Result<Vec<String>> {
     static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"~REQUIRE-DELETED ([\w\-_]+)").unwrap());
 
@@ -210,13 +218,15 @@ fn get_required_deleted(path: &Path) -> Result<Vec<String>> {
 }
 
 fn grep(path: &Path, regex: &Regex) -> Result<Vec<String>> {
-    let path = path.join("src");
     let mut results = Vec::new();
-    let walk = walkdir::WalkDir::new(path);
+    // Search the whole project instead of just `src`, the sources of workspaces are in the member directories.
+    let walk = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "target");
 
     for entry in walk {
         let entry = entry?;
-        if !entry.metadata()?.is_file() {
+        if !entry.metadata()?.is_file() || entry.path().extension() != Some("rs".as_ref()) {
             continue;
         }
         let src = fs::read_to_string(entry.path()).context("reading file")?;